@@ -0,0 +1,688 @@
+//! Concentrated-liquidity swap math in Q64.64 fixed point.
+//!
+//! Ported from the Orca Whirlpool program (`math/token_math.rs`,
+//! `math/swap_math.rs`, `math/tick_math.rs`) with the same rounding at
+//! every step, so quotes match what the program would execute. Raydium CLMM
+//! is a port of the same Uniswap V3 math and shares the swap loop, but has
+//! its own tick-to-price table.
+
+use anyhow::Result;
+use num_bigint::BigUint;
+use num_traits::{ToPrimitive, Zero};
+use std::collections::BTreeMap;
+
+use crate::freshness::Observation;
+use crate::price::Price;
+use crate::quoter::{price_impact_bps, PostTradeState, SwapDirection, SwapQuote, TransferFees};
+
+/// Fee rates are expressed in hundredths of a basis point
+pub const FEE_RATE_MUL_VALUE: u128 = 1_000_000;
+
+pub const MIN_SQRT_PRICE_X64: u128 = 4295048016;
+pub const MAX_SQRT_PRICE_X64: u128 = 79226673515401279992447579055;
+
+pub const MIN_TICK_INDEX: i32 = -443636;
+pub const MAX_TICK_INDEX: i32 = 443636;
+
+/// sqrt(1.0001)^(2^i) in Q32.96, for positive ticks (bit 0 handled separately)
+const POSITIVE_TICK_RATIOS: [u128; 18] = [
+    79236085330515764027303304731,
+    79244008939048815603706035061,
+    79259858533276714757314932305,
+    79291567232598584799939703904,
+    79355022692464371645785046466,
+    79482085999252804386437311141,
+    79736823300114093921829183326,
+    80248749790819932309965073892,
+    81282483887344747381513967011,
+    83390072131320151908154831281,
+    87770609709833776024991924138,
+    97234110755111693312479820773,
+    119332217159966728226237229890,
+    179736315981702064433883588727,
+    407748233172238350107850275304,
+    2098478828474011932436660412517,
+    55581415166113811149459800483533,
+    38992368544603139932233054999993551,
+];
+
+/// 1 / sqrt(1.0001)^(2^i) in Q64.64, for negative ticks (bit 0 handled separately)
+const NEGATIVE_TICK_RATIOS: [u128; 18] = [
+    18444899583751176498,
+    18443055278223354162,
+    18439367220385604838,
+    18431993317065449817,
+    18417254355718160513,
+    18387811781193591352,
+    18329067761203520168,
+    18212142134806087854,
+    17980523815641551639,
+    17526086738831147013,
+    16651378430235024244,
+    15030750278693429944,
+    12247334978882834399,
+    8131365268884726200,
+    3584323654723342297,
+    696457651847595233,
+    26294789957452057,
+    37481735321082,
+];
+
+/// Sqrt price at a tick, bit-for-bit with the program's `sqrt_price_from_tick_index`
+pub fn sqrt_price_from_tick_index(tick: i32) -> Result<u128> {
+    if !(MIN_TICK_INDEX..=MAX_TICK_INDEX).contains(&tick) {
+        anyhow::bail!("Tick {} out of bounds", tick);
+    }
+    let abs_tick = tick.unsigned_abs();
+
+    if tick >= 0 {
+        let mut ratio = BigUint::from(if abs_tick & 1 != 0 { 79232123823359799118286999567u128 } else { 1u128 << 96 });
+        for (bit, factor) in POSITIVE_TICK_RATIOS.iter().enumerate() {
+            if abs_tick & (2 << bit) != 0 {
+                ratio = (ratio * BigUint::from(*factor)) >> 96u32;
+            }
+        }
+        (ratio >> 32u32).to_u128().ok_or_else(|| anyhow::anyhow!("Sqrt price overflow"))
+    } else {
+        negative_tick_ratio(abs_tick)
+    }
+}
+
+/// 1 / sqrt(1.0001)^abs_tick in Q64.64
+fn negative_tick_ratio(abs_tick: u32) -> Result<u128> {
+    let mut ratio: u128 = if abs_tick & 1 != 0 { 18445821805675392311 } else { 1 << 64 };
+    for (bit, factor) in NEGATIVE_TICK_RATIOS.iter().enumerate() {
+        if abs_tick & (2 << bit) != 0 {
+            ratio = mul_div(ratio, *factor, 1 << 64, false)?;
+        }
+    }
+    Ok(ratio)
+}
+
+/// Greatest tick whose sqrt price is at or below `sqrt_price`
+pub fn tick_index_from_sqrt_price(sqrt_price: u128) -> Result<i32> {
+    TickMath::Whirlpool.tick_at_sqrt_price(sqrt_price)
+}
+
+/// Raydium CLMM's upper sqrt price bound; the lower bound matches Whirlpool's
+pub const RAYDIUM_MAX_SQRT_PRICE_X64: u128 = 79226673521066979257578248091;
+
+/// 1 / sqrt(1.0001)^(2^i) in Q64.64 as tabulated by Raydium's
+/// `get_sqrt_price_at_tick`. The low bits are rounded differently from
+/// Whirlpool's table, so the two programs disagree on most tick prices.
+const RAYDIUM_TICK_RATIOS: [u128; 19] = [
+    0xfffcb933bd6fb800,
+    0xfff97272373d4000,
+    0xfff2e50f5f657000,
+    0xffe5caca7e10f000,
+    0xffcb9843d60f7000,
+    0xff973b41fa98e800,
+    0xff2ea16466c9b000,
+    0xfe5dee046a9a3800,
+    0xfcbe86c7900bb000,
+    0xf987a7253ac65800,
+    0xf3392b0822bb6000,
+    0xe7159475a2caf000,
+    0xd097f3bdfd2f2000,
+    0xa9f746462d9f8000,
+    0x70d869a156f31c00,
+    0x31be135f97ed3200,
+    0x9aa508b5b85a500,
+    0x5d6af8dedc582c,
+    0x2216e584f5fa,
+];
+
+/// Sqrt price at a tick, bit-for-bit with Raydium's `get_sqrt_price_at_tick`:
+/// the negative-tick ratio, inverted for positive ticks
+fn raydium_sqrt_price_at_tick(tick: i32) -> Result<u128> {
+    if !(MIN_TICK_INDEX..=MAX_TICK_INDEX).contains(&tick) {
+        anyhow::bail!("Tick {} out of bounds", tick);
+    }
+    let abs_tick = tick.unsigned_abs();
+    let mut ratio: u128 = 1 << 64;
+    for (bit, factor) in RAYDIUM_TICK_RATIOS.iter().enumerate() {
+        if abs_tick & (1 << bit) != 0 {
+            ratio = (ratio * factor) >> 64;
+        }
+    }
+    Ok(if tick > 0 { u128::MAX / ratio } else { ratio })
+}
+
+/// Tick-to-price conversion of each CLMM program. Both use 1.0001 ticks
+/// but with different precomputed tables, and Raydium derives positive
+/// ticks by inverting the negative-tick ratio, so results differ in the
+/// low bits on both sides of tick 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TickMath {
+    Whirlpool,
+    Raydium,
+}
+
+impl TickMath {
+    pub fn max_sqrt_price(self) -> u128 {
+        match self {
+            TickMath::Whirlpool => MAX_SQRT_PRICE_X64,
+            TickMath::Raydium => RAYDIUM_MAX_SQRT_PRICE_X64,
+        }
+    }
+
+    pub fn sqrt_price_at_tick(self, tick: i32) -> Result<u128> {
+        match self {
+            TickMath::Whirlpool => sqrt_price_from_tick_index(tick),
+            TickMath::Raydium => raydium_sqrt_price_at_tick(tick),
+        }
+    }
+
+    /// Greatest tick whose sqrt price is at or below `sqrt_price`
+    pub fn tick_at_sqrt_price(self, sqrt_price: u128) -> Result<i32> {
+        if !(MIN_SQRT_PRICE_X64..=self.max_sqrt_price()).contains(&sqrt_price) {
+            anyhow::bail!("Sqrt price {} out of bounds", sqrt_price);
+        }
+
+        // Float estimate, then step onto the exact tick
+        let price = (sqrt_price as f64 / (1u128 << 64) as f64).powi(2);
+        let mut tick = (price.ln() / 1.0001f64.ln()).floor() as i32;
+        tick = tick.clamp(MIN_TICK_INDEX, MAX_TICK_INDEX);
+        while tick > MIN_TICK_INDEX && self.sqrt_price_at_tick(tick)? > sqrt_price {
+            tick -= 1;
+        }
+        while tick < MAX_TICK_INDEX && self.sqrt_price_at_tick(tick + 1)? <= sqrt_price {
+            tick += 1;
+        }
+        Ok(tick)
+    }
+}
+
+/// `a * b / c`, rounding up when `round_up` is set
+pub fn mul_div(a: u128, b: u128, c: u128, round_up: bool) -> Result<u128> {
+    if c == 0 {
+        anyhow::bail!("Division by zero");
+    }
+    let product = BigUint::from(a) * BigUint::from(b);
+    let divisor = BigUint::from(c);
+    let mut quotient = &product / &divisor;
+    if round_up && !(product % divisor).is_zero() {
+        quotient += 1u32;
+    }
+    quotient.to_u128().ok_or_else(|| anyhow::anyhow!("mul_div overflow"))
+}
+
+fn ordered(sqrt_price_0: u128, sqrt_price_1: u128) -> (u128, u128) {
+    if sqrt_price_0 > sqrt_price_1 {
+        (sqrt_price_1, sqrt_price_0)
+    } else {
+        (sqrt_price_0, sqrt_price_1)
+    }
+}
+
+/// Token A needed (or released) to move between two sqrt prices.
+/// `None` if the amount does not fit in a u64.
+pub fn get_amount_delta_a(sqrt_price_0: u128, sqrt_price_1: u128, liquidity: u128, round_up: bool) -> Option<u64> {
+    let (lower, upper) = ordered(sqrt_price_0, sqrt_price_1);
+    if lower == 0 {
+        return None;
+    }
+
+    let numerator = (BigUint::from(liquidity) * BigUint::from(upper - lower)) << 64u32;
+    let denominator = BigUint::from(upper) * BigUint::from(lower);
+    let mut quotient = &numerator / &denominator;
+    if round_up && !(numerator % denominator).is_zero() {
+        quotient += 1u32;
+    }
+    quotient.to_u64()
+}
+
+/// Token B needed (or released) to move between two sqrt prices.
+/// `None` if the amount does not fit in a u64.
+pub fn get_amount_delta_b(sqrt_price_0: u128, sqrt_price_1: u128, liquidity: u128, round_up: bool) -> Option<u64> {
+    let (lower, upper) = ordered(sqrt_price_0, sqrt_price_1);
+
+    let product = BigUint::from(liquidity) * BigUint::from(upper - lower);
+    let mut result = &product >> 64u32;
+    if round_up && !(product & BigUint::from(u64::MAX)).is_zero() {
+        result += 1u32;
+    }
+    result.to_u64()
+}
+
+fn get_next_sqrt_price_from_a_round_up(
+    sqrt_price: u128,
+    liquidity: u128,
+    amount: u64,
+    amount_specified_is_input: bool,
+) -> Result<u128> {
+    if amount == 0 {
+        return Ok(sqrt_price);
+    }
+
+    let product = BigUint::from(sqrt_price) * BigUint::from(amount);
+    let numerator = (BigUint::from(liquidity) * BigUint::from(sqrt_price)) << 64u32;
+    let liquidity_shifted = BigUint::from(liquidity) << 64u32;
+
+    let denominator = if amount_specified_is_input {
+        liquidity_shifted + product
+    } else {
+        if product >= liquidity_shifted {
+            anyhow::bail!("Insufficient liquidity for output amount");
+        }
+        liquidity_shifted - product
+    };
+
+    let mut price = &numerator / &denominator;
+    if !(numerator % denominator).is_zero() {
+        price += 1u32;
+    }
+    let price = price.to_u128().ok_or_else(|| anyhow::anyhow!("Sqrt price overflow"))?;
+    check_sqrt_price_bounds(price)
+}
+
+fn get_next_sqrt_price_from_b_round_down(
+    sqrt_price: u128,
+    liquidity: u128,
+    amount: u64,
+    amount_specified_is_input: bool,
+) -> Result<u128> {
+    if liquidity == 0 {
+        anyhow::bail!("Zero liquidity");
+    }
+
+    let amount_x64 = (amount as u128) << 64;
+    let mut delta = amount_x64 / liquidity;
+    if !amount_specified_is_input && !amount_x64.is_multiple_of(liquidity) {
+        delta += 1;
+    }
+
+    let price = if amount_specified_is_input {
+        sqrt_price.checked_add(delta)
+    } else {
+        sqrt_price.checked_sub(delta)
+    }
+    .ok_or_else(|| anyhow::anyhow!("Sqrt price out of range"))?;
+    check_sqrt_price_bounds(price)
+}
+
+fn check_sqrt_price_bounds(sqrt_price: u128) -> Result<u128> {
+    if !(MIN_SQRT_PRICE_X64..=MAX_SQRT_PRICE_X64).contains(&sqrt_price) {
+        anyhow::bail!("Sqrt price {} out of bounds", sqrt_price);
+    }
+    Ok(sqrt_price)
+}
+
+/// Sqrt price after trading `amount` of the specified token
+pub fn get_next_sqrt_price(
+    sqrt_price: u128,
+    liquidity: u128,
+    amount: u64,
+    amount_specified_is_input: bool,
+    a_to_b: bool,
+) -> Result<u128> {
+    if amount_specified_is_input == a_to_b {
+        get_next_sqrt_price_from_a_round_up(sqrt_price, liquidity, amount, amount_specified_is_input)
+    } else {
+        get_next_sqrt_price_from_b_round_down(sqrt_price, liquidity, amount, amount_specified_is_input)
+    }
+}
+
+/// Delta of the token whose amount was specified by the caller
+fn get_amount_fixed_delta(
+    sqrt_price_current: u128,
+    sqrt_price_target: u128,
+    liquidity: u128,
+    amount_specified_is_input: bool,
+    a_to_b: bool,
+) -> Option<u64> {
+    if a_to_b == amount_specified_is_input {
+        get_amount_delta_a(sqrt_price_current, sqrt_price_target, liquidity, amount_specified_is_input)
+    } else {
+        get_amount_delta_b(sqrt_price_current, sqrt_price_target, liquidity, amount_specified_is_input)
+    }
+}
+
+/// Delta of the token the swap solves for
+fn get_amount_unfixed_delta(
+    sqrt_price_current: u128,
+    sqrt_price_target: u128,
+    liquidity: u128,
+    amount_specified_is_input: bool,
+    a_to_b: bool,
+) -> Result<u64> {
+    if a_to_b == amount_specified_is_input {
+        get_amount_delta_b(sqrt_price_current, sqrt_price_target, liquidity, !amount_specified_is_input)
+    } else {
+        get_amount_delta_a(sqrt_price_current, sqrt_price_target, liquidity, !amount_specified_is_input)
+    }
+    .ok_or_else(|| anyhow::anyhow!("Swap amount overflow"))
+}
+
+/// Result of swapping within a single liquidity range
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SwapStep {
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee_amount: u64,
+    pub next_sqrt_price: u128,
+}
+
+/// Swap as far as `sqrt_price_target` with constant `liquidity`,
+/// consuming at most `amount_remaining` of the specified token
+pub fn compute_swap_step(
+    amount_remaining: u64,
+    fee_rate: u32,
+    liquidity: u128,
+    sqrt_price_current: u128,
+    sqrt_price_target: u128,
+    amount_specified_is_input: bool,
+    a_to_b: bool,
+) -> Result<SwapStep> {
+    let fee_rate = fee_rate as u128;
+    let initial_fixed_delta = get_amount_fixed_delta(
+        sqrt_price_current,
+        sqrt_price_target,
+        liquidity,
+        amount_specified_is_input,
+        a_to_b,
+    );
+
+    let amount_calc = if amount_specified_is_input {
+        mul_div(amount_remaining as u128, FEE_RATE_MUL_VALUE - fee_rate, FEE_RATE_MUL_VALUE, false)? as u64
+    } else {
+        amount_remaining
+    };
+
+    let next_sqrt_price = match initial_fixed_delta {
+        Some(delta) if delta <= amount_calc => sqrt_price_target,
+        _ => get_next_sqrt_price(sqrt_price_current, liquidity, amount_calc, amount_specified_is_input, a_to_b)?,
+    };
+    let is_max_swap = next_sqrt_price == sqrt_price_target;
+
+    let amount_unfixed_delta = get_amount_unfixed_delta(
+        sqrt_price_current,
+        next_sqrt_price,
+        liquidity,
+        amount_specified_is_input,
+        a_to_b,
+    )?;
+
+    // If the swap does not reach the target, recompute the fixed amount at the new price
+    let amount_fixed_delta = match initial_fixed_delta {
+        Some(delta) if is_max_swap => delta,
+        _ => get_amount_fixed_delta(sqrt_price_current, next_sqrt_price, liquidity, amount_specified_is_input, a_to_b)
+            .ok_or_else(|| anyhow::anyhow!("Swap amount overflow"))?,
+    };
+
+    let (amount_in, mut amount_out) = if amount_specified_is_input {
+        (amount_fixed_delta, amount_unfixed_delta)
+    } else {
+        (amount_unfixed_delta, amount_fixed_delta)
+    };
+
+    if !amount_specified_is_input && amount_out > amount_remaining {
+        amount_out = amount_remaining;
+    }
+
+    let fee_amount = if amount_specified_is_input && !is_max_swap {
+        amount_remaining - amount_in
+    } else {
+        mul_div(amount_in as u128, fee_rate, FEE_RATE_MUL_VALUE - fee_rate, true)? as u64
+    };
+
+    Ok(SwapStep {
+        amount_in,
+        amount_out,
+        fee_amount,
+        next_sqrt_price,
+    })
+}
+
+/// Initialized ticks across a contiguous run of tick arrays
+#[derive(Debug, Clone, Default)]
+pub struct TickWindow {
+    /// First tick covered by the window
+    lower: i32,
+    /// One past the last tick covered by the window
+    upper: i32,
+    /// `liquidity_net` of every initialized tick in the window
+    liquidity_net: BTreeMap<i32, i128>,
+}
+
+impl TickWindow {
+    /// Window covering `[lower, upper)` with the given initialized
+    /// `(tick_index, liquidity_net)` pairs
+    pub fn new(lower: i32, upper: i32, initialized: impl IntoIterator<Item = (i32, i128)>) -> Self {
+        Self {
+            lower,
+            upper,
+            liquidity_net: initialized.into_iter().collect(),
+        }
+    }
+
+    /// Next initialized tick at or below `tick_index` (price moving down)
+    fn next_initialized_at_or_below(&self, tick_index: i32) -> Option<(i32, i128)> {
+        self.liquidity_net
+            .range(self.lower..=tick_index)
+            .next_back()
+            .map(|(index, net)| (*index, *net))
+    }
+
+    /// Next initialized tick above `tick_index` (price moving up)
+    fn next_initialized_above(&self, tick_index: i32) -> Option<(i32, i128)> {
+        self.liquidity_net
+            .range(tick_index + 1..self.upper)
+            .next()
+            .map(|(index, net)| (*index, *net))
+    }
+}
+
+/// The parts of a concentrated-liquidity pool a swap reads and updates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClmmState {
+    pub sqrt_price: u128,
+    pub tick_current_index: i32,
+    pub liquidity: u128,
+    /// Fee rate in hundredths of a basis point
+    pub fee_rate: u32,
+}
+
+/// Simulate a swap the way the Whirlpool and Raydium CLMM swap loops
+/// execute it, stepping between initialized ticks and updating liquidity
+/// at each cross. A is token 0 / token A, so `AToB` moves the price down.
+///
+/// Fails if the swap would leave the tick window or exhaust all liquidity.
+pub fn simulate_swap(
+    pool: &ClmmState,
+    ticks: &TickWindow,
+    tick_math: TickMath,
+    amount: u64,
+    amount_specified_is_input: bool,
+    direction: SwapDirection,
+) -> Result<SwapQuote> {
+    let a_to_b = direction == SwapDirection::AToB;
+    // Bounded by the tighter Whirlpool limits, which `compute_swap_step` checks against
+    let sqrt_price_limit = if a_to_b { MIN_SQRT_PRICE_X64 } else { MAX_SQRT_PRICE_X64 };
+
+    let mut amount_remaining = amount;
+    let mut amount_calculated: u64 = 0;
+    let mut fee_total: u64 = 0;
+    let mut sqrt_price = pool.sqrt_price;
+    let mut tick_current_index = pool.tick_current_index;
+    let mut liquidity = pool.liquidity;
+
+    while amount_remaining > 0 && sqrt_price != sqrt_price_limit {
+        let next_tick = if a_to_b {
+            ticks.next_initialized_at_or_below(tick_current_index)
+        } else {
+            ticks.next_initialized_above(tick_current_index)
+        };
+        let boundary_index = match next_tick {
+            Some((index, _)) => index,
+            None if a_to_b => ticks.lower,
+            None => ticks.upper,
+        }
+        .clamp(MIN_TICK_INDEX, MAX_TICK_INDEX);
+        let boundary_sqrt_price = tick_math.sqrt_price_at_tick(boundary_index)?;
+        let target_sqrt_price = if a_to_b {
+            boundary_sqrt_price.max(sqrt_price_limit)
+        } else {
+            boundary_sqrt_price.min(sqrt_price_limit)
+        };
+
+        let step = compute_swap_step(
+            amount_remaining,
+            pool.fee_rate,
+            liquidity,
+            sqrt_price,
+            target_sqrt_price,
+            amount_specified_is_input,
+            a_to_b,
+        )?;
+
+        let step_in = step.amount_in + step.fee_amount;
+        if amount_specified_is_input {
+            amount_remaining -= step_in;
+            amount_calculated += step.amount_out;
+        } else {
+            amount_remaining -= step.amount_out;
+            amount_calculated += step_in;
+        }
+        fee_total += step.fee_amount;
+
+        if step.next_sqrt_price == boundary_sqrt_price {
+            match next_tick {
+                Some((_, liquidity_net)) => {
+                    let delta = if a_to_b { -liquidity_net } else { liquidity_net };
+                    liquidity = liquidity
+                        .checked_add_signed(delta)
+                        .ok_or_else(|| anyhow::anyhow!("Liquidity underflow crossing tick {}", boundary_index))?;
+                }
+                None if amount_remaining > 0 => {
+                    anyhow::bail!("Swap crosses beyond the fetched tick arrays at tick {}", boundary_index);
+                }
+                None => {}
+            }
+            tick_current_index = if a_to_b { boundary_index - 1 } else { boundary_index };
+        } else if step.next_sqrt_price != sqrt_price {
+            tick_current_index = tick_math.tick_at_sqrt_price(step.next_sqrt_price)?;
+        }
+        sqrt_price = step.next_sqrt_price;
+    }
+
+    if amount_remaining > 0 {
+        anyhow::bail!("Insufficient liquidity: {} of {} left unfilled", amount_remaining, amount);
+    }
+
+    let (amount_in, amount_out) = if amount_specified_is_input {
+        (amount, amount_calculated)
+    } else {
+        (amount_calculated, amount)
+    };
+    let price = Price::from_sqrt_price_x64(pool.sqrt_price)?;
+    let mid_out_per_in = if a_to_b { price } else { price.invert()? };
+
+    Ok(SwapQuote {
+        amount_in,
+        amount_out,
+        fee_amount: fee_total,
+        price_impact_bps: price_impact_bps(mid_out_per_in, amount_in - fee_total, amount_out)?,
+        post_state: PostTradeState::Concentrated {
+            sqrt_price_x64: sqrt_price,
+            tick_current_index,
+            liquidity,
+        },
+        transfer_fees: TransferFees::default(),
+        observed: Observation::default(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const Q64: u128 = 1 << 64;
+
+    #[test]
+    fn test_amount_deltas_round_in_protocol_favor() {
+        // Price 1.0 -> 1.21 (sqrt 1.0 -> 1.1) with L = 1e9
+        let lower = Q64;
+        let upper = Q64 + Q64 / 10;
+        let liquidity = 1_000_000_000;
+
+        let b_down = get_amount_delta_b(lower, upper, liquidity, false).unwrap();
+        let b_up = get_amount_delta_b(lower, upper, liquidity, true).unwrap();
+        assert_eq!(b_down, 99_999_999);
+        assert_eq!(b_up, 100_000_000);
+
+        let a_down = get_amount_delta_a(lower, upper, liquidity, false).unwrap();
+        let a_up = get_amount_delta_a(lower, upper, liquidity, true).unwrap();
+        assert_eq!(a_down, 90_909_090);
+        assert_eq!(a_up, 90_909_091);
+    }
+
+    #[test]
+    fn test_tick_math_bounds_and_round_trip() {
+        assert_eq!(sqrt_price_from_tick_index(MIN_TICK_INDEX).unwrap(), MIN_SQRT_PRICE_X64);
+        assert_eq!(sqrt_price_from_tick_index(MAX_TICK_INDEX).unwrap(), MAX_SQRT_PRICE_X64);
+        assert_eq!(sqrt_price_from_tick_index(0).unwrap(), Q64);
+
+        for tick in [-443_636, -100_000, -1, 0, 1, 64, -64, 12_345, 443_635] {
+            let sqrt_price = sqrt_price_from_tick_index(tick).unwrap();
+            assert_eq!(tick_index_from_sqrt_price(sqrt_price).unwrap(), tick);
+            if tick < MAX_TICK_INDEX {
+                assert_eq!(tick_index_from_sqrt_price(sqrt_price + 1).unwrap(), tick);
+            }
+        }
+        assert!(sqrt_price_from_tick_index(MAX_TICK_INDEX + 1).is_err());
+    }
+
+    #[test]
+    fn test_raydium_tick_math() {
+        let raydium = TickMath::Raydium;
+        assert_eq!(raydium.sqrt_price_at_tick(MIN_TICK_INDEX).unwrap(), MIN_SQRT_PRICE_X64);
+        assert_eq!(raydium.sqrt_price_at_tick(MAX_TICK_INDEX).unwrap(), RAYDIUM_MAX_SQRT_PRICE_X64);
+        assert_eq!(raydium.sqrt_price_at_tick(0).unwrap(), Q64);
+
+        // Evaluated from Raydium's table in arbitrary precision
+        let expected: [(i32, u128); 12] = [
+            (-300_000, 5_647_135_299_345),
+            (-100_000, 124_324_258_983_086_206),
+            (-12_345, 9_950_957_148_633_381_772),
+            (-1_000, 17_547_129_613_991_882_732),
+            (-64, 18_387_811_781_193_609_216),
+            (-1, 18_445_821_805_675_395_072),
+            (1, 18_447_666_387_855_957_090),
+            (64, 18_505_865_242_158_232_063),
+            (1_000, 19_392_480_388_906_522_465),
+            (12_345, 34_195_943_348_793_463_849),
+            (100_000, 2_737_055_259_402_209_284_734),
+            (300_000, 60_257_519_765_890_351_327_341_632),
+        ];
+        for (tick, sqrt_price) in expected {
+            assert_eq!(raydium.sqrt_price_at_tick(tick).unwrap(), sqrt_price, "tick {}", tick);
+            assert_eq!(raydium.tick_at_sqrt_price(sqrt_price).unwrap(), tick);
+            assert_eq!(raydium.tick_at_sqrt_price(sqrt_price - 1).unwrap(), tick - 1);
+        }
+
+        // Whirlpool's more precise table gives a different price one tick down
+        assert_eq!(sqrt_price_from_tick_index(-1).unwrap(), 18_445_821_805_675_392_311);
+        assert!(raydium.sqrt_price_at_tick(MAX_TICK_INDEX + 1).is_err());
+    }
+
+    #[test]
+    fn test_swap_step_exact_in_partial() {
+        // 0.3% fee, swap 1_000 of A into a deep pool at price 1.0
+        let step = compute_swap_step(1_000, 3_000, 1_000_000_000_000, Q64, MIN_SQRT_PRICE_X64, true, true).unwrap();
+        assert_eq!(step.amount_in + step.fee_amount, 1_000);
+        assert_eq!(step.fee_amount, 3);
+        assert_eq!(step.amount_out, 996);
+        assert!(step.next_sqrt_price < Q64);
+    }
+
+    #[test]
+    fn test_swap_step_exact_out_reaches_target() {
+        // Target only 0.01% away: the step is capped at the target price
+        let target = Q64 + Q64 / 20_000;
+        let step = compute_swap_step(u64::MAX, 3_000, 1_000_000_000, Q64, target, false, false).unwrap();
+        assert_eq!(step.next_sqrt_price, target);
+        assert_eq!(step.amount_out, get_amount_delta_a(Q64, target, 1_000_000_000, false).unwrap());
+        assert_eq!(step.amount_in, get_amount_delta_b(Q64, target, 1_000_000_000, true).unwrap());
+    }
+}
@@ -0,0 +1,524 @@
+//! Pool discovery with `getProgramAccounts`.
+//!
+//! Every supported AMM stores its two mints at fixed offsets in the pool
+//! account, so the pools for a mint pair are found with memcmp filters on
+//! those offsets, narrowed to pool accounts by data size or discriminator.
+
+use rpc_manager::RpcBackend;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashSet;
+
+use crate::error::PriceError;
+use crate::meteora::{LB_PAIR_DISCRIMINATOR, METEORA_DLMM_PROGRAM_ID};
+use crate::meteora_amm::{self, METEORA_AMM_PROGRAM_ID};
+use crate::orca::{WHIRLPOOL_DISCRIMINATOR, WHIRLPOOL_SIZE};
+use crate::quoter::{unix_timestamp, AccountMap, DecodedPool, DexType, PoolState, QuoterDispatcher};
+use crate::raydium::{AMM_V4_STATE_SIZE, RAYDIUM_V4_PROGRAM_ID};
+use crate::raydium_clmm::{self, ClmmAmmConfig, CLMM_POOL_STATE_SIZE, RAYDIUM_CLMM_PROGRAM_ID};
+use crate::raydium_cpmm::{self, AmmConfig, CPMM_POOL_STATE_SIZE, RAYDIUM_CPMM_PROGRAM_ID};
+use crate::token::{MintInfo, MintRegistry, TokenAccount};
+
+/// `getMultipleAccounts` accepts at most this many keys per request
+pub(crate) const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+/// How to recognize one AMM's pool accounts and where they keep their mints
+#[derive(Debug, Clone, Copy)]
+struct PoolLayout {
+    dex: DexType,
+    program_id: Pubkey,
+    data_size: Option<u64>,
+    discriminator: Option<[u8; 8]>,
+    mint_a_offset: usize,
+    mint_b_offset: usize,
+    /// The program requires mint A < mint B, so a pair has one ordering
+    sorted_mints: bool,
+}
+
+/// Offsets are into the raw account data, discriminator included
+const POOL_LAYOUTS: [PoolLayout; 6] = [
+    PoolLayout {
+        dex: DexType::RaydiumV4,
+        program_id: RAYDIUM_V4_PROGRAM_ID,
+        data_size: Some(AMM_V4_STATE_SIZE as u64),
+        discriminator: None,
+        mint_a_offset: 400,
+        mint_b_offset: 432,
+        sorted_mints: false,
+    },
+    PoolLayout {
+        dex: DexType::RaydiumCPMM,
+        program_id: RAYDIUM_CPMM_PROGRAM_ID,
+        data_size: Some(CPMM_POOL_STATE_SIZE as u64),
+        discriminator: Some(raydium_cpmm::POOL_STATE_DISCRIMINATOR),
+        mint_a_offset: 168,
+        mint_b_offset: 200,
+        sorted_mints: true,
+    },
+    PoolLayout {
+        dex: DexType::RaydiumCLMM,
+        program_id: RAYDIUM_CLMM_PROGRAM_ID,
+        data_size: Some(CLMM_POOL_STATE_SIZE as u64),
+        discriminator: Some(raydium_clmm::POOL_STATE_DISCRIMINATOR),
+        mint_a_offset: 73,
+        mint_b_offset: 105,
+        sorted_mints: true,
+    },
+    PoolLayout {
+        dex: DexType::OrcaWhirlpool,
+        program_id: orca_whirlpools_client::WHIRLPOOL_ID,
+        data_size: Some(WHIRLPOOL_SIZE as u64),
+        discriminator: Some(WHIRLPOOL_DISCRIMINATOR),
+        mint_a_offset: 101,
+        mint_b_offset: 181,
+        sorted_mints: true,
+    },
+    PoolLayout {
+        dex: DexType::MeteoraDLMM,
+        program_id: METEORA_DLMM_PROGRAM_ID,
+        data_size: None,
+        discriminator: Some(LB_PAIR_DISCRIMINATOR),
+        mint_a_offset: 88,
+        mint_b_offset: 120,
+        sorted_mints: false,
+    },
+    PoolLayout {
+        dex: DexType::MeteoraAmm,
+        program_id: METEORA_AMM_PROGRAM_ID,
+        data_size: None,
+        discriminator: Some(meteora_amm::POOL_DISCRIMINATOR),
+        mint_a_offset: 40,
+        mint_b_offset: 72,
+        sorted_mints: false,
+    },
+];
+
+impl PoolLayout {
+    /// Filters matching this AMM's pools with the given mints at the given offsets
+    fn filters(&self, mints: &[(usize, &Pubkey)]) -> Vec<RpcFilterType> {
+        let mut filters = Vec::new();
+        if let Some(size) = self.data_size {
+            filters.push(RpcFilterType::DataSize(size));
+        }
+        if let Some(discriminator) = self.discriminator {
+            filters.push(RpcFilterType::Memcmp(Memcmp::new_base58_encoded(0, &discriminator)));
+        }
+        for (offset, mint) in mints {
+            filters.push(RpcFilterType::Memcmp(Memcmp::new_base58_encoded(*offset, mint.as_ref())));
+        }
+        filters
+    }
+}
+
+/// Static description of a discovered pool. Serialized with base58 keys so
+/// registry files stay readable.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolMetadata {
+    #[serde(with = "pubkey_str")]
+    pub address: Pubkey,
+    pub dex: DexType,
+    #[serde(with = "pubkey_str")]
+    pub mint_a: Pubkey,
+    #[serde(with = "pubkey_str")]
+    pub mint_b: Pubkey,
+    /// Accounts holding the A and B reserves: token accounts, or Meteora
+    /// vaults for dynamic AMM pools. `None` for order books.
+    #[serde(with = "pubkey_pair_str")]
+    pub vaults: Option<(Pubkey, Pubkey)>,
+    /// Trade fee in parts per million. For DLMM this is the base fee only;
+    /// the volatility fee on top changes with every swap. `None` until the
+    /// fee config account of a Raydium CPMM or CLMM pool has been read.
+    pub fee_ppm: Option<u64>,
+    /// Decimals of mint A and mint B, once both mints have been read
+    pub decimals: Option<(u8, u8)>,
+}
+
+impl From<&DecodedPool> for PoolMetadata {
+    fn from(pool: &DecodedPool) -> Self {
+        let vaults = match &pool.state {
+            PoolState::RaydiumV4(amm) => Some((amm.base_vault, amm.quote_vault)),
+            PoolState::RaydiumCpmm(cpmm) => Some((cpmm.token_0_vault, cpmm.token_1_vault)),
+            PoolState::RaydiumClmm(clmm) => Some((clmm.token_vault_0, clmm.token_vault_1)),
+            PoolState::Whirlpool(whirlpool) => Some((whirlpool.token_vault_a, whirlpool.token_vault_b)),
+            PoolState::MeteoraDlmm(lb_pair) => Some((lb_pair.reserve_x, lb_pair.reserve_y)),
+            PoolState::MeteoraAmm(amm) => Some((amm.a_vault, amm.b_vault)),
+            PoolState::Phoenix(_) | PoolState::OpenBookV2(_) => None,
+        };
+        let fee_ppm = match &pool.state {
+            PoolState::RaydiumV4(amm) => ratio_ppm(amm.swap_fee_numerator, amm.swap_fee_denominator),
+            PoolState::Whirlpool(whirlpool) => Some(whirlpool.fee_rate as u64),
+            PoolState::MeteoraDlmm(lb_pair) => Some((lb_pair.base_fee_rate() / 1_000) as u64),
+            PoolState::MeteoraAmm(amm) => ratio_ppm(amm.fees.trade_fee_numerator, amm.fees.trade_fee_denominator),
+            PoolState::RaydiumCpmm(_) | PoolState::RaydiumClmm(_) | PoolState::Phoenix(_) | PoolState::OpenBookV2(_) => None,
+        };
+        Self {
+            address: pool.address,
+            dex: pool.dex,
+            mint_a: pool.mint_a,
+            mint_b: pool.mint_b,
+            vaults,
+            fee_ppm,
+            decimals: None,
+        }
+    }
+}
+
+/// Pools found by [`PoolDiscovery::pools_with_liquidity`]
+#[derive(Debug, Default)]
+pub struct LiquidPools {
+    /// Pools at or above the liquidity floor, with their reserve of the mint
+    pub pools: Vec<(PoolMetadata, u64)>,
+    /// Pools whose reserve could not be read, with the reason
+    pub failed: Vec<(Pubkey, PriceError)>,
+}
+
+fn ratio_ppm(numerator: u64, denominator: u64) -> Option<u64> {
+    (denominator != 0).then(|| (numerator as u128 * 1_000_000 / denominator as u128) as u64)
+}
+
+/// Base58 (de)serialization for a `Pubkey`
+pub(crate) mod pubkey_str {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(key: &Pubkey, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(key)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Pubkey, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Base58 (de)serialization for an optional pair of keys
+mod pubkey_pair_str {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(pair: &Option<(Pubkey, Pubkey)>, serializer: S) -> Result<S::Ok, S::Error> {
+        pair.map(|(a, b)| (a.to_string(), b.to_string())).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<(Pubkey, Pubkey)>, D::Error> {
+        let Some((a, b)) = Option::<(String, String)>::deserialize(deserializer)? else {
+            return Ok(None);
+        };
+        Ok(Some((
+            a.parse().map_err(serde::de::Error::custom)?,
+            b.parse().map_err(serde::de::Error::custom)?,
+        )))
+    }
+}
+
+/// Finds pools of the supported AMMs with `getProgramAccounts`.
+///
+/// Matched accounts that fail to decode are skipped rather than failing the
+/// whole scan, since a program may hold stale or foreign accounts that
+/// happen to pass the filters.
+pub struct PoolDiscovery {
+    layouts: Vec<PoolLayout>,
+    dispatcher: QuoterDispatcher,
+    mints: MintRegistry,
+}
+
+impl PoolDiscovery {
+    /// Discovery across Raydium V4, CPMM, CLMM, Whirlpool, DLMM and Meteora dynamic AMM
+    pub fn new() -> Self {
+        Self {
+            layouts: POOL_LAYOUTS.to_vec(),
+            dispatcher: QuoterDispatcher::new(),
+            mints: MintRegistry::new(),
+        }
+    }
+
+    /// Restrict discovery to `dexes`
+    pub fn with_dexes(dexes: &[DexType]) -> Self {
+        Self {
+            layouts: POOL_LAYOUTS.into_iter().filter(|layout| dexes.contains(&layout.dex)).collect(),
+            dispatcher: QuoterDispatcher::new(),
+            mints: MintRegistry::new(),
+        }
+    }
+
+    /// The DEXes this discovery scans
+    pub fn dexes(&self) -> impl Iterator<Item = DexType> + '_ {
+        self.layouts.iter().map(|layout| layout.dex)
+    }
+
+    /// Every pool trading `mint_x` against `mint_y`, in either orientation
+    pub async fn pools_for_pair<B: RpcBackend + ?Sized>(
+        &self,
+        rpc: &B,
+        mint_x: &Pubkey,
+        mint_y: &Pubkey,
+    ) -> Result<Vec<PoolMetadata>, PriceError> {
+        let mut pools = Vec::new();
+        for layout in &self.layouts {
+            let (low, high) = if mint_x < mint_y { (mint_x, mint_y) } else { (mint_y, mint_x) };
+            let mut orderings = vec![(low, high)];
+            if !layout.sorted_mints && low != high {
+                orderings.push((high, low));
+            }
+            for (mint_a, mint_b) in orderings {
+                let filters = layout.filters(&[(layout.mint_a_offset, mint_a), (layout.mint_b_offset, mint_b)]);
+                pools.extend(self.scan(rpc, layout, &filters).await?);
+            }
+        }
+        self.describe(rpc, &pools).await
+    }
+
+    /// Every pool holding at least `min_amount` raw units of `mint`, with
+    /// that amount. Reserves are read from vault balances, which for some
+    /// AMMs still include fees not yet swept from the pool. Pools whose
+    /// reserve cannot be read are reported in `failed` rather than failing
+    /// the scan.
+    pub async fn pools_with_liquidity<B: RpcBackend + ?Sized>(
+        &self,
+        rpc: &B,
+        mint: &Pubkey,
+        min_amount: u64,
+    ) -> Result<LiquidPools, PriceError> {
+        let mut pools = Vec::new();
+        for layout in &self.layouts {
+            for offset in [layout.mint_a_offset, layout.mint_b_offset] {
+                pools.extend(self.scan(rpc, layout, &layout.filters(&[(offset, mint)])).await?);
+            }
+        }
+
+        let required: Vec<Pubkey> = pools
+            .iter()
+            .flat_map(|pool| self.reserve_accounts(pool))
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        let accounts = fetch_accounts(rpc, &required).await?;
+
+        let mut liquid = Vec::new();
+        let mut failed = Vec::new();
+        for pool in pools {
+            match self.reserve_of(&pool, mint, &accounts) {
+                Ok(reserve) if reserve >= min_amount => liquid.push((pool, reserve)),
+                Ok(_) => {}
+                Err(err) => failed.push((pool.address, err)),
+            }
+        }
+        let (pools, reserves): (Vec<DecodedPool>, Vec<u64>) = liquid.into_iter().unzip();
+        Ok(LiquidPools {
+            pools: self.describe(rpc, &pools).await?.into_iter().zip(reserves).collect(),
+            failed,
+        })
+    }
+
+    /// Metadata for decoded pools, with fees from Raydium config accounts and
+    /// mint decimals filled in. Mints that cannot be read leave `decimals`
+    /// unset rather than failing the batch.
+    pub async fn describe<B: RpcBackend + ?Sized>(&self, rpc: &B, pools: &[DecodedPool]) -> Result<Vec<PoolMetadata>, PriceError> {
+        let configs: Vec<Pubkey> = pools
+            .iter()
+            .filter_map(|pool| match &pool.state {
+                PoolState::RaydiumCpmm(cpmm) => Some(cpmm.amm_config),
+                PoolState::RaydiumClmm(clmm) => Some(clmm.amm_config),
+                _ => None,
+            })
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        let configs = fetch_accounts(rpc, &configs).await?;
+
+        let unknown_mints: Vec<Pubkey> = pools
+            .iter()
+            .flat_map(|pool| [pool.mint_a, pool.mint_b])
+            .filter(|mint| self.mints.get(mint).is_none())
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        for (mint, account) in fetch_accounts(rpc, &unknown_mints).await? {
+            if let Ok(info) = MintInfo::from_account(&mint, &account) {
+                self.mints.insert(mint, info);
+            }
+        }
+
+        Ok(pools
+            .iter()
+            .map(|pool| {
+                let mut metadata = PoolMetadata::from(pool);
+                metadata.fee_ppm = metadata.fee_ppm.or_else(|| match &pool.state {
+                    PoolState::RaydiumCpmm(cpmm) => configs
+                        .get(&cpmm.amm_config)
+                        .and_then(|account| AmmConfig::from_bytes(&account.data).ok())
+                        .map(|config| config.trade_fee_rate),
+                    PoolState::RaydiumClmm(clmm) => configs
+                        .get(&clmm.amm_config)
+                        .and_then(|account| ClmmAmmConfig::from_bytes(&account.data).ok())
+                        .map(|config| config.trade_fee_rate as u64),
+                    _ => None,
+                });
+                metadata.decimals = self
+                    .mints
+                    .get(&pool.mint_a)
+                    .zip(self.mints.get(&pool.mint_b))
+                    .map(|(a, b)| (a.decimals, b.decimals));
+                metadata
+            })
+            .collect())
+    }
+
+    async fn scan<B: RpcBackend + ?Sized>(
+        &self,
+        rpc: &B,
+        layout: &PoolLayout,
+        filters: &[RpcFilterType],
+    ) -> Result<Vec<DecodedPool>, PriceError> {
+        let accounts = rpc.get_program_accounts(&layout.program_id, filters).await?;
+        Ok(accounts
+            .iter()
+            .filter_map(|(address, account)| self.dispatcher.decode(address, account).ok())
+            .collect())
+    }
+
+    /// Accounts needed to read a pool's reserves
+    fn reserve_accounts(&self, pool: &DecodedPool) -> Vec<Pubkey> {
+        match (&pool.state, PoolMetadata::from(pool).vaults) {
+            (PoolState::MeteoraAmm(_), _) => self
+                .dispatcher
+                .get(&METEORA_AMM_PROGRAM_ID)
+                .map(|quoter| quoter.required_accounts(pool))
+                .unwrap_or_default(),
+            (_, Some((vault_a, vault_b))) => vec![vault_a, vault_b],
+            (_, None) => Vec::new(),
+        }
+    }
+
+    /// The pool's reserve of `mint`, which must be one of its two tokens
+    fn reserve_of(&self, pool: &DecodedPool, mint: &Pubkey, accounts: &AccountMap) -> Result<u64, PriceError> {
+        let is_a = pool.mint_a == *mint;
+        if let PoolState::MeteoraAmm(amm) = &pool.state {
+            let (reserve_a, reserve_b) = meteora_amm::vault_reserves(amm, accounts, unix_timestamp())?;
+            return Ok(if is_a { reserve_a } else { reserve_b });
+        }
+        let Some((vault_a, vault_b)) = PoolMetadata::from(pool).vaults else {
+            return Ok(0);
+        };
+        let vault = if is_a { vault_a } else { vault_b };
+        let account = accounts.get(&vault).ok_or(PriceError::AccountMissing(vault))?;
+        Ok(TokenAccount::from_account(&vault, account)?.amount)
+    }
+}
+
+impl Default for PoolDiscovery {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Fetch accounts in batches of `MAX_MULTIPLE_ACCOUNTS`, skipping missing ones
+pub(crate) async fn fetch_accounts<B: RpcBackend + ?Sized>(rpc: &B, keys: &[Pubkey]) -> Result<AccountMap, PriceError> {
+    let mut accounts = AccountMap::new();
+    for chunk in keys.chunks(MAX_MULTIPLE_ACCOUNTS) {
+        let fetched: Vec<Option<Account>> = rpc.get_multiple_accounts(chunk).await?;
+        accounts.extend(chunk.iter().zip(fetched).filter_map(|(key, account)| account.map(|account| (*key, account))));
+    }
+    Ok(accounts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{put_pubkey, token_account_of};
+    use rpc_manager::InMemoryBackend;
+
+    /// Pool account image for `layout` with the given mints and zeroed state
+    fn pool_account(layout: &PoolLayout, mint_a: &Pubkey, mint_b: &Pubkey) -> Account {
+        let size = layout.data_size.map(|size| size as usize).unwrap_or(1_000);
+        let mut data = vec![0u8; size];
+        if let Some(discriminator) = layout.discriminator {
+            data[..8].copy_from_slice(&discriminator);
+        }
+        put_pubkey(&mut data, layout.mint_a_offset, mint_a);
+        put_pubkey(&mut data, layout.mint_b_offset, mint_b);
+        Account {
+            data,
+            owner: layout.program_id,
+            ..Account::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pools_for_pair_across_dexes() {
+        let backend = InMemoryBackend::new();
+        let (low, high) = {
+            let (x, y) = (Pubkey::new_unique(), Pubkey::new_unique());
+            if x < y { (x, y) } else { (y, x) }
+        };
+        let other = Pubkey::new_unique();
+
+        let mut expected = Vec::new();
+        for layout in &POOL_LAYOUTS {
+            let address = Pubkey::new_unique();
+            backend.set_account(address, pool_account(layout, &low, &high));
+            expected.push((layout.dex, address));
+            // Unrelated pair on the same program
+            backend.set_account(Pubkey::new_unique(), pool_account(layout, &low, &other));
+        }
+        // Reversed orientation is found where the program allows it
+        let reversed = Pubkey::new_unique();
+        backend.set_account(reversed, pool_account(&POOL_LAYOUTS[0], &high, &low));
+        expected.push((DexType::RaydiumV4, reversed));
+
+        let pools = PoolDiscovery::new().pools_for_pair(&backend, &high, &low).await.unwrap();
+        let mut found: Vec<(DexType, Pubkey)> = pools.iter().map(|pool| (pool.dex, pool.address)).collect();
+        found.sort_by_key(|(_, address)| *address);
+        expected.sort_by_key(|(_, address)| *address);
+        assert_eq!(found, expected);
+        assert!(pools.iter().all(|pool| pool.vaults.is_some()));
+
+        let only_v4 = PoolDiscovery::with_dexes(&[DexType::RaydiumV4]);
+        assert_eq!(only_v4.pools_for_pair(&backend, &low, &high).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_pools_above_liquidity_floor() {
+        let backend = InMemoryBackend::new();
+        let (usdc, sol, bonk) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let layout = &POOL_LAYOUTS[0];
+        const BASE_VAULT_OFFSET: usize = 336;
+        const QUOTE_VAULT_OFFSET: usize = 368;
+
+        // USDC is the quote side of one pool and the base side of the other
+        let mut pools = Vec::new();
+        for (base, quote, usdc_amount) in [(sol, usdc, 5_000_000_000u64), (usdc, bonk, 10_000_000)] {
+            let (address, base_vault, quote_vault) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+            let mut account = pool_account(layout, &base, &quote);
+            put_pubkey(&mut account.data, BASE_VAULT_OFFSET, &base_vault);
+            put_pubkey(&mut account.data, QUOTE_VAULT_OFFSET, &quote_vault);
+            backend.set_account(address, account);
+            let (base_amount, quote_amount) = if base == usdc { (usdc_amount, 1) } else { (1, usdc_amount) };
+            backend.set_account(base_vault, token_account_of(&base, base_amount));
+            backend.set_account(quote_vault, token_account_of(&quote, quote_amount));
+            pools.push(address);
+        }
+
+        let discovery = PoolDiscovery::with_dexes(&[DexType::RaydiumV4]);
+        let liquid = discovery.pools_with_liquidity(&backend, &usdc, 1_000_000_000).await.unwrap();
+        assert_eq!(liquid.pools.len(), 1);
+        assert_eq!((liquid.pools[0].0.address, liquid.pools[0].1), (pools[0], 5_000_000_000));
+
+        let all = discovery.pools_with_liquidity(&backend, &usdc, 0).await.unwrap();
+        assert_eq!((all.pools.len(), all.failed.len()), (2, 0));
+
+        // A pool whose vault has been closed is reported, not fatal
+        let broken = Pubkey::new_unique();
+        let mut account = pool_account(layout, &sol, &usdc);
+        let closed_vault = Pubkey::new_unique();
+        put_pubkey(&mut account.data, BASE_VAULT_OFFSET, &Pubkey::new_unique());
+        put_pubkey(&mut account.data, QUOTE_VAULT_OFFSET, &closed_vault);
+        backend.set_account(broken, account);
+
+        let scan = discovery.pools_with_liquidity(&backend, &usdc, 0).await.unwrap();
+        assert_eq!(scan.pools.len(), 2);
+        assert!(matches!(
+            scan.failed[..],
+            [(pool, PriceError::AccountMissing(vault))] if pool == broken && vault == closed_vault
+        ));
+    }
+}
@@ -0,0 +1,80 @@
+use solana_sdk::pubkey::Pubkey;
+use std::fmt::Display;
+
+use crate::price::Price;
+
+/// Why a pool could not be priced or quoted.
+///
+/// Quoters return this instead of a placeholder value so callers can tell
+/// a missing account from a pool that genuinely has no liquidity. Helpers
+/// that work in `anyhow` may raise a variant with `anyhow::bail!`; it is
+/// recovered intact when converted back at the quoter boundary.
+#[derive(Debug, thiserror::Error)]
+pub enum PriceError {
+    #[error("Account {0} not found")]
+    AccountMissing(Pubkey),
+
+    #[error("Account {account} has unexpected owner {owner}")]
+    WrongOwner { account: Pubkey, owner: Pubkey },
+
+    #[error("Account {account} does not match the expected layout: {reason}")]
+    LayoutMismatch { account: Pubkey, reason: String },
+
+    #[error("Pool has empty reserves")]
+    EmptyPool,
+
+    #[error("Account {account} is stale: {reason}")]
+    StaleData { account: Pubkey, reason: String },
+
+    #[error("Decimals unknown for mint {0}")]
+    DecimalsUnknown(Pubkey),
+
+    #[error("Observations span slots {oldest} to {newest}, more than {max_slot_skew} apart")]
+    SlotSkew { oldest: u64, newest: u64, max_slot_skew: u64 },
+
+    #[error("Price {0} is below the Q64.64 precision floor; price the pair the other way round")]
+    Imprecise(Price),
+
+    /// Anything else: RPC failures, unsupported pool modes, insufficient
+    /// liquidity for the requested size, arithmetic overflow
+    #[error(transparent)]
+    Other(anyhow::Error),
+}
+
+impl PriceError {
+    pub fn layout(account: Pubkey, reason: impl Display) -> Self {
+        Self::LayoutMismatch {
+            account,
+            reason: reason.to_string(),
+        }
+    }
+
+    pub fn stale(account: Pubkey, reason: impl Display) -> Self {
+        Self::StaleData {
+            account,
+            reason: reason.to_string(),
+        }
+    }
+}
+
+impl From<anyhow::Error> for PriceError {
+    fn from(err: anyhow::Error) -> Self {
+        err.downcast::<PriceError>().unwrap_or_else(PriceError::Other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recovers_typed_error_from_anyhow() {
+        let mint = Pubkey::new_unique();
+        let wrapped = anyhow::Error::from(PriceError::DecimalsUnknown(mint));
+        assert!(matches!(PriceError::from(wrapped), PriceError::DecimalsUnknown(m) if m == mint));
+
+        let other = PriceError::from(anyhow::anyhow!("RPC timed out"));
+        assert!(matches!(other, PriceError::Other(_)));
+        assert_eq!(other.to_string(), "RPC timed out");
+    }
+}
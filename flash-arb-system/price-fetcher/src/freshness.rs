@@ -0,0 +1,105 @@
+//! When pool data was observed.
+//!
+//! Prices read at different slots are not simultaneous: two pools fetched
+//! ten slots apart can disagree purely because one of them traded in
+//! between. States and quotes carry the context slot of the RPC response
+//! they were built from, so comparisons can be limited to a slot window.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::PriceError;
+
+/// Context slot and local receive time of the data behind a state or quote.
+/// The default, slot 0, means the data was never stamped.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Observation {
+    pub slot: u64,
+    /// Local wall-clock time the response arrived, in unix milliseconds
+    pub received_at_ms: u64,
+}
+
+impl Observation {
+    /// Data read at `slot` and received just now
+    pub fn received_now(slot: u64) -> Self {
+        Self {
+            slot,
+            received_at_ms: unix_timestamp_ms(),
+        }
+    }
+
+    pub fn is_stamped(&self) -> bool {
+        self.slot != 0
+    }
+
+    /// Slots between the two observations, in either order
+    pub fn slot_skew(&self, other: &Observation) -> u64 {
+        self.slot.abs_diff(other.slot)
+    }
+
+    /// Milliseconds since the data was received
+    pub fn age_ms(&self, now_ms: u64) -> u64 {
+        now_ms.saturating_sub(self.received_at_ms)
+    }
+}
+
+/// Check that `observations` may be compared as simultaneous: all stamped
+/// and no more than `max_slot_skew` slots apart. Returns the actual skew.
+pub fn check_slot_skew<'a>(
+    observations: impl IntoIterator<Item = &'a Observation>,
+    max_slot_skew: u64,
+) -> Result<u64, PriceError> {
+    let mut range: Option<(u64, u64)> = None;
+    for observation in observations {
+        if !observation.is_stamped() {
+            return Err(anyhow::anyhow!("Observation has no context slot").into());
+        }
+        let (oldest, newest) = range.get_or_insert((observation.slot, observation.slot));
+        *oldest = (*oldest).min(observation.slot);
+        *newest = (*newest).max(observation.slot);
+    }
+
+    let (oldest, newest) = range.unwrap_or_default();
+    if newest - oldest > max_slot_skew {
+        return Err(PriceError::SlotSkew {
+            oldest,
+            newest,
+            max_slot_skew,
+        });
+    }
+    Ok(newest - oldest)
+}
+
+pub(crate) fn unix_timestamp_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(slot: u64) -> Observation {
+        Observation {
+            slot,
+            received_at_ms: 1_700_000_000_000,
+        }
+    }
+
+    #[test]
+    fn test_slot_skew_window() {
+        let observations = [at(1_000), at(1_004), at(1_002)];
+        assert_eq!(check_slot_skew(&observations, 4).unwrap(), 4);
+        assert!(matches!(
+            check_slot_skew(&observations, 3),
+            Err(PriceError::SlotSkew { oldest: 1_000, newest: 1_004, max_slot_skew: 3 })
+        ));
+        assert_eq!(check_slot_skew(&[], 0).unwrap(), 0);
+
+        // Unstamped data never passes
+        assert!(check_slot_skew(&[at(1_000), Observation::default()], u64::MAX).is_err());
+        assert_eq!(at(1_000).slot_skew(&at(990)), 10);
+        assert_eq!(at(0).age_ms(1_700_000_000_250), 250);
+    }
+}
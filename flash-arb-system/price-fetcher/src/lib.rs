@@ -1,7 +1,45 @@
 pub mod raydium;
+pub mod raydium_cpmm;
+pub mod raydium_clmm;
 pub mod orca;
 pub mod meteora;
+pub mod meteora_amm;
+pub mod orderbook;
+pub mod phoenix;
+pub mod openbook;
+pub mod oracle;
+pub mod lst;
+pub mod quoter;
+pub mod clmm_math;
+pub mod stable_swap;
+pub mod price;
+pub mod token;
+pub mod error;
+pub mod freshness;
+pub mod discovery;
+pub mod registry;
+pub mod snapshot;
+pub mod stream;
+#[cfg(test)]
+mod test_utils;
 
 pub use raydium::RaydiumClient;
+pub use raydium_cpmm::RaydiumCpmmClient;
+pub use raydium_clmm::RaydiumClmmClient;
 pub use orca::OrcaClient;
 pub use meteora::MeteoraClient;
+pub use meteora_amm::MeteoraAmmClient;
+pub use phoenix::PhoenixClient;
+pub use openbook::OpenBookClient;
+pub use orderbook::{L2Book, L2Level};
+pub use oracle::{price_deviation, OracleClient, OraclePrice, PriceDeviation};
+pub use lst::{ExchangeRate, LstClient};
+pub use quoter::{AccountMap, DecodedPool, DexType, PoolPrice, PoolQuoter, PoolState, PostTradeState, QuoterDispatcher, SwapDirection, SwapQuote, TransferFees};
+pub use price::Price;
+pub use error::PriceError;
+pub use freshness::{check_slot_skew, Observation};
+pub use discovery::{LiquidPools, PoolDiscovery, PoolMetadata};
+pub use registry::PoolRegistry;
+pub use snapshot::Snapshot;
+pub use stream::{follow_pool_updates, stream_pool_updates, AccountSubscriber, PoolTracker, PoolUpdate};
+pub use token::{MintExtensions, MintInfo, MintRegistry, TokenAccount, TransferFeeConfig};
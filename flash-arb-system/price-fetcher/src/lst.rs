@@ -0,0 +1,282 @@
+//! Liquid staking token redemption rates.
+//!
+//! An LST is worth the SOL its stake pool holds per pool token, whatever it
+//! trades at on a DEX. The rate comes from the SPL stake pool program (used
+//! by jitoSOL, bSOL and most other LSTs) or Marinade's state account, and
+//! only changes once per epoch when the pool is updated.
+
+use rpc_manager::RpcBackend;
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::error::PriceError;
+use crate::freshness::Observation;
+use crate::oracle::PriceDeviation;
+use crate::price::Price;
+use crate::quoter::clock_epoch;
+
+/// SPL stake pool program
+pub const SPL_STAKE_POOL_PROGRAM_ID: Pubkey = solana_sdk::pubkey!("SPoo1Ku8WFXoNDMHPsrGSTSG1Y47rzgn41SLUNakuHy");
+/// Marinade liquid staking program
+pub const MARINADE_PROGRAM_ID: Pubkey = solana_sdk::pubkey!("MarBmsSgKXdrN1egZf5sqe1TMai9K1rFYK2WCpBRkoE");
+/// Marinade's singleton state account
+pub const MARINADE_STATE: Pubkey = solana_sdk::pubkey!("8szGkuLTAux9XMgZ2vtY39jVSowEcpBfFfD8hXSEqdGC");
+
+/// `StakePool` fields up to `last_update_epoch`. Later fields include
+/// variable-length options, so fees are not decoded.
+const STAKE_POOL_ACCOUNT_TYPE: u8 = 1;
+const STAKE_POOL_MINT_OFFSET: usize = 162;
+const STAKE_POOL_TOTAL_LAMPORTS_OFFSET: usize = 258;
+const STAKE_POOL_TOKEN_SUPPLY_OFFSET: usize = 266;
+const STAKE_POOL_LAST_UPDATE_EPOCH_OFFSET: usize = 274;
+
+const MARINADE_STATE_DISCRIMINATOR: [u8; 8] = [216, 146, 107, 94, 104, 75, 182, 177];
+const MARINADE_MSOL_MINT_OFFSET: usize = 8;
+const MARINADE_MSOL_SUPPLY_OFFSET: usize = 504;
+const MARINADE_MSOL_PRICE_OFFSET: usize = 512;
+/// `msol_price` is SOL per mSOL scaled by 2^32
+const MARINADE_PRICE_DENOMINATOR: u128 = 1 << 32;
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+fn read_pubkey(data: &[u8], offset: usize) -> Pubkey {
+    Pubkey::new_from_array(data[offset..offset + 32].try_into().unwrap())
+}
+
+/// Fields of an SPL `StakePool` account that determine its exchange rate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StakePoolState {
+    pub pool_mint: Pubkey,
+    /// Lamports under management as of `last_update_epoch`
+    pub total_lamports: u64,
+    pub pool_token_supply: u64,
+    pub last_update_epoch: u64,
+}
+
+impl StakePoolState {
+    /// Decode a stake pool account. Forks of the program that keep its
+    /// layout (e.g. Sanctum's) decode the same way.
+    pub fn from_bytes(data: &[u8]) -> anyhow::Result<Self> {
+        if data.len() < STAKE_POOL_LAST_UPDATE_EPOCH_OFFSET + 8 || data[0] != STAKE_POOL_ACCOUNT_TYPE {
+            anyhow::bail!("Not an SPL stake pool account");
+        }
+        Ok(Self {
+            pool_mint: read_pubkey(data, STAKE_POOL_MINT_OFFSET),
+            total_lamports: read_u64(data, STAKE_POOL_TOTAL_LAMPORTS_OFFSET),
+            pool_token_supply: read_u64(data, STAKE_POOL_TOKEN_SUPPLY_OFFSET),
+            last_update_epoch: read_u64(data, STAKE_POOL_LAST_UPDATE_EPOCH_OFFSET),
+        })
+    }
+}
+
+/// Fields of Marinade's `State` account that determine the mSOL rate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MarinadeState {
+    pub msol_mint: Pubkey,
+    pub msol_supply: u64,
+    /// Lamports per mSOL, scaled by 2^32, as of the last epoch update
+    pub msol_price: u64,
+}
+
+impl MarinadeState {
+    pub fn from_bytes(data: &[u8]) -> anyhow::Result<Self> {
+        if data.len() < MARINADE_MSOL_PRICE_OFFSET + 8 || data[..8] != MARINADE_STATE_DISCRIMINATOR {
+            anyhow::bail!("Not a Marinade state account");
+        }
+        Ok(Self {
+            msol_mint: read_pubkey(data, MARINADE_MSOL_MINT_OFFSET),
+            msol_supply: read_u64(data, MARINADE_MSOL_SUPPLY_OFFSET),
+            msol_price: read_u64(data, MARINADE_MSOL_PRICE_OFFSET),
+        })
+    }
+}
+
+/// SOL an LST redeems for at its stake pool
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExchangeRate {
+    /// Stake pool or Marinade state account the rate was read from
+    pub source: Pubkey,
+    pub lst_mint: Pubkey,
+    /// Lamports per raw LST unit; every supported LST has SOL's 9 decimals,
+    /// so this is also SOL per whole token. Withdrawal fees are not deducted.
+    pub rate: Price,
+    /// Epoch the rate was last updated in, for SPL stake pools
+    pub last_update_epoch: Option<u64>,
+    pub observed: Observation,
+}
+
+impl ExchangeRate {
+    /// An SPL stake pool that has not run its epoch update still reports
+    /// last epoch's rate, which undervalues the LST by one epoch of rewards
+    pub fn is_stale(&self, current_epoch: u64) -> bool {
+        self.last_update_epoch.is_some_and(|epoch| epoch < current_epoch)
+    }
+
+    /// Compare a DEX price of the LST in SOL against its redemption rate
+    pub fn deviation(&self, dex_price: Price, threshold_bps: f64) -> Result<PriceDeviation, PriceError> {
+        Ok(PriceDeviation {
+            deviation_bps: dex_price.deviation_bps(self.rate)?,
+            threshold_bps,
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct LstClient {}
+
+impl LstClient {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Decode an SPL stake pool or Marinade state account based on its owner
+    pub fn decode(&self, address: &Pubkey, account: &Account) -> Result<ExchangeRate, PriceError> {
+        match account.owner {
+            SPL_STAKE_POOL_PROGRAM_ID => {
+                let pool = StakePoolState::from_bytes(&account.data).map_err(|err| PriceError::layout(*address, err))?;
+                if pool.pool_token_supply == 0 {
+                    return Err(PriceError::EmptyPool);
+                }
+                Ok(ExchangeRate {
+                    source: *address,
+                    lst_mint: pool.pool_mint,
+                    rate: Price::from_ratio(pool.total_lamports as u128, pool.pool_token_supply as u128)?,
+                    last_update_epoch: Some(pool.last_update_epoch),
+                    observed: Observation::default(),
+                })
+            }
+            MARINADE_PROGRAM_ID => {
+                let state = MarinadeState::from_bytes(&account.data).map_err(|err| PriceError::layout(*address, err))?;
+                if state.msol_supply == 0 || state.msol_price == 0 {
+                    return Err(PriceError::EmptyPool);
+                }
+                Ok(ExchangeRate {
+                    source: *address,
+                    lst_mint: state.msol_mint,
+                    rate: Price::from_ratio(state.msol_price as u128, MARINADE_PRICE_DENOMINATOR)?,
+                    last_update_epoch: None,
+                    observed: Observation::default(),
+                })
+            }
+            owner => Err(PriceError::WrongOwner {
+                account: *address,
+                owner,
+            }),
+        }
+    }
+
+    /// Fetch redemption rates for the given stake pool and Marinade state
+    /// accounts, in order. SPL stake pools that missed this epoch's update
+    /// are reported as stale.
+    pub async fn fetch_rates<B: RpcBackend + ?Sized>(&self, rpc: &B, sources: &[Pubkey]) -> Result<Vec<ExchangeRate>, PriceError> {
+        let mut keys = sources.to_vec();
+        keys.push(solana_sdk::sysvar::clock::ID);
+        let (slot, mut accounts) = rpc.get_multiple_accounts_with_slot(&keys, None).await?;
+        let clock = accounts.pop().flatten().ok_or(PriceError::AccountMissing(solana_sdk::sysvar::clock::ID))?;
+        let epoch = clock_epoch(&clock)?;
+        let observed = Observation::received_now(slot);
+
+        sources
+            .iter()
+            .zip(accounts)
+            .map(|(address, account)| {
+                let account = account.ok_or(PriceError::AccountMissing(*address))?;
+                let rate = ExchangeRate {
+                    observed,
+                    ..self.decode(address, &account)?
+                };
+                if rate.is_stale(epoch) {
+                    return Err(PriceError::stale(
+                        *address,
+                        format!("stake pool last updated in epoch {}, now {}", rate.last_update_epoch.unwrap_or_default(), epoch),
+                    ));
+                }
+                Ok(rate)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rpc_manager::InMemoryBackend;
+
+    fn stake_pool(mint: &Pubkey, total_lamports: u64, supply: u64, epoch: u64) -> Account {
+        let mut data = vec![0u8; 611];
+        data[0] = STAKE_POOL_ACCOUNT_TYPE;
+        data[STAKE_POOL_MINT_OFFSET..STAKE_POOL_MINT_OFFSET + 32].copy_from_slice(mint.as_ref());
+        data[STAKE_POOL_TOTAL_LAMPORTS_OFFSET..STAKE_POOL_TOTAL_LAMPORTS_OFFSET + 8].copy_from_slice(&total_lamports.to_le_bytes());
+        data[STAKE_POOL_TOKEN_SUPPLY_OFFSET..STAKE_POOL_TOKEN_SUPPLY_OFFSET + 8].copy_from_slice(&supply.to_le_bytes());
+        data[STAKE_POOL_LAST_UPDATE_EPOCH_OFFSET..STAKE_POOL_LAST_UPDATE_EPOCH_OFFSET + 8].copy_from_slice(&epoch.to_le_bytes());
+        Account {
+            data,
+            owner: SPL_STAKE_POOL_PROGRAM_ID,
+            ..Account::default()
+        }
+    }
+
+    fn clock(epoch: u64) -> Account {
+        let mut data = vec![0u8; 40];
+        data[16..24].copy_from_slice(&epoch.to_le_bytes());
+        Account {
+            data,
+            owner: solana_sdk::sysvar::ID,
+            ..Account::default()
+        }
+    }
+
+    #[test]
+    fn test_marinade_rate() {
+        let msol = Pubkey::new_unique();
+        let mut data = vec![0u8; 1_000];
+        data[..8].copy_from_slice(&MARINADE_STATE_DISCRIMINATOR);
+        data[MARINADE_MSOL_MINT_OFFSET..MARINADE_MSOL_MINT_OFFSET + 32].copy_from_slice(msol.as_ref());
+        data[MARINADE_MSOL_SUPPLY_OFFSET..MARINADE_MSOL_SUPPLY_OFFSET + 8].copy_from_slice(&1_000_000_000u64.to_le_bytes());
+        // 1.25 SOL per mSOL
+        data[MARINADE_MSOL_PRICE_OFFSET..MARINADE_MSOL_PRICE_OFFSET + 8].copy_from_slice(&(5u64 << 30).to_le_bytes());
+        let account = Account {
+            data,
+            owner: MARINADE_PROGRAM_ID,
+            ..Account::default()
+        };
+
+        let rate = LstClient::new().decode(&MARINADE_STATE, &account).unwrap();
+        assert_eq!(rate.lst_mint, msol);
+        assert_eq!(rate.rate, Price::from_ratio(5, 4).unwrap());
+        assert!(!rate.is_stale(u64::MAX));
+
+        // A DEX pricing mSOL at 1.2 SOL is 400 bps below redemption
+        let deviation = rate.deviation(Price::from_ratio(6, 5).unwrap(), 50.0).unwrap();
+        assert!((deviation.deviation_bps - 400.0).abs() < 1e-6);
+        assert!(deviation.exceeds_threshold());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_stake_pool_rates() {
+        let backend = InMemoryBackend::new();
+        backend.set_slot(300_000_000);
+        let (jitosol, current, lagging) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        backend.set_account(current, stake_pool(&jitosol, 1_150_000_000_000, 1_000_000_000_000, 700));
+        backend.set_account(lagging, stake_pool(&Pubkey::new_unique(), 2_000, 1_000, 699));
+        backend.set_account(solana_sdk::sysvar::clock::ID, clock(700));
+
+        let client = LstClient::new();
+        let rates = client.fetch_rates(&backend, &[current]).await.unwrap();
+        assert_eq!((rates[0].lst_mint, rates[0].last_update_epoch), (jitosol, Some(700)));
+        assert_eq!(rates[0].rate, Price::from_ratio(115, 100).unwrap());
+        assert_eq!(rates[0].observed.slot, 300_000_000);
+
+        assert!(matches!(
+            client.fetch_rates(&backend, &[current, lagging]).await,
+            Err(PriceError::StaleData { account, .. }) if account == lagging
+        ));
+
+        let empty = stake_pool(&jitosol, 0, 0, 700);
+        assert!(matches!(client.decode(&current, &empty), Err(PriceError::EmptyPool)));
+        let foreign = Account { owner: Pubkey::new_unique(), ..empty };
+        assert!(matches!(client.decode(&current, &foreign), Err(PriceError::WrongOwner { .. })));
+    }
+}
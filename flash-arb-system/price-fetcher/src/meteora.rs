@@ -1,2 +1,706 @@
-// Meteora price client - placeholder  
+use anyhow::Result;
+use borsh::BorshDeserialize;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::error::PriceError;
+use crate::freshness::Observation;
+use crate::price::Price;
+use crate::quoter::{
+    price_impact_bps, unix_timestamp, AccountMap, DecodedPool, DexType, PoolPrice, PoolQuoter, PoolState,
+    PostTradeState, SwapDirection, SwapQuote, TransferFees,
+};
+use crate::token::MintRegistry;
+use rpc_manager::RpcBackend;
+
+/// Meteora DLMM (liquidity book) program
+pub const METEORA_DLMM_PROGRAM_ID: Pubkey = solana_sdk::pubkey!("LBUZKhRxPF3XUpBCjp4YzTKgLccjZhTSDM9t2Qen9Ea");
+
+pub(crate) const LB_PAIR_DISCRIMINATOR: [u8; 8] = [33, 11, 49, 98, 181, 101, 177, 13];
+const BIN_ARRAY_DISCRIMINATOR: [u8; 8] = [92, 142, 92, 220, 5, 148, 70, 181];
+
+/// Bins per bin array
+pub const MAX_BIN_PER_ARRAY: i32 = 70;
+
+/// Bin arrays fetched on each side of the active one
+const BIN_ARRAYS_EACH_SIDE: i32 = 2;
+
+const BASIS_POINT_MAX: u128 = 10_000;
+/// Fee rates are scaled by 1e9
+const FEE_PRECISION: u128 = 1_000_000_000;
+/// Total fee is capped at 10%
+const MAX_FEE_RATE: u128 = 100_000_000;
+const SCALE_OFFSET: u32 = 64;
+const ONE_X64: u128 = 1 << SCALE_OFFSET;
+/// Largest |bin id| the program's `pow` accepts
+const MAX_EXPONENTIAL: u32 = 0x80000;
+
+/// Fee parameters fixed at pool creation
+#[derive(BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StaticParameters {
+    pub base_factor: u16,
+    pub filter_period: u16,
+    pub decay_period: u16,
+    pub reduction_factor: u16,
+    pub variable_fee_control: u32,
+    pub max_volatility_accumulator: u32,
+    pub min_bin_id: i32,
+    pub max_bin_id: i32,
+    pub protocol_share: u16,
+    pub base_fee_power_factor: u8,
+    pub padding: [u8; 5],
+}
+
+/// Volatility state updated on every swap
+#[derive(BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VariableParameters {
+    pub volatility_accumulator: u32,
+    pub volatility_reference: u32,
+    pub index_reference: i32,
+    pub padding: [u8; 4],
+    pub last_update_timestamp: i64,
+    pub padding1: [u8; 8],
+}
+
+/// Leading fields of the DLMM `LbPair` account (after the discriminator).
+/// Rewards, oracle and bitmap fields that follow are not needed for quoting.
+#[derive(BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LbPair {
+    pub parameters: StaticParameters,
+    pub v_parameters: VariableParameters,
+    pub bump_seed: [u8; 1],
+    pub bin_step_seed: [u8; 2],
+    pub pair_type: u8,
+    pub active_id: i32,
+    pub bin_step: u16,
+    pub status: u8,
+    pub require_base_factor_seed: u8,
+    pub base_factor_seed: [u8; 2],
+    pub activation_type: u8,
+    pub padding0: u8,
+    pub token_x_mint: Pubkey,
+    pub token_y_mint: Pubkey,
+    pub reserve_x: Pubkey,
+    pub reserve_y: Pubkey,
+}
+
+impl LbPair {
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        if data.len() < 8 || data[..8] != LB_PAIR_DISCRIMINATOR {
+            anyhow::bail!("Not a DLMM LbPair account");
+        }
+        Ok(Self::deserialize(&mut &data[8..])?)
+    }
+
+    /// Base fee rate scaled by `FEE_PRECISION`
+    pub fn base_fee_rate(&self) -> u128 {
+        self.parameters.base_factor as u128
+            * self.bin_step as u128
+            * 10
+            * 10u128.pow(self.parameters.base_fee_power_factor as u32)
+    }
+
+    /// Volatility-driven fee rate scaled by `FEE_PRECISION`, rounded up
+    pub fn variable_fee_rate(&self) -> u128 {
+        if self.parameters.variable_fee_control == 0 {
+            return 0;
+        }
+        let volatility_bin = self.v_parameters.volatility_accumulator as u128 * self.bin_step as u128;
+        let variable_fee = self.parameters.variable_fee_control as u128 * volatility_bin * volatility_bin;
+        variable_fee.div_ceil(100_000_000_000)
+    }
+
+    pub fn total_fee_rate(&self) -> u128 {
+        (self.base_fee_rate() + self.variable_fee_rate()).min(MAX_FEE_RATE)
+    }
+
+    /// Decay the volatility reference as the program does at the start of a swap
+    fn update_references(&mut self, now: i64) {
+        let elapsed = now - self.v_parameters.last_update_timestamp;
+        if elapsed >= self.parameters.filter_period as i64 {
+            self.v_parameters.index_reference = self.active_id;
+            self.v_parameters.volatility_reference = if elapsed < self.parameters.decay_period as i64 {
+                (self.v_parameters.volatility_accumulator as u64 * self.parameters.reduction_factor as u64
+                    / BASIS_POINT_MAX as u64) as u32
+            } else {
+                0
+            };
+        }
+    }
+
+    /// Accumulate volatility for the distance travelled from the reference bin
+    fn update_volatility_accumulator(&mut self) {
+        let delta_id = (self.v_parameters.index_reference as i64 - self.active_id as i64).unsigned_abs();
+        let accumulator =
+            self.v_parameters.volatility_reference as u64 + delta_id * BASIS_POINT_MAX as u64;
+        self.v_parameters.volatility_accumulator =
+            accumulator.min(self.parameters.max_volatility_accumulator as u64) as u32;
+    }
+}
+
+/// Liquidity held in a single bin
+#[derive(BorshDeserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Bin {
+    pub amount_x: u64,
+    pub amount_y: u64,
+    /// Q64.64 price of the bin, cached on initialization
+    pub price: u128,
+    pub liquidity_supply: u128,
+    pub reward_per_token_stored: [u128; 2],
+    pub fee_amount_x_per_token_stored: u128,
+    pub fee_amount_y_per_token_stored: u128,
+    pub amount_x_in: u128,
+    pub amount_y_in: u128,
+}
+
+/// DLMM `BinArray` account (after the discriminator)
+#[derive(BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BinArray {
+    pub index: i64,
+    pub version: u8,
+    pub padding: [u8; 7],
+    pub lb_pair: Pubkey,
+    pub bins: [Bin; MAX_BIN_PER_ARRAY as usize],
+}
+
+impl BinArray {
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        if data.len() < 8 || data[..8] != BIN_ARRAY_DISCRIMINATOR {
+            anyhow::bail!("Not a DLMM BinArray account");
+        }
+        Ok(Self::deserialize(&mut &data[8..])?)
+    }
+}
+
+/// Index of the bin array holding `bin_id`
+pub fn bin_array_index(bin_id: i32) -> i64 {
+    bin_id.div_euclid(MAX_BIN_PER_ARRAY) as i64
+}
+
+/// Bin array PDA for an LbPair
+pub fn bin_array_address(lb_pair: &Pubkey, index: i64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"bin_array", lb_pair.as_ref(), &index.to_le_bytes()],
+        &METEORA_DLMM_PROGRAM_ID,
+    )
+    .0
+}
+
+/// Indices of the bin arrays around the active bin, lowest first
+fn bin_array_window(lb_pair: &LbPair) -> Vec<i64> {
+    let active = bin_array_index(lb_pair.active_id);
+    (-BIN_ARRAYS_EACH_SIDE as i64..=BIN_ARRAYS_EACH_SIDE as i64)
+        .map(|offset| active + offset)
+        .collect()
+}
+
+/// `base^exp` in Q64.64, bit-for-bit with the program's `pow`
+fn pow_x64(base: u128, exp: i32) -> Option<u128> {
+    let mut invert = exp.is_negative();
+    let exp = exp.unsigned_abs();
+    if exp == 0 {
+        return Some(ONE_X64);
+    }
+    if exp >= MAX_EXPONENTIAL {
+        return None;
+    }
+
+    // Work with a base below one so every square fits in 128 bits
+    let mut squared_base = base;
+    if squared_base >= ONE_X64 {
+        squared_base = u128::MAX.checked_div(squared_base)?;
+        invert = !invert;
+    }
+
+    let mut result = ONE_X64;
+    let mut bit = 1u32;
+    while bit < MAX_EXPONENTIAL {
+        if exp & bit != 0 {
+            result = result.checked_mul(squared_base)? >> SCALE_OFFSET;
+        }
+        squared_base = squared_base.checked_mul(squared_base)? >> SCALE_OFFSET;
+        bit <<= 1;
+    }
+
+    if result == 0 {
+        return None;
+    }
+    if invert {
+        result = u128::MAX.checked_div(result)?;
+    }
+    Some(result)
+}
+
+/// Q64.64 price (Y per X, raw units) of a bin
+pub fn price_from_bin_id(bin_id: i32, bin_step: u16) -> Result<u128> {
+    let base = ONE_X64 + ((bin_step as u128) << SCALE_OFFSET) / BASIS_POINT_MAX;
+    pow_x64(base, bin_id).ok_or_else(|| anyhow::anyhow!("Bin {} price out of range", bin_id))
+}
+
+/// Fee charged on top of `amount` so that `amount` is left after the fee
+fn compute_fee(amount: u64, fee_rate: u128) -> u64 {
+    (amount as u128 * fee_rate).div_ceil(FEE_PRECISION - fee_rate) as u64
+}
+
+/// Fee included in a gross `amount`
+fn compute_fee_from_amount(amount: u64, fee_rate: u128) -> u64 {
+    (amount as u128 * fee_rate).div_ceil(FEE_PRECISION) as u64
+}
+
+/// Result of swapping within one bin
+struct BinSwap {
+    amount_in_with_fees: u64,
+    amount_out: u64,
+    fee: u64,
+    /// Bin had less output than the remaining input could buy
+    exhausted: bool,
+}
+
+/// Input before fees needed to take `amount_out` from a bin at `price`, rounded up
+fn bin_amount_in(amount_out: u64, price: u128, swap_for_y: bool) -> Result<u64> {
+    let amount_in = if swap_for_y {
+        ((amount_out as u128) << SCALE_OFFSET).div_ceil(price)
+    } else {
+        (amount_out as u128 * price).div_ceil(ONE_X64)
+    };
+    u64::try_from(amount_in).map_err(|_| anyhow::anyhow!("Bin input overflow"))
+}
+
+/// Swap up to `amount_in` (fees included) against a single bin
+fn swap_in_bin(bin: &Bin, price: u128, amount_in: u64, fee_rate: u128, swap_for_y: bool) -> Result<BinSwap> {
+    let max_amount_out = if swap_for_y { bin.amount_y } else { bin.amount_x };
+    let max_amount_in = bin_amount_in(max_amount_out, price, swap_for_y)?;
+    let max_fee = compute_fee(max_amount_in, fee_rate);
+    let max_amount_in_with_fees = max_amount_in.saturating_add(max_fee);
+
+    // The program only takes the whole bin when the input strictly exceeds
+    // it; an exact match is priced through the partial-fill path below
+    if amount_in > max_amount_in_with_fees {
+        return Ok(BinSwap {
+            amount_in_with_fees: max_amount_in_with_fees,
+            amount_out: max_amount_out,
+            fee: max_fee,
+            exhausted: true,
+        });
+    }
+
+    let fee = compute_fee_from_amount(amount_in, fee_rate);
+    let amount_in_after_fee = (amount_in - fee) as u128;
+    let amount_out = if swap_for_y {
+        (amount_in_after_fee * price) >> SCALE_OFFSET
+    } else {
+        (amount_in_after_fee << SCALE_OFFSET) / price
+    };
+    Ok(BinSwap {
+        amount_in_with_fees: amount_in,
+        amount_out: (amount_out as u64).min(max_amount_out),
+        fee,
+        exhausted: false,
+    })
+}
+
+/// Take up to `amount_out` from a single bin, charging the fee on top of
+/// the input as `swap_exact_out` does
+fn swap_out_of_bin(bin: &Bin, price: u128, amount_out: u64, fee_rate: u128, swap_for_y: bool) -> Result<BinSwap> {
+    let max_amount_out = if swap_for_y { bin.amount_y } else { bin.amount_x };
+    let amount_out = amount_out.min(max_amount_out);
+    let amount_in = bin_amount_in(amount_out, price, swap_for_y)?;
+    let fee = compute_fee(amount_in, fee_rate);
+    Ok(BinSwap {
+        amount_in_with_fees: amount_in.checked_add(fee).ok_or_else(|| anyhow::anyhow!("Bin input overflow"))?,
+        amount_out,
+        fee,
+        exhausted: amount_out == max_amount_out,
+    })
+}
+
+/// Simulate `swap_exact_in` across bins, starting from the active bin and
+/// moving one bin at a time as each is drained. The volatility accumulator
+/// is updated per bin, so the variable fee rises as the swap travels.
+///
+/// `bin_arrays` must be contiguous and cover the bins the swap reaches;
+/// running past them is an error.
+pub fn simulate_swap_exact_in(
+    lb_pair: &LbPair,
+    bin_arrays: &[BinArray],
+    amount_in: u64,
+    direction: SwapDirection,
+    now: i64,
+) -> Result<SwapQuote> {
+    simulate_swap(lb_pair, bin_arrays, amount_in, direction, now, true)
+}
+
+/// Simulate `swap_exact_out` across bins, walking them as
+/// [`simulate_swap_exact_in`] does until `amount_out` is filled
+pub fn simulate_swap_exact_out(
+    lb_pair: &LbPair,
+    bin_arrays: &[BinArray],
+    amount_out: u64,
+    direction: SwapDirection,
+    now: i64,
+) -> Result<SwapQuote> {
+    simulate_swap(lb_pair, bin_arrays, amount_out, direction, now, false)
+}
+
+/// Walk bins until `amount` of input (`exact_in`) or output is used up
+fn simulate_swap(
+    lb_pair: &LbPair,
+    bin_arrays: &[BinArray],
+    amount: u64,
+    direction: SwapDirection,
+    now: i64,
+    exact_in: bool,
+) -> Result<SwapQuote> {
+    let swap_for_y = direction == SwapDirection::AToB;
+    let mut pair = lb_pair.clone();
+    pair.update_references(now);
+
+    let mut amount_left = amount;
+    let mut amount_in: u64 = 0;
+    let mut amount_out: u64 = 0;
+    let mut fee_total: u64 = 0;
+
+    while amount_left > 0 {
+        let array_index = bin_array_index(pair.active_id);
+        let array = bin_arrays
+            .iter()
+            .find(|array| array.index == array_index)
+            .ok_or_else(|| anyhow::anyhow!("Swap reaches bin array {} which was not fetched", array_index))?;
+        let bin = &array.bins[(pair.active_id as i64 - array_index * MAX_BIN_PER_ARRAY as i64) as usize];
+
+        pair.update_volatility_accumulator();
+        let has_liquidity = if swap_for_y { bin.amount_y > 0 } else { bin.amount_x > 0 };
+        if has_liquidity {
+            let price = if bin.price == 0 {
+                price_from_bin_id(pair.active_id, pair.bin_step)?
+            } else {
+                bin.price
+            };
+            let step = if exact_in {
+                swap_in_bin(bin, price, amount_left, pair.total_fee_rate(), swap_for_y)?
+            } else {
+                swap_out_of_bin(bin, price, amount_left, pair.total_fee_rate(), swap_for_y)?
+            };
+            amount_left -= if exact_in { step.amount_in_with_fees } else { step.amount_out };
+            amount_in = amount_in
+                .checked_add(step.amount_in_with_fees)
+                .ok_or_else(|| anyhow::anyhow!("Swap input overflow"))?;
+            amount_out += step.amount_out;
+            fee_total += step.fee;
+            if !step.exhausted {
+                break;
+            }
+        }
+
+        if amount_left > 0 {
+            let next = if swap_for_y { pair.active_id - 1 } else { pair.active_id + 1 };
+            if next < pair.parameters.min_bin_id || next > pair.parameters.max_bin_id {
+                anyhow::bail!("Insufficient liquidity: {} of {} left unfilled", amount_left, amount);
+            }
+            pair.active_id = next;
+        }
+    }
+
+    let mid = Price::from_x64(price_from_bin_id(lb_pair.active_id, lb_pair.bin_step)?);
+    let mid_out_per_in = if swap_for_y { mid } else { mid.invert()? };
+    Ok(SwapQuote {
+        amount_in,
+        amount_out,
+        fee_amount: fee_total,
+        price_impact_bps: price_impact_bps(mid_out_per_in, amount_in - fee_total, amount_out)?,
+        post_state: PostTradeState::Bins {
+            active_id: pair.active_id,
+            volatility_accumulator: pair.v_parameters.volatility_accumulator,
+        },
+        transfer_fees: TransferFees::default(),
+        observed: Observation::default(),
+    })
+}
+
+#[derive(Default)]
 pub struct MeteoraClient {}
+
+impl MeteoraClient {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    pub async fn get_lb_pair_price<B: RpcBackend + ?Sized>(
+        &self,
+        rpc: &B,
+        pool_address: &Pubkey,
+        mints: &MintRegistry,
+    ) -> Result<PoolPrice, PriceError> {
+        let account = rpc.get_account(pool_address).await?;
+        let lb_pair = LbPair::from_bytes(&account.data).map_err(|err| PriceError::layout(*pool_address, err))?;
+        let decimals = mints.resolve(rpc, &[lb_pair.token_x_mint, lb_pair.token_y_mint]).await?;
+        let raw = Price::from_x64(price_from_bin_id(lb_pair.active_id, lb_pair.bin_step)?);
+        Ok(PoolPrice::new(raw, decimals[0].decimals, decimals[1].decimals)?)
+    }
+}
+
+impl PoolQuoter for MeteoraClient {
+    fn dex(&self) -> DexType {
+        DexType::MeteoraDLMM
+    }
+
+    fn program_id(&self) -> Pubkey {
+        METEORA_DLMM_PROGRAM_ID
+    }
+
+    fn decode(&self, address: &Pubkey, data: &[u8]) -> Result<DecodedPool, PriceError> {
+        let lb_pair = LbPair::from_bytes(data).map_err(|err| PriceError::layout(*address, err))?;
+        Ok(DecodedPool {
+            address: *address,
+            dex: DexType::MeteoraDLMM,
+            mint_a: lb_pair.token_x_mint,
+            mint_b: lb_pair.token_y_mint,
+            state: PoolState::MeteoraDlmm(lb_pair),
+            observed: Observation::default(),
+        })
+    }
+
+    fn required_accounts(&self, _pool: &DecodedPool) -> Vec<Pubkey> {
+        // The active bin id alone determines the price
+        Vec::new()
+    }
+
+    fn quote_accounts(&self, pool: &DecodedPool) -> Vec<Pubkey> {
+        let PoolState::MeteoraDlmm(lb_pair) = &pool.state else {
+            return Vec::new();
+        };
+        bin_array_window(lb_pair)
+            .into_iter()
+            .map(|index| bin_array_address(&pool.address, index))
+            .collect()
+    }
+
+    fn mid_price(&self, pool: &DecodedPool, _accounts: &AccountMap) -> Result<Price, PriceError> {
+        let PoolState::MeteoraDlmm(lb_pair) = &pool.state else {
+            return Err(PriceError::layout(pool.address, "not a DLMM pair"));
+        };
+        Ok(Price::from_x64(price_from_bin_id(lb_pair.active_id, lb_pair.bin_step)?))
+    }
+
+    fn quote_exact_in(
+        &self,
+        pool: &DecodedPool,
+        accounts: &AccountMap,
+        amount_in: u64,
+        direction: SwapDirection,
+    ) -> Result<SwapQuote, PriceError> {
+        let PoolState::MeteoraDlmm(lb_pair) = &pool.state else {
+            return Err(PriceError::layout(pool.address, "not a DLMM pair"));
+        };
+        let bin_arrays = bin_arrays_from_accounts(&pool.address, lb_pair, accounts)?;
+        Ok(simulate_swap_exact_in(lb_pair, &bin_arrays, amount_in, direction, unix_timestamp() as i64)?)
+    }
+
+    fn quote_exact_out(
+        &self,
+        pool: &DecodedPool,
+        accounts: &AccountMap,
+        amount_out: u64,
+        direction: SwapDirection,
+    ) -> Result<SwapQuote, PriceError> {
+        let PoolState::MeteoraDlmm(lb_pair) = &pool.state else {
+            return Err(PriceError::layout(pool.address, "not a DLMM pair"));
+        };
+        let bin_arrays = bin_arrays_from_accounts(&pool.address, lb_pair, accounts)?;
+        Ok(simulate_swap_exact_out(lb_pair, &bin_arrays, amount_out, direction, unix_timestamp() as i64)?)
+    }
+}
+
+/// Bin arrays in the pair's quote window from pre-fetched accounts
+fn bin_arrays_from_accounts(
+    address: &Pubkey,
+    lb_pair: &LbPair,
+    accounts: &AccountMap,
+) -> Result<Vec<BinArray>, PriceError> {
+    let mut bin_arrays = Vec::new();
+    for index in bin_array_window(lb_pair) {
+        // Bin arrays that were never initialized hold no liquidity
+        let array_address = bin_array_address(address, index);
+        let Some(account) = accounts.get(&array_address) else {
+            bin_arrays.push(BinArray {
+                index,
+                version: 0,
+                padding: [0; 7],
+                lb_pair: *address,
+                bins: [Bin::default(); MAX_BIN_PER_ARRAY as usize],
+            });
+            continue;
+        };
+        let array = BinArray::from_bytes(&account.data).map_err(|err| PriceError::layout(array_address, err))?;
+        if array.lb_pair != *address || array.index != index {
+            return Err(PriceError::layout(array_address, format!("bin array {} does not belong to pair", index)));
+        }
+        bin_arrays.push(array);
+    }
+    Ok(bin_arrays)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::account::Account;
+
+    const BIN_SIZE: usize = 144;
+    /// Discriminator, index, version and padding, lb_pair
+    const BIN_ARRAY_BINS_OFFSET: usize = 8 + 8 + 8 + 32;
+
+    fn lb_pair(active_id: i32, bin_step: u16) -> LbPair {
+        let mut data = vec![0u8; 8 + 256];
+        data[..8].copy_from_slice(&LB_PAIR_DISCRIMINATOR);
+        let mut pair = LbPair::from_bytes(&data).unwrap();
+        pair.active_id = active_id;
+        pair.bin_step = bin_step;
+        pair.parameters.base_factor = 10_000;
+        pair.parameters.filter_period = 30;
+        pair.parameters.decay_period = 600;
+        pair.parameters.reduction_factor = 5_000;
+        pair.parameters.variable_fee_control = 40_000;
+        pair.parameters.max_volatility_accumulator = 350_000;
+        pair.parameters.min_bin_id = -443_636;
+        pair.parameters.max_bin_id = 443_636;
+        pair
+    }
+
+    /// Bin array account with (bin offset, amount_x, amount_y) filled in
+    fn bin_array_account(lb_pair: &Pubkey, index: i64, bins: &[(usize, u64, u64)]) -> Account {
+        let mut data = vec![0u8; BIN_ARRAY_BINS_OFFSET + MAX_BIN_PER_ARRAY as usize * BIN_SIZE];
+        data[..8].copy_from_slice(&BIN_ARRAY_DISCRIMINATOR);
+        data[8..16].copy_from_slice(&index.to_le_bytes());
+        data[24..56].copy_from_slice(lb_pair.as_ref());
+        for (offset, amount_x, amount_y) in bins {
+            let at = BIN_ARRAY_BINS_OFFSET + offset * BIN_SIZE;
+            data[at..at + 8].copy_from_slice(&amount_x.to_le_bytes());
+            data[at + 8..at + 16].copy_from_slice(&amount_y.to_le_bytes());
+        }
+        Account {
+            data,
+            owner: METEORA_DLMM_PROGRAM_ID,
+            ..Account::default()
+        }
+    }
+
+    #[test]
+    fn test_price_from_bin_id() {
+        assert_eq!(price_from_bin_id(0, 10).unwrap(), ONE_X64);
+        let up = price_from_bin_id(100, 10).unwrap() as f64 / ONE_X64 as f64;
+        let down = price_from_bin_id(-100, 10).unwrap() as f64 / ONE_X64 as f64;
+        assert!((up - 1.001f64.powi(100)).abs() < 1e-12);
+        assert!((down - 1.001f64.powi(-100)).abs() < 1e-12);
+        assert!(price_from_bin_id(MAX_EXPONENTIAL as i32, 10).is_err());
+    }
+
+    #[test]
+    fn test_fee_rates() {
+        let mut pair = lb_pair(0, 10);
+        // base_factor 10_000 * bin_step 10 * 10 = 0.1%
+        assert_eq!(pair.base_fee_rate(), 1_000_000);
+        assert_eq!(pair.variable_fee_rate(), 0);
+
+        // Crossing 5 bins from the reference accumulates 50_000 volatility
+        pair.active_id = 5;
+        pair.update_volatility_accumulator();
+        assert_eq!(pair.v_parameters.volatility_accumulator, 50_000);
+        // 40_000 * (50_000 * 10)^2 / 1e11 = 100_000
+        assert_eq!(pair.variable_fee_rate(), 100_000);
+        assert_eq!(pair.total_fee_rate(), 1_100_000);
+
+        // Within the filter period the reference bin is kept
+        pair.v_parameters.last_update_timestamp = 1_000;
+        pair.update_references(1_010);
+        assert_eq!(pair.v_parameters.index_reference, 0);
+        // After it, the reference moves and decays by the reduction factor
+        pair.update_references(1_100);
+        assert_eq!(pair.v_parameters.index_reference, 5);
+        assert_eq!(pair.v_parameters.volatility_reference, 25_000);
+    }
+
+    #[test]
+    fn test_swap_across_bins() {
+        let address = Pubkey::new_unique();
+        let pair = lb_pair(0, 10);
+        let pool = DecodedPool {
+            address,
+            dex: DexType::MeteoraDLMM,
+            mint_a: pair.token_x_mint,
+            mint_b: pair.token_y_mint,
+            state: PoolState::MeteoraDlmm(pair.clone()),
+            observed: Observation::default(),
+        };
+        let client = MeteoraClient::new();
+        assert_eq!(client.quote_accounts(&pool).len(), 5);
+
+        // 1_000 Y in the active bin, 1_000 Y in the bin below, which sits in the previous array
+        let mut accounts = AccountMap::new();
+        accounts.insert(bin_array_address(&address, 0), bin_array_account(&address, 0, &[(0, 0, 1_000)]));
+        accounts.insert(
+            bin_array_address(&address, -1),
+            bin_array_account(&address, -1, &[(69, 0, 1_000)]),
+        );
+
+        // Small swap stays in the active bin at price 1.0 and a 0.1% fee
+        let small = client.quote_exact_in(&pool, &accounts, 500, SwapDirection::AToB).unwrap();
+        assert_eq!(small.fee_amount, 1);
+        assert_eq!(small.amount_out, 499);
+        assert_eq!(small.post_state, PostTradeState::Bins { active_id: 0, volatility_accumulator: 0 });
+
+        // Exactly the active bin's capacity is still a partial fill, as in the program
+        let bin = Bin { amount_y: 1_000, ..Bin::default() };
+        let exact = swap_in_bin(&bin, ONE_X64, 1_002, pair.total_fee_rate(), true).unwrap();
+        assert!(!exact.exhausted);
+        assert_eq!((exact.amount_out, exact.fee), (1_000, 2));
+        assert!(swap_in_bin(&bin, ONE_X64, 1_003, pair.total_fee_rate(), true).unwrap().exhausted);
+
+        // Larger swap drains the active bin and continues one bin down at a higher fee
+        let large = client.quote_exact_in(&pool, &accounts, 1_500, SwapDirection::AToB).unwrap();
+        assert_eq!(large.amount_in, 1_500);
+        assert!(large.amount_out > 1_000 && large.amount_out < 1_500);
+        let PostTradeState::Bins { active_id, volatility_accumulator } = large.post_state else {
+            panic!("expected bin post-trade state");
+        };
+        assert_eq!(active_id, -1);
+        assert_eq!(volatility_accumulator, 10_000);
+        assert!(large.price_impact_bps > 0.0);
+
+        // Only bins within the fetched window can be used
+        assert!(client.quote_exact_in(&pool, &accounts, 3_000, SwapDirection::AToB).is_err());
+    }
+
+    #[test]
+    fn test_swap_exact_out() {
+        let address = Pubkey::new_unique();
+        let pair = lb_pair(0, 10);
+        let pool = DecodedPool {
+            address,
+            dex: DexType::MeteoraDLMM,
+            mint_a: pair.token_x_mint,
+            mint_b: pair.token_y_mint,
+            state: PoolState::MeteoraDlmm(pair),
+            observed: Observation::default(),
+        };
+        let client = MeteoraClient::new();
+        let mut accounts = AccountMap::new();
+        accounts.insert(bin_array_address(&address, 0), bin_array_account(&address, 0, &[(0, 0, 1_000)]));
+        accounts.insert(
+            bin_array_address(&address, -1),
+            bin_array_account(&address, -1, &[(69, 0, 1_000)]),
+        );
+
+        // Mirrors the exact-in quote: 499 out costs 499 plus a fee of 1
+        let small = client.quote_exact_out(&pool, &accounts, 499, SwapDirection::AToB).unwrap();
+        assert_eq!((small.amount_in, small.amount_out, small.fee_amount), (500, 499, 1));
+        assert_eq!(small.post_state, PostTradeState::Bins { active_id: 0, volatility_accumulator: 0 });
+
+        // Draining the active bin moves on to the next one
+        let large = client.quote_exact_out(&pool, &accounts, 1_500, SwapDirection::AToB).unwrap();
+        assert_eq!(large.amount_out, 1_500);
+        assert!(matches!(large.post_state, PostTradeState::Bins { active_id: -1, .. }));
+        let back = client.quote_exact_in(&pool, &accounts, large.amount_in, SwapDirection::AToB).unwrap();
+        assert!(back.amount_out >= 1_500);
+
+        assert!(client.quote_exact_out(&pool, &accounts, 2_001, SwapDirection::AToB).is_err());
+    }
+}
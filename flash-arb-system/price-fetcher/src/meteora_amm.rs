@@ -0,0 +1,683 @@
+use anyhow::Result;
+use borsh::BorshDeserialize;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::error::PriceError;
+use crate::freshness::Observation;
+use crate::lst::{MarinadeState, StakePoolState, MARINADE_PROGRAM_ID, MARINADE_STATE, SPL_STAKE_POOL_PROGRAM_ID};
+use crate::price::Price;
+use crate::quoter::{
+    price_impact_bps, required_account, unix_timestamp, AccountMap, DecodedPool, DexType, PoolQuoter, PoolState,
+    PostTradeState, SwapDirection, SwapQuote, TransferFees,
+};
+use crate::stable_swap;
+use crate::token::{mint_supply, vault_amount};
+
+/// Meteora dynamic AMM program
+pub const METEORA_AMM_PROGRAM_ID: Pubkey = solana_sdk::pubkey!("Eo7WjKq67rjJQSZxS6z3YkapzY3eMj6Xy8X5EQVn5UaB");
+/// Meteora dynamic vault program, which holds the AMM's reserves
+pub const METEORA_VAULT_PROGRAM_ID: Pubkey = solana_sdk::pubkey!("24Uqj9JCLxUeoC3hGfh5W3s9FM9uCHDS2SG3LYwBpyTi");
+
+pub(crate) const POOL_DISCRIMINATOR: [u8; 8] = [241, 154, 109, 4, 17, 177, 109, 188];
+const VAULT_DISCRIMINATOR: [u8; 8] = [211, 8, 232, 43, 2, 152, 117, 119];
+
+/// Depeg virtual prices are scaled by 1e6
+const VIRTUAL_PRICE_PRECISION: u128 = 1_000_000;
+/// Seconds a depeg pool's cached virtual price is used before the program
+/// re-reads it from the stake pool
+const BASE_CACHE_EXPIRES: u64 = 60 * 10;
+
+/// Locked profit degrades linearly at `locked_profit_degradation / 1e12` per second
+const LOCKED_PROFIT_DEGRADATION_DENOMINATOR: u128 = 1_000_000_000_000;
+
+#[derive(BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolFees {
+    pub trade_fee_numerator: u64,
+    pub trade_fee_denominator: u64,
+    pub protocol_trade_fee_numerator: u64,
+    pub protocol_trade_fee_denominator: u64,
+}
+
+impl PoolFees {
+    /// Total trading fee on `amount_in`, protocol share included
+    pub fn trading_fee(&self, amount_in: u64) -> Result<u64> {
+        if self.trade_fee_denominator == 0 {
+            anyhow::bail!("Pool has a zero trade fee denominator");
+        }
+        Ok((amount_in as u128 * self.trade_fee_numerator as u128 / self.trade_fee_denominator as u128) as u64)
+    }
+}
+
+#[derive(BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PoolType {
+    Permissioned,
+    Permissionless,
+}
+
+/// Multipliers that scale both tokens to a common precision for the stable curve
+#[derive(BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenMultiplier {
+    pub token_a_multiplier: u64,
+    pub token_b_multiplier: u64,
+    pub precision_factor: u8,
+}
+
+#[derive(BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DepegType {
+    None,
+    Marinade,
+    Lido,
+    SplStake,
+}
+
+/// Virtual price cache for pools pairing a token with its liquid-staked form
+#[derive(BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Depeg {
+    /// SOL per token B, scaled by 1e6
+    pub base_virtual_price: u64,
+    /// Unix time the virtual price was cached at
+    pub base_cache_updated: u64,
+    pub depeg_type: DepegType,
+}
+
+impl Depeg {
+    /// Stake account the virtual price is read from: Marinade's state, or
+    /// the SPL stake pool recorded in the pool's `stake` field
+    pub fn stake_account(&self, pool: &DynamicAmmPool) -> Option<Pubkey> {
+        match self.depeg_type {
+            DepegType::None | DepegType::Lido => None,
+            DepegType::Marinade => Some(MARINADE_STATE),
+            DepegType::SplStake => Some(pool.stake),
+        }
+    }
+
+    /// Virtual price the program swaps at `now`: the cached one until it
+    /// expires, then the stake pool's current redemption rate
+    pub fn virtual_price(&self, pool: &DynamicAmmPool, accounts: &AccountMap, now: u64) -> Result<u64, PriceError> {
+        if now <= self.base_cache_updated.saturating_add(BASE_CACHE_EXPIRES) {
+            return Ok(self.base_virtual_price);
+        }
+        let Some(address) = self.stake_account(pool) else {
+            return Err(anyhow::anyhow!("{:?} depeg pools cannot refresh their virtual price", self.depeg_type).into());
+        };
+        let account = required_account(accounts, &address)?;
+        let (numerator, denominator) = match (self.depeg_type, account.owner) {
+            (DepegType::Marinade, MARINADE_PROGRAM_ID) => {
+                let state = MarinadeState::from_bytes(&account.data).map_err(|err| PriceError::layout(address, err))?;
+                (state.msol_price as u128, 1u128 << 32)
+            }
+            (DepegType::SplStake, SPL_STAKE_POOL_PROGRAM_ID) => {
+                let stake_pool = StakePoolState::from_bytes(&account.data).map_err(|err| PriceError::layout(address, err))?;
+                (stake_pool.total_lamports as u128, stake_pool.pool_token_supply as u128)
+            }
+            (_, owner) => return Err(PriceError::WrongOwner { account: address, owner }),
+        };
+        if denominator == 0 {
+            return Err(PriceError::EmptyPool);
+        }
+        u64::try_from(numerator * VIRTUAL_PRICE_PRECISION / denominator)
+            .map_err(|_| anyhow::anyhow!("Virtual price overflow").into())
+    }
+}
+
+#[derive(BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CurveType {
+    ConstantProduct,
+    Stable {
+        amp: u64,
+        token_multiplier: TokenMultiplier,
+        depeg: Depeg,
+        last_amp_updated_timestamp: u64,
+    },
+}
+
+/// Dynamic AMM `Pool` account (after the discriminator)
+#[derive(BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DynamicAmmPool {
+    pub lp_mint: Pubkey,
+    pub token_a_mint: Pubkey,
+    pub token_b_mint: Pubkey,
+    pub a_vault: Pubkey,
+    pub b_vault: Pubkey,
+    /// Pool's token accounts for the vaults' LP shares
+    pub a_vault_lp: Pubkey,
+    pub b_vault_lp: Pubkey,
+    pub a_vault_lp_bump: u8,
+    pub enabled: bool,
+    pub protocol_token_a_fee: Pubkey,
+    pub protocol_token_b_fee: Pubkey,
+    pub fee_last_updated_at: u64,
+    pub padding0: [u8; 24],
+    pub fees: PoolFees,
+    pub pool_type: PoolType,
+    pub stake: Pubkey,
+    pub total_locked_lp: u64,
+    /// Activation point, whitelisted vault, creator and activation type
+    pub bootstrapping: [u8; 73],
+    /// Partner fee numerator, authority and pending fees
+    pub partner_info: [u8; 56],
+    pub padding: [u8; 342],
+    pub curve_type: CurveType,
+}
+
+impl DynamicAmmPool {
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        if data.len() < 8 || data[..8] != POOL_DISCRIMINATOR {
+            anyhow::bail!("Not a Meteora dynamic AMM pool account");
+        }
+        Ok(Self::deserialize(&mut &data[8..])?)
+    }
+}
+
+#[derive(BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LockedProfitTracker {
+    pub last_updated_locked_profit: u64,
+    pub last_report: u64,
+    pub locked_profit_degradation: u64,
+}
+
+/// Dynamic vault account (after the discriminator)
+#[derive(BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Vault {
+    pub enabled: u8,
+    pub vault_bump: u8,
+    pub token_vault_bump: u8,
+    /// Tokens held by the vault and its lending strategies
+    pub total_amount: u64,
+    pub token_vault: Pubkey,
+    pub fee_vault: Pubkey,
+    pub token_mint: Pubkey,
+    pub lp_mint: Pubkey,
+    pub strategies: [Pubkey; 30],
+    pub base: Pubkey,
+    pub admin: Pubkey,
+    pub operator: Pubkey,
+    pub locked_profit_tracker: LockedProfitTracker,
+}
+
+impl Vault {
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        if data.len() < 8 || data[..8] != VAULT_DISCRIMINATOR {
+            anyhow::bail!("Not a Meteora vault account");
+        }
+        Ok(Self::deserialize(&mut &data[8..])?)
+    }
+
+    /// Strategy profit that has not finished unlocking at `now`
+    fn locked_profit(&self, now: u64) -> u64 {
+        let tracker = &self.locked_profit_tracker;
+        let elapsed = now.saturating_sub(tracker.last_report) as u128;
+        let locked_ratio = elapsed * tracker.locked_profit_degradation as u128;
+        if locked_ratio > LOCKED_PROFIT_DEGRADATION_DENOMINATOR {
+            return 0;
+        }
+        (tracker.last_updated_locked_profit as u128 * (LOCKED_PROFIT_DEGRADATION_DENOMINATOR - locked_ratio)
+            / LOCKED_PROFIT_DEGRADATION_DENOMINATOR) as u64
+    }
+
+    /// Tokens backing vault LP shares at `now`
+    pub fn unlocked_amount(&self, now: u64) -> u64 {
+        self.total_amount.saturating_sub(self.locked_profit(now))
+    }
+}
+
+/// LP mint PDA of a vault
+pub fn vault_lp_mint_address(vault: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"lp_mint", vault.as_ref()], &METEORA_VAULT_PROGRAM_ID).0
+}
+
+/// The pool's token A and B reserves: its share of each vault's unlocked amount
+pub fn vault_reserves(pool: &DynamicAmmPool, accounts: &AccountMap, now: u64) -> Result<(u64, u64), PriceError> {
+    let reserve = |vault_address: &Pubkey, vault_lp: &Pubkey| -> Result<u64, PriceError> {
+        let vault_account = required_account(accounts, vault_address)?;
+        let vault = Vault::from_bytes(&vault_account.data).map_err(|err| PriceError::layout(*vault_address, err))?;
+        let lp_amount = vault_amount(accounts, vault_lp)?;
+        let lp_supply = mint_supply(required_account(accounts, &vault.lp_mint)?)
+            .ok_or_else(|| PriceError::layout(vault.lp_mint, "not a mint"))?;
+        if lp_supply == 0 {
+            return Ok(0);
+        }
+        Ok((lp_amount as u128 * vault.unlocked_amount(now) as u128 / lp_supply as u128) as u64)
+    };
+
+    Ok((reserve(&pool.a_vault, &pool.a_vault_lp)?, reserve(&pool.b_vault, &pool.b_vault_lp)?))
+}
+
+/// Constant-product output, rounding the new destination reserve up
+fn constant_product_out(amount_in: u64, reserve_in: u64, reserve_out: u64) -> Result<u64> {
+    let invariant = reserve_in as u128 * reserve_out as u128;
+    let new_reserve_in = reserve_in as u128 + amount_in as u128;
+    if invariant / new_reserve_in == 0 {
+        anyhow::bail!("Swap would drain the pool");
+    }
+    let new_reserve_out = invariant.div_ceil(new_reserve_in);
+    Ok((reserve_out as u128 - new_reserve_out) as u64)
+}
+
+/// Scaling of one token's raw amounts onto the stable curve: multiplied by
+/// `numerator / denominator`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct StableScale {
+    numerator: u128,
+    denominator: u128,
+}
+
+impl StableScale {
+    /// Scale of each token. Token B of a depeg pool is the staked token and
+    /// is also weighted by its virtual price, so the curve balances SOL value.
+    fn of_pool(token_multiplier: &TokenMultiplier, depeg: &Depeg) -> Result<(Self, Self)> {
+        let scale_a = Self {
+            numerator: token_multiplier.token_a_multiplier as u128,
+            denominator: 1,
+        };
+        let scale_b = match depeg.depeg_type {
+            DepegType::None => Self {
+                numerator: token_multiplier.token_b_multiplier as u128,
+                denominator: 1,
+            },
+            _ if depeg.base_virtual_price == 0 => anyhow::bail!("Depeg pool has no virtual price"),
+            _ => Self {
+                numerator: token_multiplier.token_b_multiplier as u128 * depeg.base_virtual_price as u128,
+                denominator: VIRTUAL_PRICE_PRECISION,
+            },
+        };
+        if scale_a.numerator == 0 || scale_b.numerator == 0 {
+            anyhow::bail!("Stable pool has a zero token multiplier");
+        }
+        Ok((scale_a, scale_b))
+    }
+
+    fn upscale(&self, amount: u64) -> Result<u128> {
+        let scaled = (amount as u128).checked_mul(self.numerator);
+        scaled
+            .map(|scaled| scaled / self.denominator)
+            .ok_or_else(|| anyhow::anyhow!("Stable curve amount overflow"))
+    }
+
+    fn downscale(&self, amount: u128) -> Result<u64> {
+        let raw = amount.checked_mul(self.denominator).map(|raw| raw / self.numerator);
+        raw.and_then(|raw| u64::try_from(raw).ok())
+            .ok_or_else(|| anyhow::anyhow!("Swap output overflow"))
+    }
+}
+
+/// Stable-curve output with both sides scaled to a common precision
+fn stable_out(amp: u64, amount_in: u64, reserve_in: u64, reserve_out: u64, scale_in: StableScale, scale_out: StableScale) -> Result<u64> {
+    let scaled_out = stable_swap::swap_exact_in(
+        amp,
+        scale_in.upscale(amount_in)?,
+        scale_in.upscale(reserve_in)?,
+        scale_out.upscale(reserve_out)?,
+    )?;
+    scale_out.downscale(scaled_out)
+}
+
+/// Marginal stable-curve price of the input token in output units, before fees
+fn stable_mid_price(amp: u64, reserve_in: u64, reserve_out: u64, scale_in: StableScale, scale_out: StableScale) -> Result<Price> {
+    let scaled = stable_swap::marginal_price(amp, scale_in.upscale(reserve_in)?, scale_out.upscale(reserve_out)?)?;
+    scaled
+        .mul_ratio(scale_in.numerator, scale_in.denominator)?
+        .mul_ratio(scale_out.denominator, scale_out.numerator)
+}
+
+/// Quote an exact-in swap against the pool's vault-backed reserves.
+///
+/// Depeg pools are priced at the cached virtual price in `pool`; use
+/// [`with_virtual_price`] first to apply the one the program would use.
+/// Vault deposits and withdrawals round through LP shares on chain, so the
+/// output can differ from this quote by a unit either way.
+pub fn quote_exact_in(
+    pool: &DynamicAmmPool,
+    reserve_a: u64,
+    reserve_b: u64,
+    amount_in: u64,
+    direction: SwapDirection,
+) -> Result<SwapQuote> {
+    if !pool.enabled {
+        anyhow::bail!("Pool is disabled");
+    }
+    let (reserve_in, reserve_out) = match direction {
+        SwapDirection::AToB => (reserve_a, reserve_b),
+        SwapDirection::BToA => (reserve_b, reserve_a),
+    };
+    if reserve_in == 0 || reserve_out == 0 {
+        anyhow::bail!(PriceError::EmptyPool);
+    }
+
+    let fee = pool.fees.trading_fee(amount_in)?;
+    let amount_after_fee = amount_in - fee;
+    let (amount_out, mid_out_per_in) = match &pool.curve_type {
+        CurveType::ConstantProduct => (
+            constant_product_out(amount_after_fee, reserve_in, reserve_out)?,
+            Price::from_ratio(reserve_out as u128, reserve_in as u128)?,
+        ),
+        CurveType::Stable { amp, token_multiplier, depeg, .. } => {
+            let (scale_a, scale_b) = StableScale::of_pool(token_multiplier, depeg)?;
+            let (scale_in, scale_out) = match direction {
+                SwapDirection::AToB => (scale_a, scale_b),
+                SwapDirection::BToA => (scale_b, scale_a),
+            };
+            (
+                stable_out(*amp, amount_after_fee, reserve_in, reserve_out, scale_in, scale_out)?,
+                stable_mid_price(*amp, reserve_in, reserve_out, scale_in, scale_out)?,
+            )
+        }
+    };
+
+    let reserve_in_after = reserve_in
+        .checked_add(amount_in)
+        .ok_or_else(|| anyhow::anyhow!("Swap input overflows the pool reserve"))?;
+    let (reserve_a, reserve_b) = match direction {
+        SwapDirection::AToB => (reserve_in_after, reserve_b - amount_out),
+        SwapDirection::BToA => (reserve_a - amount_out, reserve_in_after),
+    };
+    Ok(SwapQuote {
+        amount_in,
+        amount_out,
+        fee_amount: fee,
+        price_impact_bps: price_impact_bps(mid_out_per_in, amount_after_fee, amount_out)?,
+        post_state: PostTradeState::Reserves { reserve_a, reserve_b },
+        transfer_fees: TransferFees::default(),
+        observed: Observation::default(),
+    })
+}
+
+/// `pool` with its depeg virtual price brought up to date as of `now`, the
+/// way the program refreshes it at the start of a swap
+pub fn with_virtual_price(pool: &DynamicAmmPool, accounts: &AccountMap, now: u64) -> Result<DynamicAmmPool, PriceError> {
+    let mut updated = pool.clone();
+    if let CurveType::Stable { depeg, .. } = &mut updated.curve_type {
+        if depeg.depeg_type != DepegType::None {
+            depeg.base_virtual_price = depeg.virtual_price(pool, accounts, now)?;
+        }
+    }
+    Ok(updated)
+}
+
+#[derive(Default)]
+pub struct MeteoraAmmClient {}
+
+impl MeteoraAmmClient {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl PoolQuoter for MeteoraAmmClient {
+    fn dex(&self) -> DexType {
+        DexType::MeteoraAmm
+    }
+
+    fn program_id(&self) -> Pubkey {
+        METEORA_AMM_PROGRAM_ID
+    }
+
+    fn decode(&self, address: &Pubkey, data: &[u8]) -> Result<DecodedPool, PriceError> {
+        let pool = DynamicAmmPool::from_bytes(data).map_err(|err| PriceError::layout(*address, err))?;
+        Ok(DecodedPool {
+            address: *address,
+            dex: DexType::MeteoraAmm,
+            mint_a: pool.token_a_mint,
+            mint_b: pool.token_b_mint,
+            state: PoolState::MeteoraAmm(pool),
+            observed: Observation::default(),
+        })
+    }
+
+    fn required_accounts(&self, pool: &DecodedPool) -> Vec<Pubkey> {
+        let PoolState::MeteoraAmm(amm) = &pool.state else {
+            return Vec::new();
+        };
+        let mut accounts = vec![
+            amm.a_vault,
+            amm.b_vault,
+            amm.a_vault_lp,
+            amm.b_vault_lp,
+            vault_lp_mint_address(&amm.a_vault),
+            vault_lp_mint_address(&amm.b_vault),
+        ];
+        if let CurveType::Stable { depeg, .. } = &amm.curve_type {
+            accounts.extend(depeg.stake_account(amm));
+        }
+        accounts
+    }
+
+    fn mid_price(&self, pool: &DecodedPool, accounts: &AccountMap) -> Result<Price, PriceError> {
+        let PoolState::MeteoraAmm(amm) = &pool.state else {
+            return Err(PriceError::layout(pool.address, "not a Meteora dynamic AMM pool"));
+        };
+        let now = unix_timestamp();
+        let (reserve_a, reserve_b) = vault_reserves(amm, accounts, now)?;
+        if reserve_a == 0 || reserve_b == 0 {
+            return Err(PriceError::EmptyPool);
+        }
+        match &with_virtual_price(amm, accounts, now)?.curve_type {
+            CurveType::ConstantProduct => Ok(Price::from_ratio(reserve_b as u128, reserve_a as u128)?),
+            CurveType::Stable { amp, token_multiplier, depeg, .. } => {
+                let (scale_a, scale_b) = StableScale::of_pool(token_multiplier, depeg)?;
+                Ok(stable_mid_price(*amp, reserve_a, reserve_b, scale_a, scale_b)?)
+            }
+        }
+    }
+
+    fn quote_exact_in(
+        &self,
+        pool: &DecodedPool,
+        accounts: &AccountMap,
+        amount_in: u64,
+        direction: SwapDirection,
+    ) -> Result<SwapQuote, PriceError> {
+        let PoolState::MeteoraAmm(amm) = &pool.state else {
+            return Err(PriceError::layout(pool.address, "not a Meteora dynamic AMM pool"));
+        };
+        let now = unix_timestamp();
+        let (reserve_a, reserve_b) = vault_reserves(amm, accounts, now)?;
+        let amm = with_virtual_price(amm, accounts, now)?;
+        Ok(quote_exact_in(&amm, reserve_a, reserve_b, amount_in, direction)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::token_account;
+    use crate::token::SPL_TOKEN_PROGRAM_ID;
+    use solana_sdk::account::Account;
+
+    /// Serialized pool size without the curve, followed by a stable curve
+    const POOL_BODY_SIZE: usize = 866;
+    const VAULT_BODY_SIZE: usize = 1219;
+
+    fn pool(curve: CurveType) -> DynamicAmmPool {
+        let mut data = vec![0u8; 8 + POOL_BODY_SIZE + 64];
+        data[..8].copy_from_slice(&POOL_DISCRIMINATOR);
+        let mut pool = DynamicAmmPool::from_bytes(&data).unwrap();
+        pool.enabled = true;
+        pool.a_vault = Pubkey::new_unique();
+        pool.b_vault = Pubkey::new_unique();
+        pool.a_vault_lp = Pubkey::new_unique();
+        pool.b_vault_lp = Pubkey::new_unique();
+        // 0.25%
+        pool.fees.trade_fee_numerator = 250;
+        pool.fees.trade_fee_denominator = 100_000;
+        pool.curve_type = curve;
+        pool
+    }
+
+    fn stable_curve(amp: u64) -> CurveType {
+        depeg_curve(amp, DepegType::None, 0, 0)
+    }
+
+    fn depeg_curve(amp: u64, depeg_type: DepegType, base_virtual_price: u64, base_cache_updated: u64) -> CurveType {
+        CurveType::Stable {
+            amp,
+            token_multiplier: TokenMultiplier {
+                token_a_multiplier: 1,
+                token_b_multiplier: 1,
+                precision_factor: 6,
+            },
+            depeg: Depeg {
+                base_virtual_price,
+                base_cache_updated,
+                depeg_type,
+            },
+            last_amp_updated_timestamp: 0,
+        }
+    }
+
+    /// SPL stake pool holding `total_lamports` against `supply` pool tokens
+    fn stake_pool_account(total_lamports: u64, supply: u64) -> Account {
+        let mut data = vec![0u8; 611];
+        data[0] = 1;
+        data[258..266].copy_from_slice(&total_lamports.to_le_bytes());
+        data[266..274].copy_from_slice(&supply.to_le_bytes());
+        Account {
+            data,
+            owner: SPL_STAKE_POOL_PROGRAM_ID,
+            ..Account::default()
+        }
+    }
+
+    fn vault_account(total_amount: u64, lp_mint: &Pubkey, locked_profit: u64, last_report: u64) -> Account {
+        let mut data = vec![0u8; 8 + VAULT_BODY_SIZE];
+        data[..8].copy_from_slice(&VAULT_DISCRIMINATOR);
+        data[11..19].copy_from_slice(&total_amount.to_le_bytes());
+        data[115..147].copy_from_slice(lp_mint.as_ref());
+        let tracker = 8 + VAULT_BODY_SIZE - 24;
+        data[tracker..tracker + 8].copy_from_slice(&locked_profit.to_le_bytes());
+        data[tracker + 8..tracker + 16].copy_from_slice(&last_report.to_le_bytes());
+        // Fully unlocks over 1_000 seconds
+        data[tracker + 16..tracker + 24].copy_from_slice(&1_000_000_000u64.to_le_bytes());
+        Account {
+            data,
+            owner: METEORA_VAULT_PROGRAM_ID,
+            ..Account::default()
+        }
+    }
+
+    fn mint_account(supply: u64) -> Account {
+        let mut data = vec![0u8; 82];
+        data[36..44].copy_from_slice(&supply.to_le_bytes());
+        Account {
+            data,
+            owner: SPL_TOKEN_PROGRAM_ID,
+            ..Account::default()
+        }
+    }
+
+    #[test]
+    fn test_vault_reserves_use_unlocked_share() {
+        let pool = pool(CurveType::ConstantProduct);
+        let lp_mint_a = vault_lp_mint_address(&pool.a_vault);
+        let lp_mint_b = vault_lp_mint_address(&pool.b_vault);
+
+        let mut accounts = AccountMap::new();
+        // Vault A holds 1_100 with 100 of fresh profit still locked; the pool owns half its LP
+        accounts.insert(pool.a_vault, vault_account(1_100, &lp_mint_a, 100, 1_000));
+        accounts.insert(pool.a_vault_lp, token_account(500));
+        accounts.insert(lp_mint_a, mint_account(1_000));
+        accounts.insert(pool.b_vault, vault_account(4_000, &lp_mint_b, 0, 0));
+        accounts.insert(pool.b_vault_lp, token_account(1_000));
+        accounts.insert(lp_mint_b, mint_account(1_000));
+
+        assert_eq!(vault_reserves(&pool, &accounts, 1_000).unwrap(), (500, 4_000));
+        // Halfway through the unlock period half the profit counts
+        assert_eq!(vault_reserves(&pool, &accounts, 1_500).unwrap(), (525, 4_000));
+        assert_eq!(vault_reserves(&pool, &accounts, 5_000).unwrap(), (550, 4_000));
+
+        accounts.remove(&lp_mint_b);
+        assert!(vault_reserves(&pool, &accounts, 1_000).is_err());
+    }
+
+    #[test]
+    fn test_constant_product_quote() {
+        let pool = pool(CurveType::ConstantProduct);
+        let quote = quote_exact_in(&pool, 1_000_000, 2_000_000, 10_000, SwapDirection::AToB).unwrap();
+        assert_eq!(quote.fee_amount, 25);
+        // 2e6 - ceil(2e12 / 1_009_975)
+        assert_eq!(quote.amount_out, 2_000_000 - 1_980_248);
+        assert_eq!(
+            quote.post_state,
+            PostTradeState::Reserves { reserve_a: 1_010_000, reserve_b: 1_980_248 }
+        );
+    }
+
+    #[test]
+    fn test_stable_quote_beats_constant_product() {
+        let stable = pool(stable_curve(100));
+        let constant = pool(CurveType::ConstantProduct);
+        let reserves = (5_000_000_000_000, 5_000_000_000_000);
+        let amount = 100_000_000_000;
+
+        let stable_quote = quote_exact_in(&stable, reserves.0, reserves.1, amount, SwapDirection::AToB).unwrap();
+        let constant_quote = quote_exact_in(&constant, reserves.0, reserves.1, amount, SwapDirection::AToB).unwrap();
+        assert!(stable_quote.amount_out > constant_quote.amount_out);
+        assert!(stable_quote.amount_out < amount - stable_quote.fee_amount);
+        // A 2% trade with amp 100 moves the price by about 2 bps
+        assert!(stable_quote.price_impact_bps < 5.0);
+
+        // The curve is symmetric for balanced reserves
+        let reverse = quote_exact_in(&stable, reserves.0, reserves.1, amount, SwapDirection::BToA).unwrap();
+        assert_eq!(reverse.amount_out, stable_quote.amount_out);
+
+        // Small pools still get an exact marginal price
+        let unit = StableScale { numerator: 1, denominator: 1 };
+        assert_eq!(stable_mid_price(100, 1_000, 1_000, unit, unit).unwrap(), Price::ONE);
+        let skewed = stable_mid_price(100, 1_500_000, 500_000, unit, unit).unwrap();
+        assert!(skewed < Price::ONE && skewed > Price::from_ratio(98, 100).unwrap());
+        // Multipliers convert scaled prices back to raw units, e.g. 6 vs 9 decimals
+        let six_decimals = StableScale { numerator: 1_000, denominator: 1 };
+        assert_eq!(stable_mid_price(100, 1_000_000, 1_000_000_000, six_decimals, unit).unwrap(), Price::from_integer(1_000));
+
+        // Inputs that would overflow the reserve are rejected rather than wrapped
+        assert!(quote_exact_in(&constant, u64::MAX - 10, 1_000, 100, SwapDirection::AToB).is_err());
+    }
+
+    #[test]
+    fn test_depeg_pool_uses_virtual_price() {
+        // SOL against an LST worth 1.1 SOL, cached at t = 1_000
+        let mut depeg = pool(depeg_curve(100, DepegType::SplStake, 1_100_000, 1_000));
+        depeg.stake = Pubkey::new_unique();
+        let client = MeteoraAmmClient::new();
+        let decoded = DecodedPool {
+            address: Pubkey::new_unique(),
+            dex: DexType::MeteoraAmm,
+            mint_a: depeg.token_a_mint,
+            mint_b: depeg.token_b_mint,
+            state: PoolState::MeteoraAmm(depeg.clone()),
+            observed: Observation::default(),
+        };
+        assert_eq!(client.required_accounts(&decoded).last(), Some(&depeg.stake));
+
+        // Reserves worth the same in SOL sit at the balance point of the curve
+        let reserves = (1_100_000_000_000, 1_000_000_000_000);
+        let quote = quote_exact_in(&depeg, reserves.0, reserves.1, 1_100_000, SwapDirection::AToB).unwrap();
+        assert_eq!(quote.fee_amount, 2_750);
+        assert!(quote.amount_out.abs_diff((1_100_000 - 2_750) * 10 / 11) <= 1);
+        let CurveType::Stable { token_multiplier, depeg: cached, .. } = &depeg.curve_type else { unreachable!() };
+        let (scale_a, scale_b) = StableScale::of_pool(token_multiplier, cached).unwrap();
+        assert_eq!(
+            stable_mid_price(100, reserves.0, reserves.1, scale_a, scale_b).unwrap(),
+            Price::from_ratio(10, 11).unwrap()
+        );
+
+        // The cache is used for ten minutes, then re-read from the stake pool
+        let mut accounts = AccountMap::new();
+        assert_eq!(cached.virtual_price(&depeg, &accounts, 1_600).unwrap(), 1_100_000);
+        assert!(matches!(
+            cached.virtual_price(&depeg, &accounts, 1_601),
+            Err(PriceError::AccountMissing(key)) if key == depeg.stake
+        ));
+        accounts.insert(depeg.stake, stake_pool_account(1_125_000_000, 1_000_000_000));
+        let refreshed = with_virtual_price(&depeg, &accounts, 1_601).unwrap();
+        let CurveType::Stable { depeg: refreshed, .. } = refreshed.curve_type else { unreachable!() };
+        assert_eq!(refreshed.base_virtual_price, 1_125_000);
+
+        // Lido pools can only use their cache
+        let lido = pool(depeg_curve(100, DepegType::Lido, 1_050_000, 1_000));
+        assert!(with_virtual_price(&lido, &accounts, 1_500).is_ok());
+        assert!(with_virtual_price(&lido, &accounts, 5_000).is_err());
+        let unset = pool(depeg_curve(100, DepegType::Marinade, 0, 0));
+        assert!(quote_exact_in(&unset, reserves.0, reserves.1, 1_000, SwapDirection::AToB).is_err());
+    }
+}
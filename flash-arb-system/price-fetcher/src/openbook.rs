@@ -0,0 +1,297 @@
+use anyhow::Result;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::error::PriceError;
+use crate::freshness::Observation;
+use crate::orderbook::{BookOrder, BookParams, FeeBasis, L2Book};
+use crate::price::Price;
+use crate::quoter::{
+    required_account, unix_timestamp, AccountMap, DecodedPool, DexType, PoolQuoter, PoolState, SwapDirection, SwapQuote,
+};
+
+/// OpenBook v2 program
+pub const OPENBOOK_V2_PROGRAM_ID: Pubkey = solana_sdk::pubkey!("opnb2LAfJYbRMAHHvqjCwQxanZn7ReEHp1k81EohpZb");
+
+const MARKET_DISCRIMINATOR: [u8; 8] = [219, 190, 213, 55, 0, 227, 198, 154];
+const BOOK_SIDE_DISCRIMINATOR: [u8; 8] = [72, 44, 225, 141, 178, 130, 97, 57];
+const MARKET_SIZE: usize = 8 + 840;
+const BOOK_SIDE_SIZE: usize = 8 + 90_944;
+
+/// Roots (16), reserved roots (32) and reserved bytes (256), then the
+/// `OrderTreeNodes` header: type and padding (4), bump index (4), free list
+/// length (4) and head (4), reserved (512)
+const BOOK_SIDE_NODES_OFFSET: usize = 16 + 32 + 256 + 528;
+const BOOK_SIDE_CAPACITY: usize = 1024;
+const NODE_SIZE: usize = 88;
+// The node array runs to the end of the account
+const _: () = assert!(BOOK_SIDE_NODES_OFFSET + BOOK_SIDE_CAPACITY * NODE_SIZE == BOOK_SIDE_SIZE - 8);
+const INNER_NODE_TAG: u8 = 1;
+const LEAF_NODE_TAG: u8 = 2;
+
+/// Decoded OpenBook v2 market.
+///
+/// Prices are in quote lots per base lot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpenBookMarket {
+    pub base_decimals: u8,
+    pub quote_decimals: u8,
+    /// Unix time after which the market no longer trades, zero if never
+    pub time_expiry: i64,
+    pub bids: Pubkey,
+    pub asks: Pubkey,
+    pub quote_lot_size: i64,
+    pub base_lot_size: i64,
+    /// Taker fee in millionths of the quote amount
+    pub taker_fee: i64,
+    pub base_mint: Pubkey,
+    pub quote_mint: Pubkey,
+}
+
+impl OpenBookMarket {
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        if data.len() != MARKET_SIZE || data[..8] != MARKET_DISCRIMINATOR {
+            anyhow::bail!("Invalid OpenBook v2 market account ({} bytes)", data.len());
+        }
+        let read_i64 = |offset: usize| i64::from_le_bytes(data[offset..offset + 8].try_into().unwrap());
+        let read_pubkey = |offset: usize| Pubkey::new_from_array(data[offset..offset + 32].try_into().unwrap());
+        Ok(Self {
+            base_decimals: data[9],
+            quote_decimals: data[10],
+            time_expiry: read_i64(48),
+            bids: read_pubkey(200),
+            asks: read_pubkey(232),
+            quote_lot_size: read_i64(448),
+            base_lot_size: read_i64(456),
+            taker_fee: read_i64(488),
+            base_mint: read_pubkey(576),
+            quote_mint: read_pubkey(608),
+        })
+    }
+
+    pub fn book_params(&self) -> Result<BookParams> {
+        if self.base_lot_size <= 0 || self.quote_lot_size <= 0 || self.taker_fee < 0 {
+            anyhow::bail!("OpenBook v2 market has invalid lot sizes or taker fee");
+        }
+        Ok(BookParams {
+            base_lot_size: self.base_lot_size as u64,
+            quote_lot_size: self.quote_lot_size as u64,
+            quote_lots_per_price_num: 1,
+            quote_lots_per_price_den: 1,
+            taker_fee_ppm: self.taker_fee as u64,
+            fee_basis: FeeBasis::QuoteAtoms,
+        })
+    }
+
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.time_expiry != 0 && self.time_expiry <= now as i64
+    }
+
+    /// Aggregated book from the market's bids and asks accounts
+    pub fn book(&self, accounts: &AccountMap, now: u64) -> Result<L2Book> {
+        let side = |key: &Pubkey| -> Result<Vec<BookOrder>, PriceError> {
+            let account = required_account(accounts, key)?;
+            book_side_orders(&account.data).map_err(|err| PriceError::layout(*key, err))
+        };
+        L2Book::from_orders(self.book_params()?, &side(&self.bids)?, &side(&self.asks)?, now)
+    }
+}
+
+/// Fixed-price orders resting on an OpenBook v2 `BookSide` account.
+///
+/// Oracle-pegged orders live in a second tree and are not included.
+pub fn book_side_orders(data: &[u8]) -> Result<Vec<BookOrder>> {
+    if data.len() != BOOK_SIDE_SIZE || data[..8] != BOOK_SIDE_DISCRIMINATOR {
+        anyhow::bail!("Invalid OpenBook v2 book side account ({} bytes)", data.len());
+    }
+    let body = &data[8..];
+    let read_u16 = |offset: usize| u16::from_le_bytes(body[offset..offset + 2].try_into().unwrap());
+    let read_u32 = |offset: usize| u32::from_le_bytes(body[offset..offset + 4].try_into().unwrap());
+    let read_u64 = |offset: usize| u64::from_le_bytes(body[offset..offset + 8].try_into().unwrap());
+
+    let mut orders = Vec::new();
+    // Fixed-order root: node handle and leaf count
+    if read_u32(4) == 0 {
+        return Ok(orders);
+    }
+    let mut visited = vec![false; BOOK_SIDE_CAPACITY];
+    let mut stack = vec![read_u32(0)];
+    while let Some(handle) = stack.pop() {
+        let index = handle as usize;
+        if index >= BOOK_SIDE_CAPACITY || std::mem::replace(&mut visited[index], true) {
+            anyhow::bail!("Corrupt OpenBook v2 order tree at node {}", handle);
+        }
+        let node = BOOK_SIDE_NODES_OFFSET + index * NODE_SIZE;
+        match body[node] {
+            INNER_NODE_TAG => {
+                stack.push(read_u32(node + 24));
+                stack.push(read_u32(node + 28));
+            }
+            LEAF_NODE_TAG => {
+                let time_in_force = read_u16(node + 2);
+                let quantity = read_u64(node + 56) as i64;
+                let timestamp = read_u64(node + 64);
+                orders.push(BookOrder {
+                    // The upper half of the 128-bit key is the price in lots
+                    price: read_u64(node + 16),
+                    base_lots: quantity.max(0) as u64,
+                    expires_at: (time_in_force != 0).then_some(timestamp + time_in_force as u64),
+                });
+            }
+            tag => anyhow::bail!("Unexpected OpenBook v2 node tag {} at node {}", tag, handle),
+        }
+    }
+    Ok(orders)
+}
+
+#[derive(Default)]
+pub struct OpenBookClient {}
+
+impl OpenBookClient {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl PoolQuoter for OpenBookClient {
+    fn dex(&self) -> DexType {
+        DexType::OpenBookV2
+    }
+
+    fn program_id(&self) -> Pubkey {
+        OPENBOOK_V2_PROGRAM_ID
+    }
+
+    fn decode(&self, address: &Pubkey, data: &[u8]) -> Result<DecodedPool, PriceError> {
+        let market = OpenBookMarket::from_bytes(data).map_err(|err| PriceError::layout(*address, err))?;
+        Ok(DecodedPool {
+            address: *address,
+            dex: DexType::OpenBookV2,
+            mint_a: market.base_mint,
+            mint_b: market.quote_mint,
+            state: PoolState::OpenBookV2(market),
+            observed: Observation::default(),
+        })
+    }
+
+    fn required_accounts(&self, pool: &DecodedPool) -> Vec<Pubkey> {
+        let PoolState::OpenBookV2(market) = &pool.state else {
+            return Vec::new();
+        };
+        vec![market.bids, market.asks]
+    }
+
+    fn mid_price(&self, pool: &DecodedPool, accounts: &AccountMap) -> Result<Price, PriceError> {
+        let PoolState::OpenBookV2(market) = &pool.state else {
+            return Err(PriceError::layout(pool.address, "not an OpenBook v2 market"));
+        };
+        Ok(market.book(accounts, unix_timestamp())?.mid_price()?)
+    }
+
+    fn quote_exact_in(
+        &self,
+        pool: &DecodedPool,
+        accounts: &AccountMap,
+        amount_in: u64,
+        direction: SwapDirection,
+    ) -> Result<SwapQuote, PriceError> {
+        let PoolState::OpenBookV2(market) = &pool.state else {
+            return Err(PriceError::layout(pool.address, "not an OpenBook v2 market"));
+        };
+        let now = unix_timestamp();
+        if market.is_expired(now) {
+            return Err(PriceError::stale(pool.address, format!("market expired at {}", market.time_expiry)));
+        }
+        Ok(market.book(accounts, now)?.quote_exact_in(amount_in, direction)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orderbook::L2Level;
+    use solana_sdk::account::Account;
+
+    fn book_side_account(leaves: &[(u64, u64)]) -> Account {
+        let mut data = vec![0u8; BOOK_SIDE_SIZE];
+        data[..8].copy_from_slice(&BOOK_SIDE_DISCRIMINATOR);
+        let body = &mut data[8..];
+        body[4..8].copy_from_slice(&(leaves.len() as u32).to_le_bytes());
+
+        // Node 0 is an inner node over leaves 1 and 2; a lone leaf is the root
+        let first_leaf = if leaves.len() > 1 {
+            body[BOOK_SIDE_NODES_OFFSET] = INNER_NODE_TAG;
+            body[BOOK_SIDE_NODES_OFFSET + 24..BOOK_SIDE_NODES_OFFSET + 28].copy_from_slice(&1u32.to_le_bytes());
+            body[BOOK_SIDE_NODES_OFFSET + 28..BOOK_SIDE_NODES_OFFSET + 32].copy_from_slice(&2u32.to_le_bytes());
+            1
+        } else {
+            0
+        };
+        for (i, &(price, quantity)) in leaves.iter().enumerate() {
+            let node = BOOK_SIDE_NODES_OFFSET + (first_leaf + i) * NODE_SIZE;
+            body[node] = LEAF_NODE_TAG;
+            body[node + 8..node + 16].copy_from_slice(&(i as u64).to_le_bytes());
+            body[node + 16..node + 24].copy_from_slice(&price.to_le_bytes());
+            body[node + 56..node + 64].copy_from_slice(&quantity.to_le_bytes());
+        }
+        Account {
+            lamports: 0,
+            data,
+            owner: OPENBOOK_V2_PROGRAM_ID,
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    fn market_data(bids: &Pubkey, asks: &Pubkey) -> Vec<u8> {
+        let mut data = vec![0u8; MARKET_SIZE];
+        data[..8].copy_from_slice(&MARKET_DISCRIMINATOR);
+        data[9] = 9;
+        data[10] = 6;
+        data[200..232].copy_from_slice(bids.as_ref());
+        data[232..264].copy_from_slice(asks.as_ref());
+        // 0.01 SOL base lots, 1 micro-USDC quote lots, 4 bps taker fee
+        data[448..456].copy_from_slice(&1i64.to_le_bytes());
+        data[456..464].copy_from_slice(&10_000_000i64.to_le_bytes());
+        data[488..496].copy_from_slice(&400i64.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn test_book_side_leaves() {
+        let account = book_side_account(&[(1_500_000, 3), (1_499_000, 7)]);
+        let mut orders = book_side_orders(&account.data).unwrap();
+        orders.sort_by_key(|order| order.price);
+        assert_eq!(orders[0], BookOrder { price: 1_499_000, base_lots: 7, expires_at: None });
+        assert_eq!(orders[1].base_lots, 3);
+        assert!(book_side_orders(&book_side_account(&[]).data).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_quote_through_client() {
+        let (bids, asks) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let client = OpenBookClient::new();
+        let pool = client.decode(&Pubkey::new_unique(), &market_data(&bids, &asks)).unwrap();
+        assert_eq!(client.required_accounts(&pool), vec![bids, asks]);
+
+        let accounts: AccountMap = [
+            (bids, book_side_account(&[(1_500_000, 3), (1_499_000, 7)])),
+            (asks, book_side_account(&[(1_501_000, 5)])),
+        ]
+        .into_iter()
+        .collect();
+        let PoolState::OpenBookV2(market) = &pool.state else { unreachable!() };
+        let book = market.book(&accounts, 0).unwrap();
+        assert_eq!(book.asks, vec![L2Level { price: 1_501_000, base_lots: 5 }]);
+
+        // 150 USDC per SOL is 0.15 micro-USDC per lamport
+        assert_eq!(book.best_bid().unwrap(), Some(Price::from_ratio(15, 100).unwrap()));
+        let mid = client.mid_price(&pool, &accounts).unwrap();
+        assert!((mid.to_f64() - 0.15005).abs() < 1e-12);
+
+        // Buy with 3.1 USDC: two lots at 1.501 USDC each
+        let quote = client.quote_exact_in(&pool, &accounts, 3_100_000, SwapDirection::BToA).unwrap();
+        assert_eq!(quote.amount_out, 20_000_000);
+        assert_eq!(quote.fee_amount, 1_201);
+        assert_eq!(quote.amount_in, 3_002_000 + 1_201);
+    }
+}
@@ -0,0 +1,279 @@
+use anyhow::Result;
+use borsh::BorshDeserialize;
+use rpc_manager::RpcBackend;
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::price::Price;
+
+/// Pyth pull-oracle receiver, which owns posted `PriceUpdateV2` accounts
+pub const PYTH_RECEIVER_PROGRAM_ID: Pubkey = solana_sdk::pubkey!("rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ");
+/// Pyth push oracle, whose sponsored feed accounts are also `PriceUpdateV2`
+pub const PYTH_PUSH_ORACLE_PROGRAM_ID: Pubkey = solana_sdk::pubkey!("pythWSnswVUd12oZpeFP8e9CVaEqJg25g1Vtc2biRsT");
+/// Switchboard on-demand program
+pub const SWITCHBOARD_ON_DEMAND_PROGRAM_ID: Pubkey = solana_sdk::pubkey!("SBondMDrcV3K4kxZR1HNVT7osZxAHVHgYXL5Ze1oMUv");
+
+const PRICE_UPDATE_V2_DISCRIMINATOR: [u8; 8] = [34, 241, 35, 99, 157, 126, 244, 205];
+const PULL_FEED_DISCRIMINATOR: [u8; 8] = [196, 27, 108, 196, 10, 215, 219, 40];
+
+/// `PullFeedAccountData` offsets: the feed's last update time, then the
+/// aggregated `CurrentResult` (value, std_dev, ..., num_samples)
+const PULL_FEED_LAST_UPDATE_OFFSET: usize = 8 + 2208;
+const PULL_FEED_RESULT_OFFSET: usize = 8 + 2256;
+const PULL_FEED_NUM_SAMPLES_OFFSET: usize = PULL_FEED_RESULT_OFFSET + 96;
+/// Switchboard results are fixed-point with 18 decimals
+const SWITCHBOARD_EXPONENT: i32 = -18;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OracleSource {
+    Pyth,
+    Switchboard,
+}
+
+/// Reference price from an oracle, in whole units of the feed's quote
+/// currency (usually USD) per whole token
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OraclePrice {
+    pub source: OracleSource,
+    pub price: Price,
+    /// Pyth's confidence interval or Switchboard's standard deviation
+    pub confidence: Price,
+    /// Unix timestamp of the observation
+    pub publish_time: i64,
+}
+
+impl OraclePrice {
+    /// Whether the observation is more than `max_age_secs` older than `now`
+    pub fn is_stale(&self, now: i64, max_age_secs: i64) -> bool {
+        now - self.publish_time > max_age_secs
+    }
+}
+
+#[derive(BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerificationLevel {
+    /// Verified by fewer guardian signatures than the quorum
+    Partial { num_signatures: u8 },
+    Full,
+}
+
+#[derive(BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PriceFeedMessage {
+    pub feed_id: [u8; 32],
+    pub price: i64,
+    pub conf: u64,
+    pub exponent: i32,
+    pub publish_time: i64,
+    pub prev_publish_time: i64,
+    pub ema_price: i64,
+    pub ema_conf: u64,
+}
+
+/// Pyth `PriceUpdateV2` account (after the discriminator)
+#[derive(BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PriceUpdateV2 {
+    pub write_authority: Pubkey,
+    pub verification_level: VerificationLevel,
+    pub price_message: PriceFeedMessage,
+    pub posted_slot: u64,
+}
+
+impl PriceUpdateV2 {
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        if data.len() < 8 || data[..8] != PRICE_UPDATE_V2_DISCRIMINATOR {
+            anyhow::bail!("Not a Pyth PriceUpdateV2 account");
+        }
+        Ok(Self::deserialize(&mut &data[8..])?)
+    }
+
+    /// Price and confidence scaled by the message exponent. Partially
+    /// verified updates are rejected, as the receiver does by default.
+    pub fn oracle_price(&self) -> Result<OraclePrice> {
+        if self.verification_level != VerificationLevel::Full {
+            anyhow::bail!("Pyth price update is only {:?}", self.verification_level);
+        }
+        let message = &self.price_message;
+        if message.price <= 0 {
+            anyhow::bail!("Pyth price {} is not positive", message.price);
+        }
+        Ok(OraclePrice {
+            source: OracleSource::Pyth,
+            price: Price::from_decimal(message.price as u128, message.exponent)?,
+            confidence: Price::from_decimal(message.conf as u128, message.exponent)?,
+            publish_time: message.publish_time,
+        })
+    }
+}
+
+/// Decode the aggregated result of a Switchboard on-demand pull feed
+pub fn switchboard_pull_feed_price(data: &[u8]) -> Result<OraclePrice> {
+    if data.len() <= PULL_FEED_NUM_SAMPLES_OFFSET || data[..8] != PULL_FEED_DISCRIMINATOR {
+        anyhow::bail!("Not a Switchboard pull feed account");
+    }
+    if data[PULL_FEED_NUM_SAMPLES_OFFSET] == 0 {
+        anyhow::bail!("Switchboard pull feed has no samples");
+    }
+    let read_i128 = |offset: usize| i128::from_le_bytes(data[offset..offset + 16].try_into().unwrap());
+    let (value, std_dev) = (read_i128(PULL_FEED_RESULT_OFFSET), read_i128(PULL_FEED_RESULT_OFFSET + 16));
+    if value <= 0 || std_dev < 0 {
+        anyhow::bail!("Switchboard pull feed result {} is not positive", value);
+    }
+    Ok(OraclePrice {
+        source: OracleSource::Switchboard,
+        price: Price::from_decimal(value as u128, SWITCHBOARD_EXPONENT)?,
+        confidence: Price::from_decimal(std_dev as u128, SWITCHBOARD_EXPONENT)?,
+        publish_time: i64::from_le_bytes(
+            data[PULL_FEED_LAST_UPDATE_OFFSET..PULL_FEED_LAST_UPDATE_OFFSET + 8].try_into().unwrap(),
+        ),
+    })
+}
+
+/// How far a DEX price sits from an oracle price, and how far it may
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PriceDeviation {
+    pub deviation_bps: f64,
+    pub threshold_bps: f64,
+}
+
+impl PriceDeviation {
+    pub fn exceeds_threshold(&self) -> bool {
+        self.deviation_bps > self.threshold_bps
+    }
+}
+
+/// Compare a DEX price against an oracle price in the same units.
+///
+/// The allowed band is `confidence_multiple` oracle confidence intervals,
+/// but never narrower than `min_threshold_bps`, so a very tight oracle
+/// does not flag ordinary DEX spreads.
+pub fn price_deviation(
+    dex_price: Price,
+    oracle: &OraclePrice,
+    confidence_multiple: f64,
+    min_threshold_bps: f64,
+) -> Result<PriceDeviation> {
+    let confidence_bps = oracle.confidence.bps_of(oracle.price)?;
+    Ok(PriceDeviation {
+        deviation_bps: dex_price.deviation_bps(oracle.price)?,
+        threshold_bps: (confidence_bps * confidence_multiple).max(min_threshold_bps),
+    })
+}
+
+#[derive(Default)]
+pub struct OracleClient {}
+
+impl OracleClient {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Decode a Pyth or Switchboard account based on its owner
+    pub fn decode(&self, address: &Pubkey, account: &Account) -> Result<OraclePrice> {
+        match account.owner {
+            PYTH_RECEIVER_PROGRAM_ID | PYTH_PUSH_ORACLE_PROGRAM_ID => {
+                PriceUpdateV2::from_bytes(&account.data)?.oracle_price()
+            }
+            SWITCHBOARD_ON_DEMAND_PROGRAM_ID => switchboard_pull_feed_price(&account.data),
+            owner => anyhow::bail!("Account {} is owned by {}, not a supported oracle", address, owner),
+        }
+    }
+
+    pub async fn fetch_price<B: RpcBackend + ?Sized>(&self, rpc: &B, address: &Pubkey) -> Result<OraclePrice> {
+        let account = rpc.get_account(address).await?;
+        self.decode(address, &account)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(owner: Pubkey, data: Vec<u8>) -> Account {
+        Account {
+            lamports: 0,
+            data,
+            owner,
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    /// Fully verified update: SOL at 150.25 +/- 0.075
+    fn pyth_update_data(verification: &[u8]) -> Vec<u8> {
+        let mut data = PRICE_UPDATE_V2_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&[7u8; 32]);
+        data.extend_from_slice(verification);
+        data.extend_from_slice(&[1u8; 32]);
+        data.extend_from_slice(&15_025_000_000i64.to_le_bytes());
+        data.extend_from_slice(&7_500_000u64.to_le_bytes());
+        data.extend_from_slice(&(-8i32).to_le_bytes());
+        data.extend_from_slice(&1_700_000_000i64.to_le_bytes());
+        data.extend_from_slice(&1_699_999_999i64.to_le_bytes());
+        data.extend_from_slice(&15_020_000_000i64.to_le_bytes());
+        data.extend_from_slice(&8_000_000u64.to_le_bytes());
+        data.extend_from_slice(&250_000_000u64.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn test_decode_pyth_price_update() {
+        let client = OracleClient::new();
+        let price = client
+            .decode(&Pubkey::new_unique(), &account(PYTH_RECEIVER_PROGRAM_ID, pyth_update_data(&[1])))
+            .unwrap();
+        assert_eq!(price.source, OracleSource::Pyth);
+        assert_eq!(price.price, Price::from_ratio(15_025, 100).unwrap());
+        assert_eq!(price.confidence, Price::from_ratio(75, 1_000).unwrap());
+        assert_eq!(price.publish_time, 1_700_000_000);
+        assert!(price.is_stale(1_700_000_061, 60));
+
+        let partial = account(PYTH_RECEIVER_PROGRAM_ID, pyth_update_data(&[0, 5]));
+        assert_eq!(PriceUpdateV2::from_bytes(&partial.data).unwrap().posted_slot, 250_000_000);
+        assert!(client.decode(&Pubkey::new_unique(), &partial).is_err());
+    }
+
+    #[test]
+    fn test_decode_switchboard_pull_feed() {
+        let mut data = vec![0u8; PULL_FEED_NUM_SAMPLES_OFFSET + 64];
+        data[..8].copy_from_slice(&PULL_FEED_DISCRIMINATOR);
+        data[PULL_FEED_LAST_UPDATE_OFFSET..PULL_FEED_LAST_UPDATE_OFFSET + 8]
+            .copy_from_slice(&1_700_000_100i64.to_le_bytes());
+        data[PULL_FEED_RESULT_OFFSET..PULL_FEED_RESULT_OFFSET + 16]
+            .copy_from_slice(&(150_100_000_000_000_000_000i128).to_le_bytes());
+        data[PULL_FEED_RESULT_OFFSET + 16..PULL_FEED_RESULT_OFFSET + 32]
+            .copy_from_slice(&(50_000_000_000_000_000i128).to_le_bytes());
+
+        let feed = account(SWITCHBOARD_ON_DEMAND_PROGRAM_ID, data);
+        let client = OracleClient::new();
+        assert!(client.decode(&Pubkey::new_unique(), &feed).is_err());
+
+        let mut feed = feed;
+        feed.data[PULL_FEED_NUM_SAMPLES_OFFSET] = 3;
+        let price = client.decode(&Pubkey::new_unique(), &feed).unwrap();
+        assert_eq!(price.price, Price::from_ratio(1_501, 10).unwrap());
+        assert_eq!(price.confidence, Price::from_ratio(5, 100).unwrap());
+        assert_eq!(price.publish_time, 1_700_000_100);
+    }
+
+    #[test]
+    fn test_confidence_scaled_deviation() {
+        let oracle = OraclePrice {
+            source: OracleSource::Pyth,
+            price: Price::from_integer(100),
+            confidence: Price::from_ratio(1, 10).unwrap(),
+            publish_time: 0,
+        };
+        let dex_price = |hundredths: u128| Price::from_ratio(hundredths, 100).unwrap();
+        // Three confidence intervals is 30 bps
+        let within = price_deviation(dex_price(10_025), &oracle, 3.0, 10.0).unwrap();
+        assert!((within.deviation_bps - 25.0).abs() < 1e-9);
+        assert!((within.threshold_bps - 30.0).abs() < 1e-9);
+        assert!(!within.exceeds_threshold());
+        assert!(price_deviation(dex_price(9_960), &oracle, 3.0, 10.0).unwrap().exceeds_threshold());
+
+        // The floor applies when confidence is tiny
+        let tight = OraclePrice { confidence: Price::from_ratio(1, 1_000).unwrap(), ..oracle };
+        let deviation = price_deviation(dex_price(10_005), &tight, 3.0, 10.0).unwrap();
+        assert_eq!(deviation.threshold_bps, 10.0);
+        assert!(!deviation.exceeds_threshold());
+    }
+}
@@ -1,11 +1,19 @@
 use anyhow::Result;
 use solana_sdk::pubkey::Pubkey;
 use borsh::BorshDeserialize;
-use solana_client::nonblocking::rpc_client::RpcClient;
+use rpc_manager::RpcBackend;
 
 // Use the orca client crate
-use orca_whirlpools_client::{Whirlpool, WHIRLPOOL_ID};
+use orca_whirlpools_client::{TickArray, Whirlpool, WHIRLPOOL_ID};
 
+use crate::clmm_math::{self, ClmmState, TickMath, TickWindow};
+use crate::error::PriceError;
+use crate::freshness::Observation;
+use crate::price::Price;
+use crate::quoter::{AccountMap, DecodedPool, DexType, PoolPrice, PoolQuoter, PoolState, SwapDirection, SwapQuote};
+use crate::token::MintRegistry;
+
+#[derive(Default)]
 pub struct OrcaClient {}
 
 impl OrcaClient {
@@ -13,19 +21,366 @@ impl OrcaClient {
         Self {}
     }
 
-    pub async fn get_whirlpool_price(&self, rpc: &RpcClient, pool_address: &Pubkey) -> Result<f64> {
-        let account_data = rpc.get_account_data(pool_address).await?;
-        
-        // Deserialize using the SDK
-        // Note: In real logic, we'd check owner == standard Whirlpool Program ID
-        use anchor_lang::AccountDeserialize;
-        let pool = Whirlpool::deserialize(&mut account_data.as_slice())?;
+    pub async fn get_whirlpool_price<B: RpcBackend + ?Sized>(
+        &self,
+        rpc: &B,
+        pool_address: &Pubkey,
+        mints: &MintRegistry,
+    ) -> Result<PoolPrice, PriceError> {
+        let account = rpc.get_account(pool_address).await?;
+        if account.owner != WHIRLPOOL_ID {
+            return Err(PriceError::WrongOwner {
+                account: *pool_address,
+                owner: account.owner,
+            });
+        }
+
+        let pool = decode_whirlpool(pool_address, &account.data)?;
+
+        let decimals = mints.resolve(rpc, &[pool.token_mint_a, pool.token_mint_b]).await?;
+        Ok(PoolPrice::new(pool_price(&pool)?, decimals[0].decimals, decimals[1].decimals)?)
+    }
+}
+
+/// Anchor discriminator and size of a `Whirlpool` account
+pub(crate) const WHIRLPOOL_DISCRIMINATOR: [u8; 8] = [63, 149, 209, 12, 225, 128, 99, 9];
+pub(crate) const WHIRLPOOL_SIZE: usize = 653;
+
+/// Deserialize a `Whirlpool` account with the SDK after checking its
+/// discriminator and size, which the SDK does not
+fn decode_whirlpool(address: &Pubkey, data: &[u8]) -> Result<Whirlpool, PriceError> {
+    if data.len() != WHIRLPOOL_SIZE || data[..8] != WHIRLPOOL_DISCRIMINATOR {
+        return Err(PriceError::layout(*address, format!("not a Whirlpool account ({} bytes)", data.len())));
+    }
+    Whirlpool::deserialize(&mut &data[..]).map_err(|err| PriceError::layout(*address, err))
+}
+
+/// Ticks per Whirlpool tick array
+pub const TICK_ARRAY_SIZE: i32 = 88;
+
+/// Tick arrays fetched on each side of the current one
+const TICK_ARRAYS_EACH_SIDE: i32 = 2;
+
+/// Start index of the tick array containing `tick_index`
+pub fn tick_array_start_index(tick_index: i32, tick_spacing: u16) -> i32 {
+    let ticks_in_array = TICK_ARRAY_SIZE * tick_spacing as i32;
+    tick_index.div_euclid(ticks_in_array) * ticks_in_array
+}
+
+/// Tick array PDA for a Whirlpool
+pub fn tick_array_address(whirlpool: &Pubkey, start_tick_index: i32) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"tick_array", whirlpool.as_ref(), start_tick_index.to_string().as_bytes()],
+        &WHIRLPOOL_ID,
+    )
+    .0
+}
+
+/// Start indices of the tick arrays around the pool's current tick, lowest first
+fn tick_array_window_starts(whirlpool: &Whirlpool) -> Vec<i32> {
+    let ticks_in_array = TICK_ARRAY_SIZE * whirlpool.tick_spacing as i32;
+    let current = tick_array_start_index(whirlpool.tick_current_index, whirlpool.tick_spacing);
+    (-TICK_ARRAYS_EACH_SIDE..=TICK_ARRAYS_EACH_SIDE)
+        .map(|offset| current + offset * ticks_in_array)
+        .collect()
+}
+
+/// Tick window around the pool's current tick built from fetched tick
+/// arrays. Arrays that were never initialized on chain hold no initialized
+/// ticks and are treated as empty.
+pub fn tick_window_from_accounts(
+    pool_address: &Pubkey,
+    whirlpool: &Whirlpool,
+    accounts: &AccountMap,
+) -> Result<TickWindow, PriceError> {
+    let starts = tick_array_window_starts(whirlpool);
+    let tick_arrays = starts
+        .iter()
+        .map(|start| tick_array_address(pool_address, *start))
+        .filter_map(|address| accounts.get(&address).map(|account| (address, account)))
+        .map(|(address, account)| {
+            let array =
+                TickArray::deserialize(&mut &account.data[..]).map_err(|err| PriceError::layout(address, err))?;
+            if array.whirlpool != *pool_address {
+                return Err(PriceError::layout(address, format!("tick array belongs to whirlpool {}", array.whirlpool)));
+            }
+            Ok(array)
+        })
+        .collect::<Result<Vec<_>, PriceError>>()?;
+
+    let tick_spacing = whirlpool.tick_spacing as i32;
+    let initialized = tick_arrays.iter().flat_map(|array| {
+        array
+            .ticks
+            .iter()
+            .enumerate()
+            .filter(|(_, tick)| tick.initialized)
+            .map(move |(offset, tick)| (array.start_tick_index + offset as i32 * tick_spacing, tick.liquidity_net))
+    });
+    let upper = starts[starts.len() - 1] + TICK_ARRAY_SIZE * tick_spacing;
+    Ok(TickWindow::new(starts[0], upper, initialized))
+}
+
+/// Current pool price; a pool with no in-range liquidity has no tradable price
+fn pool_price(whirlpool: &Whirlpool) -> Result<Price, PriceError> {
+    if whirlpool.sqrt_price == 0 || whirlpool.liquidity == 0 {
+        return Err(PriceError::EmptyPool);
+    }
+    Ok(Price::from_sqrt_price_x64(whirlpool.sqrt_price)?)
+}
+
+/// Simulate a Whirlpool swap across the initialized ticks in `ticks`
+pub fn simulate_swap(
+    whirlpool: &Whirlpool,
+    ticks: &TickWindow,
+    amount: u64,
+    amount_specified_is_input: bool,
+    direction: SwapDirection,
+) -> Result<SwapQuote> {
+    let state = ClmmState {
+        sqrt_price: whirlpool.sqrt_price,
+        tick_current_index: whirlpool.tick_current_index,
+        liquidity: whirlpool.liquidity,
+        fee_rate: whirlpool.fee_rate.into(),
+    };
+    clmm_math::simulate_swap(&state, ticks, TickMath::Whirlpool, amount, amount_specified_is_input, direction)
+}
+
+impl PoolQuoter for OrcaClient {
+    fn dex(&self) -> DexType {
+        DexType::OrcaWhirlpool
+    }
+
+    fn program_id(&self) -> Pubkey {
+        WHIRLPOOL_ID
+    }
+
+    fn decode(&self, address: &Pubkey, data: &[u8]) -> Result<DecodedPool, PriceError> {
+        let pool = decode_whirlpool(address, data)?;
+        Ok(DecodedPool {
+            address: *address,
+            dex: DexType::OrcaWhirlpool,
+            mint_a: pool.token_mint_a,
+            mint_b: pool.token_mint_b,
+            state: PoolState::Whirlpool(pool),
+            observed: Observation::default(),
+        })
+    }
+
+    fn required_accounts(&self, _pool: &DecodedPool) -> Vec<Pubkey> {
+        // Mid price only needs the pool's own sqrt_price
+        Vec::new()
+    }
+
+    fn quote_accounts(&self, pool: &DecodedPool) -> Vec<Pubkey> {
+        let PoolState::Whirlpool(whirlpool) = &pool.state else {
+            return Vec::new();
+        };
+        tick_array_window_starts(whirlpool)
+            .into_iter()
+            .map(|start| tick_array_address(&pool.address, start))
+            .collect()
+    }
+
+    fn mid_price(&self, pool: &DecodedPool, _accounts: &AccountMap) -> Result<Price, PriceError> {
+        let PoolState::Whirlpool(whirlpool) = &pool.state else {
+            return Err(PriceError::layout(pool.address, "not a Whirlpool"));
+        };
+        pool_price(whirlpool)
+    }
+
+    fn quote_exact_in(
+        &self,
+        pool: &DecodedPool,
+        accounts: &AccountMap,
+        amount_in: u64,
+        direction: SwapDirection,
+    ) -> Result<SwapQuote, PriceError> {
+        let PoolState::Whirlpool(whirlpool) = &pool.state else {
+            return Err(PriceError::layout(pool.address, "not a Whirlpool"));
+        };
+        let ticks = tick_window_from_accounts(&pool.address, whirlpool, accounts)?;
+        Ok(simulate_swap(whirlpool, &ticks, amount_in, true, direction)?)
+    }
+
+    fn quote_exact_out(
+        &self,
+        pool: &DecodedPool,
+        accounts: &AccountMap,
+        amount_out: u64,
+        direction: SwapDirection,
+    ) -> Result<SwapQuote, PriceError> {
+        let PoolState::Whirlpool(whirlpool) = &pool.state else {
+            return Err(PriceError::layout(pool.address, "not a Whirlpool"));
+        };
+        let ticks = tick_window_from_accounts(&pool.address, whirlpool, accounts)?;
+        Ok(simulate_swap(whirlpool, &ticks, amount_out, false, direction)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quoter::PostTradeState;
+    use solana_sdk::account::Account;
+
+    const TICK_SIZE: usize = 113;
+    const TICK_ARRAY_TICKS_OFFSET: usize = 12;
+    const Q64: u128 = 1 << 64;
+
+    fn whirlpool(tick_spacing: u16, sqrt_price: u128, liquidity: u128, fee_rate: u16) -> Whirlpool {
+        let mut pool = Whirlpool::deserialize(&mut &[0u8; WHIRLPOOL_SIZE][..]).unwrap();
+        pool.tick_spacing = tick_spacing;
+        pool.sqrt_price = sqrt_price;
+        pool.liquidity = liquidity;
+        pool.fee_rate = fee_rate;
+        pool.tick_current_index = clmm_math::tick_index_from_sqrt_price(sqrt_price).unwrap();
+        pool
+    }
+
+    /// Tick array account with the given (offset, liquidity_net) ticks initialized
+    fn tick_array_account(pool: &Pubkey, start_tick_index: i32, ticks: &[(usize, i128)]) -> Account {
+        let mut data = vec![0u8; TICK_ARRAY_TICKS_OFFSET + TICK_ARRAY_SIZE as usize * TICK_SIZE + 32];
+        data[8..12].copy_from_slice(&start_tick_index.to_le_bytes());
+        for (offset, liquidity_net) in ticks {
+            let at = TICK_ARRAY_TICKS_OFFSET + offset * TICK_SIZE;
+            data[at] = 1;
+            data[at + 1..at + 17].copy_from_slice(&liquidity_net.to_le_bytes());
+            data[at + 17..at + 33].copy_from_slice(&liquidity_net.unsigned_abs().to_le_bytes());
+        }
+        let tail = data.len() - 32;
+        data[tail..].copy_from_slice(pool.as_ref());
+        Account {
+            data,
+            owner: WHIRLPOOL_ID,
+            ..Account::default()
+        }
+    }
+
+    #[test]
+    fn test_tick_array_start_index() {
+        assert_eq!(tick_array_start_index(0, 64), 0);
+        assert_eq!(tick_array_start_index(5_631, 64), 0);
+        assert_eq!(tick_array_start_index(5_632, 64), 5_632);
+        assert_eq!(tick_array_start_index(-1, 64), -5_632);
+        assert_eq!(tick_array_start_index(-5_633, 1), -5_720);
+    }
+
+    #[test]
+    fn test_decode_checks_layout() {
+        let client = OrcaClient::new();
+        let address = Pubkey::new_unique();
+        let mut data = vec![0u8; WHIRLPOOL_SIZE];
+        data[..8].copy_from_slice(&WHIRLPOOL_DISCRIMINATOR);
+        assert_eq!(client.decode(&address, &data).unwrap().dex, DexType::OrcaWhirlpool);
+
+        // Other Whirlpool program accounts, e.g. tick arrays, are rejected
+        let mut wrong_kind = data.clone();
+        wrong_kind[0] ^= 1;
+        let too_long = [&data[..], &[0u8; 8]].concat();
+        for data in [&wrong_kind[..], &too_long, &data[..7]] {
+            assert!(matches!(client.decode(&address, data), Err(PriceError::LayoutMismatch { .. })));
+        }
+    }
+
+    #[test]
+    fn test_swap_within_range() {
+        // Price 1.0, 0.3% fee, L = 1e12, no initialized ticks nearby
+        let pool = whirlpool(64, Q64, 1_000_000_000_000, 3_000);
+        let ticks = TickWindow::new(-11_264, 11_264, []);
+
+        let quote = simulate_swap(&pool, &ticks, 1_000_000, true, SwapDirection::AToB).unwrap();
+        assert_eq!(quote.amount_in, 1_000_000);
+        assert_eq!(quote.fee_amount, 3_000);
+        assert!(quote.amount_out < 997_000 && quote.amount_out > 996_000);
+        let PostTradeState::Concentrated { sqrt_price_x64, tick_current_index, liquidity } = quote.post_state else {
+            panic!("expected concentrated post-trade state");
+        };
+        assert!(sqrt_price_x64 < Q64);
+        assert_eq!(tick_current_index, -1);
+        assert_eq!(liquidity, pool.liquidity);
+
+        // Exact out charges at most one unit less than the matching exact in
+        let exact_out = simulate_swap(&pool, &ticks, quote.amount_out, false, SwapDirection::AToB).unwrap();
+        assert_eq!(exact_out.amount_out, quote.amount_out);
+        assert!(exact_out.amount_in <= quote.amount_in);
+        assert!(exact_out.amount_in >= quote.amount_in - 1);
+
+        // Leaving the fetched window is an error rather than a silent fill
+        assert!(simulate_swap(&pool, &ticks, u64::MAX / 2, true, SwapDirection::BToA).is_err());
+    }
+
+    #[test]
+    fn test_swap_crosses_initialized_tick() {
+        // Positions [-64, 64) with L = 1e9 and [-640, 640) with L = 5e9
+        let address = Pubkey::new_unique();
+        let pool_state = whirlpool(64, Q64, 6_000_000_000, 3_000);
+        let pool = DecodedPool {
+            address,
+            dex: DexType::OrcaWhirlpool,
+            mint_a: pool_state.token_mint_a,
+            mint_b: pool_state.token_mint_b,
+            state: PoolState::Whirlpool(pool_state.clone()),
+            observed: Observation::default(),
+        };
+
+        let mut accounts = AccountMap::new();
+        accounts.insert(
+            tick_array_address(&address, -5_632),
+            tick_array_account(&address, -5_632, &[(78, 5_000_000_000), (87, 1_000_000_000)]),
+        );
+        accounts.insert(
+            tick_array_address(&address, 0),
+            tick_array_account(&address, 0, &[(1, -1_000_000_000), (10, -5_000_000_000)]),
+        );
+        let client = OrcaClient::new();
+        assert_eq!(client.quote_accounts(&pool).len(), 5);
+
+        let amount_in = 50_000_000;
+        let quote = client.quote_exact_in(&pool, &accounts, amount_in, SwapDirection::AToB).unwrap();
+
+        // Same swap stepped by hand: full range down to tick -64, then the rest at L = 5e9
+        let sqrt_price_at_cross = clmm_math::sqrt_price_from_tick_index(-64).unwrap();
+        let first = clmm_math::compute_swap_step(amount_in, 3_000, 6_000_000_000, Q64, sqrt_price_at_cross, true, true)
+            .unwrap();
+        assert_eq!(first.next_sqrt_price, sqrt_price_at_cross);
+        let remaining = amount_in - first.amount_in - first.fee_amount;
+        let second = clmm_math::compute_swap_step(
+            remaining,
+            3_000,
+            5_000_000_000,
+            sqrt_price_at_cross,
+            clmm_math::sqrt_price_from_tick_index(-640).unwrap(),
+            true,
+            true,
+        )
+        .unwrap();
+
+        assert_eq!(quote.amount_out, first.amount_out + second.amount_out);
+        assert_eq!(quote.fee_amount, first.fee_amount + second.fee_amount);
+        let PostTradeState::Concentrated { sqrt_price_x64, tick_current_index, liquidity } = quote.post_state else {
+            panic!("expected concentrated post-trade state");
+        };
+        assert_eq!(sqrt_price_x64, second.next_sqrt_price);
+        assert_eq!(liquidity, 5_000_000_000);
+        assert!(tick_current_index < -64 && tick_current_index > -640);
 
-        // Price from sqrt_price
-        // Price = (sqrt_price / 2^64)^2
-        let sqrt_price_x64 = pool.sqrt_price;
-        let price = (sqrt_price_x64 as f64 / ((1u128 << 64) as f64)).powi(2);
+        // Ignoring the tick arrays overstates the output
+        let single_range = simulate_swap(
+            &pool_state,
+            &TickWindow::new(-11_264, 11_264, []),
+            amount_in,
+            true,
+            SwapDirection::AToB,
+        )
+        .unwrap();
+        assert!(single_range.amount_out > quote.amount_out);
 
-        Ok(price)
+        // Tick arrays must belong to the pool being quoted
+        accounts.insert(
+            tick_array_address(&address, 0),
+            tick_array_account(&Pubkey::new_unique(), 0, &[]),
+        );
+        assert!(client.quote_exact_in(&pool, &accounts, amount_in, SwapDirection::AToB).is_err());
     }
 }
@@ -0,0 +1,362 @@
+use anyhow::Result;
+use std::collections::BTreeMap;
+
+use crate::error::PriceError;
+use crate::freshness::Observation;
+use crate::price::Price;
+use crate::quoter::{price_impact_bps, PostTradeState, SwapDirection, SwapQuote, TransferFees};
+
+/// Taker fees are carried in parts per million of the quote amount
+pub const FEE_PPM_DENOMINATOR: u64 = 1_000_000;
+
+/// A resting order as decoded from a venue's book account, in venue units
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BookOrder {
+    /// Price in the venue's price units (Phoenix ticks, OpenBook price lots)
+    pub price: u64,
+    pub base_lots: u64,
+    /// Unix timestamp after which the order can no longer be matched
+    pub expires_at: Option<u64>,
+}
+
+/// Aggregated size resting at one price
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct L2Level {
+    pub price: u64,
+    pub base_lots: u64,
+}
+
+/// Unit a venue charges its taker fee in, which decides where it rounds up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeeBasis {
+    /// Whole quote lots, as Phoenix does
+    QuoteLots,
+    /// Quote atoms, as OpenBook v2 does
+    QuoteAtoms,
+}
+
+/// Lot sizes and fees needed to turn venue units into token amounts.
+///
+/// Matching `base_lots` at `price` costs
+/// `base_lots * price * quote_lots_per_price_num / quote_lots_per_price_den`
+/// quote lots, each worth `quote_lot_size` quote atoms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BookParams {
+    pub base_lot_size: u64,
+    pub quote_lot_size: u64,
+    pub quote_lots_per_price_num: u64,
+    pub quote_lots_per_price_den: u64,
+    pub taker_fee_ppm: u64,
+    pub fee_basis: FeeBasis,
+}
+
+impl BookParams {
+    /// Quote lots for `base_lots` at `price`, rounded down or up
+    fn quote_lots(&self, base_lots: u64, price: u64, round_up: bool) -> u128 {
+        let numerator = base_lots as u128 * price as u128 * self.quote_lots_per_price_num as u128;
+        let denominator = self.quote_lots_per_price_den as u128;
+        if round_up {
+            numerator.div_ceil(denominator)
+        } else {
+            numerator / denominator
+        }
+    }
+
+    /// Most base lots at `price` whose cost fits in `budget` quote lots
+    fn affordable_lots(&self, budget: u128, price: u64) -> u128 {
+        let per_lot = price as u128 * self.quote_lots_per_price_num as u128;
+        if per_lot == 0 {
+            return u128::MAX;
+        }
+        budget * self.quote_lots_per_price_den as u128 / per_lot
+    }
+
+    /// Quote atoms in `quote_lots`
+    fn quote_atoms(&self, quote_lots: u128) -> Result<u64> {
+        u64::try_from(quote_lots * self.quote_lot_size as u128)
+            .map_err(|_| anyhow::anyhow!("{} quote lots overflow a token amount", quote_lots))
+    }
+
+    /// Taker fee in quote atoms on a fill of `quote_lots`, rounded up
+    /// against the taker in the venue's fee unit
+    fn taker_fee(&self, quote_lots: u128) -> Result<u64> {
+        let ppm = self.taker_fee_ppm as u128;
+        match self.fee_basis {
+            FeeBasis::QuoteLots => self.quote_atoms((quote_lots * ppm).div_ceil(FEE_PPM_DENOMINATOR as u128)),
+            FeeBasis::QuoteAtoms => {
+                let quote_atoms = self.quote_atoms(quote_lots)? as u128;
+                Ok((quote_atoms * ppm).div_ceil(FEE_PPM_DENOMINATOR as u128) as u64)
+            }
+        }
+    }
+
+    /// Most quote lots a taker can fill with `amount` quote atoms and still
+    /// cover the fee on them
+    fn spendable_quote_lots(&self, amount: u64) -> u128 {
+        let scale = |amount: u128| {
+            amount * FEE_PPM_DENOMINATOR as u128 / (FEE_PPM_DENOMINATOR + self.taker_fee_ppm) as u128
+        };
+        match self.fee_basis {
+            FeeBasis::QuoteLots => scale((amount / self.quote_lot_size) as u128),
+            FeeBasis::QuoteAtoms => scale(amount as u128) / self.quote_lot_size as u128,
+        }
+    }
+
+    /// Price in raw quote atoms per base atom
+    pub fn raw_price(&self, price: u64) -> Result<Price> {
+        let quote_atoms = (price as u128 * self.quote_lots_per_price_num as u128)
+            .checked_mul(self.quote_lot_size as u128)
+            .ok_or_else(|| anyhow::anyhow!("Price {} overflows", price))?;
+        Price::from_ratio(quote_atoms, self.quote_lots_per_price_den as u128 * self.base_lot_size as u128)
+    }
+}
+
+/// Price-aggregated order book. Bids are sorted best (highest) first and
+/// asks best (lowest) first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct L2Book {
+    pub params: BookParams,
+    pub bids: Vec<L2Level>,
+    pub asks: Vec<L2Level>,
+}
+
+fn aggregate(orders: &[BookOrder], now: u64) -> BTreeMap<u64, u64> {
+    let mut levels = BTreeMap::new();
+    for order in orders {
+        if order.base_lots == 0 || order.expires_at.is_some_and(|expiry| expiry <= now) {
+            continue;
+        }
+        *levels.entry(order.price).or_insert(0u64) += order.base_lots;
+    }
+    levels
+}
+
+impl L2Book {
+    /// Aggregate resting orders into levels, dropping those expired at `now`
+    pub fn from_orders(params: BookParams, bids: &[BookOrder], asks: &[BookOrder], now: u64) -> Result<Self> {
+        if params.base_lot_size == 0 || params.quote_lot_size == 0 || params.quote_lots_per_price_den == 0 {
+            anyhow::bail!("Order book has a zero lot size");
+        }
+        let to_level = |(price, base_lots)| L2Level { price, base_lots };
+        Ok(Self {
+            params,
+            bids: aggregate(bids, now).into_iter().rev().map(to_level).collect(),
+            asks: aggregate(asks, now).into_iter().map(to_level).collect(),
+        })
+    }
+
+    /// Best bid in raw quote atoms per base atom, if any
+    pub fn best_bid(&self) -> Result<Option<Price>> {
+        self.bids.first().map(|level| self.params.raw_price(level.price)).transpose()
+    }
+
+    /// Best ask in raw quote atoms per base atom, if any
+    pub fn best_ask(&self) -> Result<Option<Price>> {
+        self.asks.first().map(|level| self.params.raw_price(level.price)).transpose()
+    }
+
+    /// Midpoint of the best bid and ask in raw quote atoms per base atom
+    pub fn mid_price(&self) -> Result<Price> {
+        match (self.best_bid()?, self.best_ask()?) {
+            (Some(bid), Some(ask)) => Ok(bid.midpoint(ask)),
+            (None, None) => anyhow::bail!(PriceError::EmptyPool),
+            _ => anyhow::bail!("Order book is one-sided"),
+        }
+    }
+
+    /// Quote a taker order spending exactly `amount_in` of the input token,
+    /// where A is the base token and B the quote token.
+    ///
+    /// Selling base (`AToB`) matches whole base lots against the bids; any
+    /// remainder below one lot is not spent. Buying base (`BToA`) matches as
+    /// many lots of the asks as `amount_in` covers including the taker fee.
+    /// Takers always pay the fee in the quote token, so `fee_amount` is in
+    /// the output token when selling. The returned `amount_in` is what the
+    /// order actually consumes.
+    pub fn quote_exact_in(&self, amount_in: u64, direction: SwapDirection) -> Result<SwapQuote> {
+        let mid = self.mid_price()?;
+        match direction {
+            SwapDirection::AToB => self.sell_base(amount_in, mid),
+            SwapDirection::BToA => self.buy_base(amount_in, mid),
+        }
+    }
+
+    fn sell_base(&self, amount_in: u64, mid: Price) -> Result<SwapQuote> {
+        let params = &self.params;
+        let lots = amount_in / params.base_lot_size;
+        if lots == 0 {
+            anyhow::bail!("Amount {} is below one base lot ({})", amount_in, params.base_lot_size);
+        }
+
+        let mut remaining = lots;
+        let mut quote_lots = 0u128;
+        let mut top_of_book = None;
+        for (index, level) in self.bids.iter().enumerate() {
+            let take = remaining.min(level.base_lots);
+            quote_lots += params.quote_lots(take, level.price, false);
+            remaining -= take;
+            if remaining == 0 {
+                top_of_book = match level.base_lots - take {
+                    0 => self.bids.get(index + 1).copied(),
+                    left => Some(L2Level { price: level.price, base_lots: left }),
+                };
+                break;
+            }
+        }
+        if remaining > 0 {
+            anyhow::bail!("Bids only absorb {} of {} base lots", lots - remaining, lots);
+        }
+
+        let gross = params.quote_atoms(quote_lots)?;
+        let fee = params.taker_fee(quote_lots)?;
+        let amount_in = lots * params.base_lot_size;
+        Ok(SwapQuote {
+            amount_in,
+            amount_out: gross - fee,
+            fee_amount: fee,
+            price_impact_bps: price_impact_bps(mid, amount_in, gross)?,
+            post_state: PostTradeState::Book { top_of_book },
+            transfer_fees: TransferFees::default(),
+            observed: Observation::default(),
+        })
+    }
+
+    fn buy_base(&self, amount_in: u64, mid: Price) -> Result<SwapQuote> {
+        let params = &self.params;
+        let mut budget = params.spendable_quote_lots(amount_in);
+
+        let mut lots = 0u64;
+        let mut quote_lots = 0u128;
+        let mut top_of_book = None;
+        let mut exhausted = true;
+        for (index, level) in self.asks.iter().enumerate() {
+            let take = params.affordable_lots(budget, level.price).min(level.base_lots as u128) as u64;
+            let cost = params.quote_lots(take, level.price, true);
+            lots += take;
+            quote_lots += cost;
+            budget -= cost;
+            if take < level.base_lots {
+                top_of_book = Some(L2Level { price: level.price, base_lots: level.base_lots - take });
+                exhausted = false;
+                break;
+            }
+            if budget == 0 {
+                top_of_book = self.asks.get(index + 1).copied();
+                exhausted = false;
+                break;
+            }
+        }
+        if exhausted && budget > 0 {
+            anyhow::bail!("Asks only offer {} base lots, leaving {} quote lots unspent", lots, budget);
+        }
+        if lots == 0 {
+            anyhow::bail!("Amount {} does not buy one base lot", amount_in);
+        }
+
+        let gross = params.quote_atoms(quote_lots)?;
+        let fee = params.taker_fee(quote_lots)?;
+        let amount_out = lots * params.base_lot_size;
+        Ok(SwapQuote {
+            amount_in: gross + fee,
+            amount_out,
+            fee_amount: fee,
+            price_impact_bps: price_impact_bps(mid.invert()?, gross, amount_out)?,
+            post_state: PostTradeState::Book { top_of_book },
+            transfer_fees: TransferFees::default(),
+            observed: Observation::default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order(price: u64, base_lots: u64) -> BookOrder {
+        BookOrder { price, base_lots, expires_at: None }
+    }
+
+    /// 1 base lot = 1_000 atoms, 1 quote lot = 10 atoms, price in quote lots per base lot
+    fn book(taker_fee_ppm: u64) -> L2Book {
+        book_with_basis(taker_fee_ppm, FeeBasis::QuoteAtoms)
+    }
+
+    fn book_with_basis(taker_fee_ppm: u64, fee_basis: FeeBasis) -> L2Book {
+        let params = BookParams {
+            base_lot_size: 1_000,
+            quote_lot_size: 10,
+            quote_lots_per_price_num: 1,
+            quote_lots_per_price_den: 1,
+            taker_fee_ppm,
+            fee_basis,
+        };
+        let bids = [order(99, 5), order(98, 10), order(99, 5), BookOrder { expires_at: Some(10), ..order(100, 50) }];
+        let asks = [order(101, 4), order(103, 10)];
+        L2Book::from_orders(params, &bids, &asks, 100).unwrap()
+    }
+
+    #[test]
+    fn test_levels_and_top_of_book() {
+        let book = book(0);
+        assert_eq!(
+            book.bids,
+            vec![L2Level { price: 99, base_lots: 10 }, L2Level { price: 98, base_lots: 10 }]
+        );
+        assert_eq!(book.best_bid().unwrap(), Some(Price::from_ratio(99, 100).unwrap()));
+        assert_eq!(book.best_ask().unwrap(), Some(Price::from_ratio(101, 100).unwrap()));
+        assert!((book.mid_price().unwrap().to_f64() - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_sell_walks_bids() {
+        // 12.5 lots in: 10 at 99 and 2 at 98, half a lot left unspent
+        let quote = book(500).quote_exact_in(12_500, SwapDirection::AToB).unwrap();
+        assert_eq!(quote.amount_in, 12_000);
+        let gross = (10 * 99 + 2 * 98) * 10;
+        assert_eq!(quote.fee_amount, (gross * 500u64).div_ceil(1_000_000));
+        assert_eq!(quote.amount_out, gross - quote.fee_amount);
+        assert_eq!(
+            quote.post_state,
+            PostTradeState::Book { top_of_book: Some(L2Level { price: 98, base_lots: 8 }) }
+        );
+        assert!(book(0).quote_exact_in(21_000, SwapDirection::AToB).is_err());
+    }
+
+    #[test]
+    fn test_buy_walks_asks_with_fee() {
+        // 4 lots at 101 cost 4_040, leaving room for 2 lots at 103
+        let quote = book(1_000).quote_exact_in(6_200, SwapDirection::BToA).unwrap();
+        assert_eq!(quote.amount_out, 6_000);
+        assert_eq!(quote.amount_in - quote.fee_amount, 4_040 + 2_060);
+        assert_eq!(quote.fee_amount, 7);
+        assert!(quote.amount_in <= 6_200);
+        assert_eq!(
+            quote.post_state,
+            PostTradeState::Book { top_of_book: Some(L2Level { price: 103, base_lots: 8 }) }
+        );
+        assert!(book(0).quote_exact_in(1_000_000, SwapDirection::BToA).is_err());
+    }
+
+    #[test]
+    fn test_fee_rounds_up_in_venue_units() {
+        // 610 quote lots at 1_000 ppm: 6_100 atoms owe 6.1, rounded up to 7
+        // atoms, but 0.61 lots round up to a whole lot of 10 atoms
+        let quote = book_with_basis(1_000, FeeBasis::QuoteLots).quote_exact_in(6_200, SwapDirection::BToA).unwrap();
+        assert_eq!((quote.amount_out, quote.fee_amount, quote.amount_in), (6_000, 10, 6_110));
+
+        let quote = book_with_basis(1_000, FeeBasis::QuoteLots).quote_exact_in(12_500, SwapDirection::AToB).unwrap();
+        // 1_186 quote lots owe 1.186 lots, rounded up to 2
+        let gross = (10 * 99 + 2 * 98) * 10;
+        assert_eq!((quote.fee_amount, quote.amount_out), (20, gross - 20));
+    }
+
+    #[test]
+    fn test_oversized_fill_is_rejected() {
+        let params = BookParams {
+            quote_lot_size: u64::MAX,
+            ..book(0).params
+        };
+        let book = L2Book::from_orders(params, &[order(2, 10)], &[order(3, 10)], 0).unwrap();
+        assert!(book.quote_exact_in(2_000, SwapDirection::AToB).is_err());
+    }
+}
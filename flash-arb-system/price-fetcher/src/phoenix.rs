@@ -0,0 +1,287 @@
+use anyhow::Result;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::error::PriceError;
+use crate::freshness::Observation;
+use crate::orderbook::{BookOrder, BookParams, FeeBasis, L2Book};
+use crate::price::Price;
+use crate::quoter::{unix_timestamp, AccountMap, DecodedPool, DexType, PoolQuoter, PoolState, SwapDirection, SwapQuote};
+
+/// Phoenix order book program
+pub const PHOENIX_PROGRAM_ID: Pubkey = solana_sdk::pubkey!("PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY");
+
+/// First eight bytes of sha256("phoenix::program::accounts::MarketHeader")
+const MARKET_HEADER_DISCRIMINANT: u64 = 12610206325545081173;
+const MARKET_HEADER_SIZE: usize = 576;
+/// `FIFOMarket` padding and scalar fields ahead of the bid tree
+const BIDS_TREE_OFFSET: usize = MARKET_HEADER_SIZE + 304;
+
+/// Red-black tree root and padding, then allocator size, bump index and
+/// free list head
+const TREE_HEADER_SIZE: usize = 32;
+/// Four u32 registers (left, right, parent, color), `FIFOOrderId` key and
+/// `FIFORestingOrder` value
+const TREE_NODE_SIZE: usize = 64;
+/// Tree node handles are 1-based; zero is the null handle
+const NIL: u32 = 0;
+
+const TAKER_FEE_BPS_TO_PPM: u64 = 100;
+
+/// Only active markets accept taker orders
+pub const MARKET_STATUS_ACTIVE: u64 = 1;
+
+/// Decoded Phoenix market: header parameters and every resting order.
+///
+/// Prices are in ticks. Matching costs
+/// `base_lots * ticks * tick_size_in_quote_lots_per_base_unit / base_lots_per_base_unit`
+/// quote lots.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PhoenixMarket {
+    pub status: u64,
+    pub base_mint: Pubkey,
+    pub quote_mint: Pubkey,
+    pub base_decimals: u32,
+    pub quote_decimals: u32,
+    pub base_lot_size: u64,
+    pub quote_lot_size: u64,
+    pub base_lots_per_base_unit: u64,
+    pub tick_size_in_quote_lots_per_base_unit: u64,
+    pub taker_fee_bps: u64,
+    pub bids: Vec<BookOrder>,
+    pub asks: Vec<BookOrder>,
+}
+
+impl PhoenixMarket {
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        if data.len() < BIDS_TREE_OFFSET {
+            anyhow::bail!("Invalid Phoenix market account ({} bytes)", data.len());
+        }
+        let read_u32 = |offset: usize| u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap());
+        let read_u64 = |offset: usize| u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap());
+        let read_pubkey = |offset: usize| Pubkey::new_from_array(data[offset..offset + 32].try_into().unwrap());
+
+        if read_u64(0) != MARKET_HEADER_DISCRIMINANT {
+            anyhow::bail!("Not a Phoenix market account");
+        }
+        let bids_size = read_u64(16) as usize;
+        let asks_size = read_u64(24) as usize;
+        let asks_offset = BIDS_TREE_OFFSET + TREE_HEADER_SIZE + bids_size * TREE_NODE_SIZE;
+
+        Ok(Self {
+            status: read_u64(8),
+            base_decimals: read_u32(40),
+            base_mint: read_pubkey(48),
+            base_lot_size: read_u64(112),
+            quote_decimals: read_u32(120),
+            quote_mint: read_pubkey(128),
+            quote_lot_size: read_u64(192),
+            base_lots_per_base_unit: read_u64(832),
+            tick_size_in_quote_lots_per_base_unit: read_u64(840),
+            taker_fee_bps: read_u64(856),
+            bids: read_order_tree(data, BIDS_TREE_OFFSET, bids_size)?,
+            asks: read_order_tree(data, asks_offset, asks_size)?,
+        })
+    }
+
+    pub fn book_params(&self) -> BookParams {
+        BookParams {
+            base_lot_size: self.base_lot_size,
+            quote_lot_size: self.quote_lot_size,
+            quote_lots_per_price_num: self.tick_size_in_quote_lots_per_base_unit,
+            quote_lots_per_price_den: self.base_lots_per_base_unit,
+            taker_fee_ppm: self.taker_fee_bps * TAKER_FEE_BPS_TO_PPM,
+            fee_basis: FeeBasis::QuoteLots,
+        }
+    }
+
+    /// Aggregated book of orders still live at unix time `now`.
+    /// Slot-based expiries are not checked.
+    pub fn book(&self, now: u64) -> Result<L2Book> {
+        L2Book::from_orders(self.book_params(), &self.bids, &self.asks, now)
+    }
+}
+
+/// Collect every order reachable from the root of a sokoban red-black tree
+/// of `FIFOOrderId` -> `FIFORestingOrder`
+fn read_order_tree(data: &[u8], offset: usize, capacity: usize) -> Result<Vec<BookOrder>> {
+    let end = offset + TREE_HEADER_SIZE + capacity * TREE_NODE_SIZE;
+    if data.len() < end {
+        anyhow::bail!("Phoenix market truncated: order tree ends at {} of {} bytes", end, data.len());
+    }
+    let read_u32 = |offset: usize| u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap());
+    let read_u64 = |offset: usize| u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap());
+
+    let mut orders = Vec::new();
+    let mut visited = vec![false; capacity];
+    let mut stack = vec![read_u32(offset)];
+    while let Some(handle) = stack.pop() {
+        if handle == NIL {
+            continue;
+        }
+        let index = handle as usize - 1;
+        if index >= capacity || std::mem::replace(&mut visited[index], true) {
+            anyhow::bail!("Corrupt Phoenix order tree at node {}", handle);
+        }
+        let node = offset + TREE_HEADER_SIZE + index * TREE_NODE_SIZE;
+        stack.push(read_u32(node));
+        stack.push(read_u32(node + 4));
+
+        let last_valid_unix_timestamp = read_u64(node + 56);
+        orders.push(BookOrder {
+            price: read_u64(node + 16),
+            base_lots: read_u64(node + 40),
+            // The order can still match during its last valid second
+            expires_at: (last_valid_unix_timestamp != 0).then_some(last_valid_unix_timestamp + 1),
+        });
+    }
+    Ok(orders)
+}
+
+#[derive(Default)]
+pub struct PhoenixClient {}
+
+impl PhoenixClient {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl PoolQuoter for PhoenixClient {
+    fn dex(&self) -> DexType {
+        DexType::Phoenix
+    }
+
+    fn program_id(&self) -> Pubkey {
+        PHOENIX_PROGRAM_ID
+    }
+
+    fn decode(&self, address: &Pubkey, data: &[u8]) -> Result<DecodedPool, PriceError> {
+        let market = PhoenixMarket::from_bytes(data).map_err(|err| PriceError::layout(*address, err))?;
+        Ok(DecodedPool {
+            address: *address,
+            dex: DexType::Phoenix,
+            mint_a: market.base_mint,
+            mint_b: market.quote_mint,
+            state: PoolState::Phoenix(market),
+            observed: Observation::default(),
+        })
+    }
+
+    /// The book lives in the market account itself
+    fn required_accounts(&self, _pool: &DecodedPool) -> Vec<Pubkey> {
+        Vec::new()
+    }
+
+    fn mid_price(&self, pool: &DecodedPool, _accounts: &AccountMap) -> Result<Price, PriceError> {
+        let PoolState::Phoenix(market) = &pool.state else {
+            return Err(PriceError::layout(pool.address, "not a Phoenix market"));
+        };
+        Ok(market.book(unix_timestamp())?.mid_price()?)
+    }
+
+    fn quote_exact_in(
+        &self,
+        pool: &DecodedPool,
+        _accounts: &AccountMap,
+        amount_in: u64,
+        direction: SwapDirection,
+    ) -> Result<SwapQuote, PriceError> {
+        let PoolState::Phoenix(market) = &pool.state else {
+            return Err(PriceError::layout(pool.address, "not a Phoenix market"));
+        };
+        if market.status != MARKET_STATUS_ACTIVE {
+            return Err(anyhow::anyhow!("Phoenix market {} is not active (status {})", pool.address, market.status).into());
+        }
+        Ok(market.book(unix_timestamp())?.quote_exact_in(amount_in, direction)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orderbook::L2Level;
+    use crate::test_utils::{put_u32, put_u64};
+
+    const TREE_SIZE: usize = 4;
+
+    /// Write a tree whose root is node 1 with nodes 2 and 3 as its children
+    fn put_tree(data: &mut [u8], offset: usize, orders: &[(u64, u64, u64)]) {
+        put_u32(data, offset, 1);
+        for (i, &(price, base_lots, last_valid_ts)) in orders.iter().enumerate() {
+            let node = offset + TREE_HEADER_SIZE + i * TREE_NODE_SIZE;
+            if i == 0 {
+                put_u32(data, node, if orders.len() > 1 { 2 } else { NIL });
+                put_u32(data, node + 4, if orders.len() > 2 { 3 } else { NIL });
+            } else {
+                put_u32(data, node + 8, 1);
+            }
+            put_u64(data, node + 16, price);
+            put_u64(data, node + 24, i as u64);
+            put_u64(data, node + 40, base_lots);
+            put_u64(data, node + 56, last_valid_ts);
+        }
+    }
+
+    fn market_data(bids: &[(u64, u64, u64)], asks: &[(u64, u64, u64)]) -> Vec<u8> {
+        let tree_bytes = TREE_HEADER_SIZE + TREE_SIZE * TREE_NODE_SIZE;
+        let mut data = vec![0u8; BIDS_TREE_OFFSET + 3 * tree_bytes];
+        put_u64(&mut data, 0, MARKET_HEADER_DISCRIMINANT);
+        put_u64(&mut data, 8, MARKET_STATUS_ACTIVE);
+        put_u64(&mut data, 16, TREE_SIZE as u64);
+        put_u64(&mut data, 24, TREE_SIZE as u64);
+        put_u64(&mut data, 32, TREE_SIZE as u64);
+        put_u32(&mut data, 40, 9);
+        put_u64(&mut data, 112, 1_000_000);
+        put_u32(&mut data, 120, 6);
+        put_u64(&mut data, 192, 1);
+        // 1000 base lots per SOL, 0.001 USDC ticks
+        put_u64(&mut data, 832, 1_000);
+        put_u64(&mut data, 840, 1_000);
+        put_u64(&mut data, 856, 2);
+        put_tree(&mut data, BIDS_TREE_OFFSET, bids);
+        put_tree(&mut data, BIDS_TREE_OFFSET + tree_bytes, asks);
+        data
+    }
+
+    #[test]
+    fn test_decode_market_book() {
+        let data = market_data(&[(149_990, 300, 0), (149_980, 500, 0), (149_990, 200, 50)], &[(150_010, 700, 0)]);
+        let market = PhoenixMarket::from_bytes(&data).unwrap();
+        assert_eq!(market.bids.len(), 3);
+
+        let book = market.book(10).unwrap();
+        assert_eq!(
+            book.bids,
+            vec![L2Level { price: 149_990, base_lots: 500 }, L2Level { price: 149_980, base_lots: 500 }]
+        );
+        // 149.99 USDC per SOL is 0.14999 USDC atoms per lamport
+        assert_eq!(book.best_bid().unwrap(), Some(Price::from_ratio(14_999, 100_000).unwrap()));
+        assert!((book.best_ask().unwrap().unwrap().to_f64() - 0.15001).abs() < 1e-12);
+
+        // The second bid order has expired by t=51
+        assert_eq!(market.book(51).unwrap().bids[0], L2Level { price: 149_990, base_lots: 300 });
+    }
+
+    #[test]
+    fn test_quote_through_client() {
+        let data = market_data(&[(149_990, 300, 0), (149_980, 500, 0)], &[(150_010, 700, 0)]);
+        let client = PhoenixClient::new();
+        let pool = client.decode(&Pubkey::new_unique(), &data).unwrap();
+
+        // Sell 0.5 SOL: 300 lots at 149.99 and 200 at 149.98
+        let quote = client
+            .quote_exact_in(&pool, &AccountMap::new(), 500_000_000, SwapDirection::AToB)
+            .unwrap();
+        let gross = 300 * 149_990 + 200 * 149_980;
+        assert_eq!(quote.fee_amount, (gross * 200u64).div_ceil(1_000_000));
+        assert_eq!(quote.amount_out, gross - quote.fee_amount);
+        assert!(quote.price_impact_bps > 0.0);
+
+        let mut closed = data.clone();
+        put_u64(&mut closed, 8, 4);
+        let pool = client.decode(&Pubkey::new_unique(), &closed).unwrap();
+        assert!(client
+            .quote_exact_in(&pool, &AccountMap::new(), 500_000_000, SwapDirection::AToB)
+            .is_err());
+    }
+}
@@ -0,0 +1,211 @@
+//! Fixed-point prices.
+//!
+//! Prices are unsigned Q64.64, the same representation the CLMM and DLMM
+//! programs use on chain, so pool prices convert without going through
+//! floating point and compare exactly. Resolution is 2^-64 (about 5e-20),
+//! which leaves raw-unit prices as small as 1e-12 with seven significant
+//! digits. Below [`Price::MIN_PRECISE`] rounding error grows quickly: a
+//! token with 18 decimals worth a millionth of a 6-decimal token has a raw
+//! price of 1e-18, only 18 units of resolution. Such prices are rejected
+//! when a [`PoolPrice`](crate::quoter::PoolPrice) is built; the inverse
+//! price is large and exact.
+
+use anyhow::Result;
+use serde::{Serialize, Serializer};
+use std::fmt;
+
+use crate::clmm_math::mul_div;
+
+const ONE_X64: u128 = 1 << 64;
+const BPS: u128 = 10_000;
+
+/// Unsigned Q64.64 fixed-point price
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Price(u128);
+
+impl Price {
+    pub const ZERO: Price = Price(0);
+    pub const ONE: Price = Price(ONE_X64);
+    /// Smallest price held to seven significant digits: 2^24 units of
+    /// resolution, about 9.1e-13
+    pub const MIN_PRECISE: Price = Price(1 << 24);
+
+    pub const fn from_x64(x64: u128) -> Self {
+        Self(x64)
+    }
+
+    pub const fn to_x64(self) -> u128 {
+        self.0
+    }
+
+    pub const fn from_integer(value: u64) -> Self {
+        Self((value as u128) << 64)
+    }
+
+    /// `numerator / denominator`, rounded down
+    pub fn from_ratio(numerator: u128, denominator: u128) -> Result<Self> {
+        Ok(Self(mul_div(numerator, ONE_X64, denominator, false)?))
+    }
+
+    /// Price encoded by a Q64.64 CLMM sqrt price, `(sqrt_price / 2^64)^2`
+    pub fn from_sqrt_price_x64(sqrt_price_x64: u128) -> Result<Self> {
+        Ok(Self(mul_div(sqrt_price_x64, sqrt_price_x64, ONE_X64, false)?))
+    }
+
+    /// `mantissa * 10^exponent`, as oracles publish prices
+    pub fn from_decimal(mantissa: u128, exponent: i32) -> Result<Self> {
+        let power = 10u128
+            .checked_pow(exponent.unsigned_abs())
+            .ok_or_else(|| anyhow::anyhow!("Decimal exponent {} out of range", exponent))?;
+        if exponent >= 0 {
+            Self::from_ratio(mantissa, 1)?.mul_ratio(power, 1)
+        } else {
+            Self::from_ratio(mantissa, power)
+        }
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Whether the price is at or above [`MIN_PRECISE`](Self::MIN_PRECISE)
+    pub fn is_precise(self) -> bool {
+        self >= Self::MIN_PRECISE
+    }
+
+    /// `self * numerator / denominator`, rounded down
+    pub fn mul_ratio(self, numerator: u128, denominator: u128) -> Result<Self> {
+        Ok(Self(mul_div(self.0, numerator, denominator, false)?))
+    }
+
+    /// Price of the other token in terms of this one, rounded down
+    pub fn invert(self) -> Result<Self> {
+        if self.is_zero() {
+            anyhow::bail!("Cannot invert a zero price");
+        }
+        Ok(Self(mul_div(ONE_X64, ONE_X64, self.0, false)?))
+    }
+
+    /// Convert a raw-unit price (B atoms per A atom) into whole tokens of B
+    /// per whole token of A
+    pub fn scale_decimals(self, decimals_a: u8, decimals_b: u8) -> Result<Self> {
+        let shift = decimals_a as i32 - decimals_b as i32;
+        let factor = 10u128
+            .checked_pow(shift.unsigned_abs())
+            .ok_or_else(|| anyhow::anyhow!("Decimal shift {} out of range", shift))?;
+        if shift >= 0 {
+            self.mul_ratio(factor, 1)
+        } else {
+            self.mul_ratio(1, factor)
+        }
+    }
+
+    /// Halfway between two prices, rounded down
+    pub fn midpoint(self, other: Price) -> Self {
+        Self(self.0 / 2 + other.0 / 2 + (self.0 & other.0 & 1))
+    }
+
+    pub fn abs_diff(self, other: Price) -> Self {
+        Self(self.0.abs_diff(other.0))
+    }
+
+    /// Spread between two prices in basis points of the lower one, rounded down
+    pub fn spread_bps(self, other: Price) -> Result<u64> {
+        let lower = self.min(other);
+        if lower.is_zero() {
+            anyhow::bail!("Spread against a zero price is undefined");
+        }
+        let bps = mul_div(self.abs_diff(other).0, BPS, lower.0, false)?;
+        Ok(bps.min(u64::MAX as u128) as u64)
+    }
+
+    /// This value in basis points of `reference`
+    pub fn bps_of(self, reference: Price) -> Result<f64> {
+        if reference.is_zero() {
+            anyhow::bail!("Basis points of a zero price are undefined");
+        }
+        let bps_x64 = mul_div(self.0, BPS << 64, reference.0, false)?;
+        Ok(Self(bps_x64).to_f64())
+    }
+
+    /// Distance from `reference` in basis points of `reference`
+    pub fn deviation_bps(self, reference: Price) -> Result<f64> {
+        self.abs_diff(reference).bps_of(reference)
+    }
+
+    /// Lossy conversion for display and statistics
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 / ONE_X64 as f64
+    }
+}
+
+impl fmt::Display for Price {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_f64())
+    }
+}
+
+/// Serialized as a JSON number so downstream consumers keep working
+impl Serialize for Price {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_f64(self.to_f64())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exact_ratio_and_inversion() {
+        let price = Price::from_ratio(3, 2).unwrap();
+        assert_eq!(price.to_x64(), 3 << 63);
+        assert_eq!(price.invert().unwrap(), Price::from_ratio(2, 3).unwrap());
+        assert_eq!(Price::from_integer(4).invert().unwrap().invert().unwrap(), Price::from_integer(4));
+        assert!(Price::ZERO.invert().is_err());
+
+        // Orders exactly where f64 cannot tell the two apart
+        let a = Price::from_ratio(u64::MAX as u128, 1 << 20).unwrap();
+        let b = Price::from_x64(a.to_x64() + 1);
+        assert!(a < b);
+        assert_eq!(a.to_f64(), b.to_f64());
+    }
+
+    #[test]
+    fn test_sqrt_price_and_decimals() {
+        // sqrt price for 0.15 micro-USDC per lamport, i.e. 150 USDC per SOL
+        let sqrt_price = (0.15f64.sqrt() * ONE_X64 as f64) as u128;
+        let raw = Price::from_sqrt_price_x64(sqrt_price).unwrap();
+        assert!((raw.to_f64() - 0.15).abs() < 1e-12);
+        let ui = raw.scale_decimals(9, 6).unwrap();
+        assert!((ui.to_f64() - 150.0).abs() < 1e-9);
+        assert!(raw.to_x64() - ui.scale_decimals(6, 9).unwrap().to_x64() <= 1);
+
+        assert_eq!(Price::from_decimal(15_025, -2).unwrap(), Price::from_ratio(15_025, 100).unwrap());
+        assert_eq!(Price::from_decimal(3, 2).unwrap(), Price::from_integer(300));
+    }
+
+    #[test]
+    fn test_spread_and_deviation() {
+        let a = Price::from_integer(100);
+        let b = Price::from_ratio(801, 8).unwrap();
+        assert_eq!(a.spread_bps(b).unwrap(), 12);
+        assert_eq!(b.spread_bps(a).unwrap(), 12);
+        assert_eq!(a.midpoint(b), Price::from_ratio(1_601, 16).unwrap());
+        assert_eq!(b.deviation_bps(a).unwrap(), 12.5);
+        assert!(a.spread_bps(Price::ZERO).is_err());
+    }
+
+    #[test]
+    fn test_precision_floor() {
+        // 1 micro-unit of an 18-decimal token for one 6-decimal token: raw
+        // 1e6 atoms per 1e24 atoms is 1e-18, a mere 18 units of resolution
+        let tiny = Price::from_ratio(1_000_000, 10u128.pow(24)).unwrap();
+        assert_eq!(tiny.to_x64(), 18);
+        assert!(!tiny.is_precise());
+        assert!(tiny.invert().unwrap().is_precise());
+
+        assert!(Price::from_ratio(1, 10u128.pow(12)).unwrap().is_precise());
+        assert!(!Price::from_ratio(1, 10u128.pow(13)).unwrap().is_precise());
+    }
+}
@@ -0,0 +1,723 @@
+use anyhow::Result;
+use orca_whirlpools_client::Whirlpool;
+use rpc_manager::RpcBackend;
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::PriceError;
+use crate::freshness::Observation;
+use crate::meteora::{LbPair, MeteoraClient};
+use crate::meteora_amm::{DynamicAmmPool, MeteoraAmmClient};
+use crate::openbook::{OpenBookClient, OpenBookMarket};
+use crate::orca::OrcaClient;
+use crate::orderbook::L2Level;
+use crate::phoenix::{PhoenixClient, PhoenixMarket};
+use crate::price::Price;
+use crate::raydium::{AmmV4State, RaydiumClient};
+use crate::raydium_clmm::{ClmmPoolState, RaydiumClmmClient};
+use crate::raydium_cpmm::{CpmmPoolState, RaydiumCpmmClient};
+use crate::token::{MintInfo, MintRegistry};
+
+/// Accounts fetched for quoting a pool, keyed by address
+pub type AccountMap = HashMap<Pubkey, Account>;
+
+/// Supported DEX Types
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum DexType {
+    RaydiumV4,
+    RaydiumCPMM,
+    RaydiumCLMM,
+    OrcaWhirlpool,
+    MeteoraDLMM,
+    MeteoraAmm,
+    Phoenix,
+    OpenBookV2,
+}
+
+/// Swap direction relative to the pool's (A, B) token ordering
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwapDirection {
+    AToB,
+    BToA,
+}
+
+/// DEX-specific decoded pool account
+#[derive(Debug, Clone)]
+pub enum PoolState {
+    RaydiumV4(AmmV4State),
+    RaydiumCpmm(CpmmPoolState),
+    RaydiumClmm(ClmmPoolState),
+    Whirlpool(Whirlpool),
+    MeteoraDlmm(LbPair),
+    MeteoraAmm(DynamicAmmPool),
+    Phoenix(PhoenixMarket),
+    OpenBookV2(OpenBookMarket),
+}
+
+/// A decoded pool with its address and token pair.
+/// For Raydium V4, A is the base (coin) mint and B the quote (pc) mint;
+/// for CPMM and CLMM, A is token 0 and B token 1;
+/// for DLMM, A is token X and B token Y;
+/// for order books, A is the base mint and B the quote mint.
+#[derive(Debug, Clone)]
+pub struct DecodedPool {
+    pub address: Pubkey,
+    pub dex: DexType,
+    pub mint_a: Pubkey,
+    pub mint_b: Pubkey,
+    pub state: PoolState,
+    /// Slot the pool account was read at. Quoters decode without I/O and
+    /// leave this unstamped; the dispatcher, snapshots and trackers fill it in.
+    pub observed: Observation,
+}
+
+/// Pool price as a raw-unit ratio and adjusted for mint decimals
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolPrice {
+    /// Raw units of token B per raw unit of token A
+    pub raw: Price,
+    /// Whole token B per whole token A
+    pub ui: Price,
+}
+
+impl PoolPrice {
+    /// Fails with [`PriceError::Imprecise`] when either price is below
+    /// [`Price::MIN_PRECISE`]
+    pub fn new(raw: Price, decimals_a: u8, decimals_b: u8) -> Result<Self> {
+        let ui = raw.scale_decimals(decimals_a, decimals_b)?;
+        if let Some(imprecise) = [raw, ui].into_iter().find(|price| !price.is_precise()) {
+            anyhow::bail!(PriceError::Imprecise(imprecise));
+        }
+        Ok(Self { raw, ui })
+    }
+}
+
+/// Pool state after a quoted swap executes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PostTradeState {
+    /// Constant-product pools: reserves of token A and B
+    Reserves { reserve_a: u64, reserve_b: u64 },
+    /// Concentrated-liquidity pools
+    Concentrated {
+        sqrt_price_x64: u128,
+        tick_current_index: i32,
+        liquidity: u128,
+    },
+    /// Liquidity-book pools: the bin the swap ended in
+    Bins { active_id: i32, volatility_accumulator: u32 },
+    /// Order books: the best level left on the side the taker hit
+    Book { top_of_book: Option<L2Level> },
+}
+
+/// Result of an exact-amount swap quote, in raw token units
+#[derive(Debug, Clone, PartialEq)]
+pub struct SwapQuote {
+    /// Total input paid, including the fee
+    pub amount_in: u64,
+    pub amount_out: u64,
+    /// Portion of `amount_in` taken as the trading fee. Order books charge
+    /// takers in the quote token, so selling base there reports it in output units.
+    pub fee_amount: u64,
+    /// Execution price shortfall versus the pre-trade mid price, excluding fees
+    pub price_impact_bps: f64,
+    pub post_state: PostTradeState,
+    /// Token-2022 transfer fees withheld outside the pool
+    pub transfer_fees: TransferFees,
+    /// When the quoted pool was read, copied from the pool by the dispatcher
+    pub observed: Observation,
+}
+
+/// Token-2022 transfer fees on the legs of a swap, in raw units of each leg's token
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TransferFees {
+    /// Withheld from the input on its way into the pool
+    pub input: u64,
+    /// Withheld from the output on its way to the trader
+    pub output: u64,
+}
+
+/// Quote an exact-in swap net of Token-2022 transfer fees on both mints.
+///
+/// The pool only receives `amount_in` less the input mint's fee, so `quote`
+/// is called with that reduced amount, and the trader only receives the
+/// pool's output less the output mint's fee. Interest-bearing mints need no
+/// adjustment since interest only changes UI amounts; transfer hooks cannot
+/// be simulated and are not accounted for.
+pub fn quote_exact_in_with_transfer_fees(
+    amount_in: u64,
+    mint_in: &MintInfo,
+    mint_out: &MintInfo,
+    epoch: u64,
+    quote: impl FnOnce(u64) -> Result<SwapQuote, PriceError>,
+) -> Result<SwapQuote, PriceError> {
+    let input_fee = mint_in.extensions.transfer_fee(epoch, amount_in);
+    let mut quote = quote(amount_in - input_fee)?;
+    let output_fee = mint_out.extensions.transfer_fee(epoch, quote.amount_out);
+    quote.amount_in = amount_in;
+    quote.amount_out -= output_fee;
+    quote.transfer_fees = TransferFees {
+        input: input_fee,
+        output: output_fee,
+    };
+    Ok(quote)
+}
+
+/// Quote an exact-out swap gross of Token-2022 transfer fees on both mints.
+///
+/// The pool must send enough that `amount_out` is left once the output
+/// mint's fee is withheld, so `quote` is called with that larger amount;
+/// the trader then sends enough that the input the pool asks for arrives
+/// after the input mint's fee.
+pub fn quote_exact_out_with_transfer_fees(
+    amount_out: u64,
+    mint_in: &MintInfo,
+    mint_out: &MintInfo,
+    epoch: u64,
+    quote: impl FnOnce(u64) -> Result<SwapQuote, PriceError>,
+) -> Result<SwapQuote, PriceError> {
+    let pool_out = mint_out
+        .extensions
+        .pre_fee_amount(epoch, amount_out)
+        .ok_or_else(|| anyhow::anyhow!("Output {} grossed up for its transfer fee exceeds u64", amount_out))?;
+    let mut quote = quote(pool_out)?;
+    let amount_in = mint_in
+        .extensions
+        .pre_fee_amount(epoch, quote.amount_in)
+        .ok_or_else(|| anyhow::anyhow!("Input {} grossed up for its transfer fee exceeds u64", quote.amount_in))?;
+    let output_fee = mint_out.extensions.transfer_fee(epoch, quote.amount_out);
+    quote.transfer_fees = TransferFees {
+        input: amount_in - quote.amount_in,
+        output: output_fee,
+    };
+    quote.amount_in = amount_in;
+    quote.amount_out -= output_fee;
+    Ok(quote)
+}
+
+/// Current epoch from the Clock sysvar account
+pub fn clock_epoch(account: &Account) -> Result<u64, PriceError> {
+    let bytes = account.data.get(16..24).ok_or_else(|| {
+        PriceError::layout(solana_sdk::sysvar::clock::ID, format!("Clock sysvar is {} bytes", account.data.len()))
+    })?;
+    Ok(u64::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+/// Price impact of receiving `amount_out` for `amount_in_after_fee`, given
+/// the pre-trade mid price in output per input units. A fill at or better
+/// than mid has zero impact; an impact that cannot be computed is an error
+pub fn price_impact_bps(mid_out_per_in: Price, amount_in_after_fee: u64, amount_out: u64) -> Result<f64> {
+    if amount_in_after_fee == 0 {
+        anyhow::bail!("Price impact is undefined when no input reaches the pool");
+    }
+    let execution = Price::from_ratio(amount_out as u128, amount_in_after_fee as u128)?;
+    if execution >= mid_out_per_in {
+        return Ok(0.0);
+    }
+    execution.deviation_bps(mid_out_per_in)
+}
+
+/// Current Unix time in seconds, for pool states that decay or unlock over time
+pub(crate) fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+/// Common interface implemented by every DEX client.
+///
+/// Quoting is split into a pure decode step and a pricing step so callers
+/// can batch the account fetches for many pools.
+pub trait PoolQuoter: Send + Sync {
+    fn dex(&self) -> DexType;
+
+    /// Program that owns this DEX's pool accounts
+    fn program_id(&self) -> Pubkey;
+
+    /// Decode a pool account's data
+    fn decode(&self, address: &Pubkey, data: &[u8]) -> Result<DecodedPool, PriceError>;
+
+    /// Accounts besides the pool itself needed to price it (vaults etc.)
+    fn required_accounts(&self, pool: &DecodedPool) -> Vec<Pubkey>;
+
+    /// Accounts needed to quote swaps, a superset of `required_accounts`
+    fn quote_accounts(&self, pool: &DecodedPool) -> Vec<Pubkey> {
+        self.required_accounts(pool)
+    }
+
+    /// Mid price of token A denominated in token B, in raw units
+    fn mid_price(&self, pool: &DecodedPool, accounts: &AccountMap) -> Result<Price, PriceError>;
+
+    /// Quote a swap of exactly `amount_in` of the input token
+    fn quote_exact_in(
+        &self,
+        _pool: &DecodedPool,
+        _accounts: &AccountMap,
+        _amount_in: u64,
+        _direction: SwapDirection,
+    ) -> Result<SwapQuote, PriceError> {
+        Err(anyhow::anyhow!("{:?} does not support exact-in quotes yet", self.dex()).into())
+    }
+
+    /// Quote a swap that yields exactly `amount_out` of the output token
+    fn quote_exact_out(
+        &self,
+        _pool: &DecodedPool,
+        _accounts: &AccountMap,
+        _amount_out: u64,
+        _direction: SwapDirection,
+    ) -> Result<SwapQuote, PriceError> {
+        Err(anyhow::anyhow!("{:?} does not support exact-out quotes yet", self.dex()).into())
+    }
+}
+
+/// Look up a pre-fetched account a quoter depends on
+pub(crate) fn required_account<'a>(accounts: &'a AccountMap, address: &Pubkey) -> Result<&'a Account, PriceError> {
+    accounts.get(address).ok_or(PriceError::AccountMissing(*address))
+}
+
+/// Routes pool accounts to the quoter registered for their owning program
+pub struct QuoterDispatcher {
+    quoters: HashMap<Pubkey, Box<dyn PoolQuoter>>,
+    mints: MintRegistry,
+}
+
+impl QuoterDispatcher {
+    /// Dispatcher with no quoters registered
+    pub fn empty() -> Self {
+        Self {
+            quoters: HashMap::new(),
+            mints: MintRegistry::new(),
+        }
+    }
+
+    /// Dispatcher with every supported DEX registered
+    pub fn new() -> Self {
+        let mut dispatcher = Self::empty();
+        dispatcher.register(Box::new(RaydiumClient::new()));
+        dispatcher.register(Box::new(RaydiumCpmmClient::new()));
+        dispatcher.register(Box::new(RaydiumClmmClient::new()));
+        dispatcher.register(Box::new(OrcaClient::new()));
+        dispatcher.register(Box::new(MeteoraClient::new()));
+        dispatcher.register(Box::new(MeteoraAmmClient::new()));
+        dispatcher.register(Box::new(PhoenixClient::new()));
+        dispatcher.register(Box::new(OpenBookClient::new()));
+        dispatcher
+    }
+
+    /// Register a quoter, replacing any existing one for the same program
+    pub fn register(&mut self, quoter: Box<dyn PoolQuoter>) {
+        self.quoters.insert(quoter.program_id(), quoter);
+    }
+
+    /// Mint metadata cache used for decimal adjustment
+    pub fn mints(&self) -> &MintRegistry {
+        &self.mints
+    }
+
+    pub fn get(&self, program_id: &Pubkey) -> Option<&dyn PoolQuoter> {
+        self.quoters.get(program_id).map(|q| q.as_ref())
+    }
+
+    /// Decode a pool account with the quoter for its owner program
+    pub fn decode(&self, address: &Pubkey, account: &Account) -> Result<DecodedPool, PriceError> {
+        self.quoter_for(&account.owner)?.decode(address, &account.data)
+    }
+
+    /// Fetch a pool and its dependent accounts, then compute its mid price
+    pub async fn fetch_mid_price<B: RpcBackend + ?Sized>(
+        &self,
+        rpc: &B,
+        pool_address: &Pubkey,
+    ) -> Result<Price, PriceError> {
+        let (quoter, pool, accounts) = self.fetch_with_quoter(rpc, pool_address, false).await?;
+        quoter.mid_price(&pool, &accounts)
+    }
+
+    /// Fetch a pool's mid price as both a raw ratio and a UI price
+    pub async fn fetch_price<B: RpcBackend + ?Sized>(
+        &self,
+        rpc: &B,
+        pool_address: &Pubkey,
+    ) -> Result<PoolPrice, PriceError> {
+        let (quoter, pool, accounts) = self.fetch_with_quoter(rpc, pool_address, false).await?;
+        let raw = quoter.mid_price(&pool, &accounts)?;
+        let mints = self.mints.resolve(rpc, &[pool.mint_a, pool.mint_b]).await?;
+        Ok(PoolPrice::new(raw, mints[0].decimals, mints[1].decimals)?)
+    }
+
+    /// Fetch and decode a pool along with every account needed to quote it
+    pub async fn fetch_pool<B: RpcBackend + ?Sized>(
+        &self,
+        rpc: &B,
+        pool_address: &Pubkey,
+    ) -> Result<(DecodedPool, AccountMap), PriceError> {
+        let (_, pool, accounts) = self.fetch_with_quoter(rpc, pool_address, true).await?;
+        Ok((pool, accounts))
+    }
+
+    /// Fetch a pool and quote a swap of exactly `amount_in`, net of any
+    /// Token-2022 transfer fees on either mint
+    pub async fn fetch_quote_exact_in<B: RpcBackend + ?Sized>(
+        &self,
+        rpc: &B,
+        pool_address: &Pubkey,
+        amount_in: u64,
+        direction: SwapDirection,
+    ) -> Result<SwapQuote, PriceError> {
+        let (_, pool, accounts) = self.fetch_with_quoter(rpc, pool_address, true).await?;
+        self.quote_exact_in(&pool, &accounts, amount_in, direction)
+    }
+
+    /// Fetch a pool and quote a swap yielding exactly `amount_out` after any
+    /// Token-2022 transfer fees on either mint
+    pub async fn fetch_quote_exact_out<B: RpcBackend + ?Sized>(
+        &self,
+        rpc: &B,
+        pool_address: &Pubkey,
+        amount_out: u64,
+        direction: SwapDirection,
+    ) -> Result<SwapQuote, PriceError> {
+        let (_, pool, accounts) = self.fetch_with_quoter(rpc, pool_address, true).await?;
+        self.quote_exact_out(&pool, &accounts, amount_out, direction)
+    }
+
+    /// Accounts needed to quote `pool` through the dispatcher: the quoter's
+    /// own plus both mints, and the Clock sysvar for the epoch when either
+    /// mint charges a transfer fee. Whether one does is read from the mint
+    /// registry, so resolve the pool's mints first.
+    pub fn quote_accounts(&self, pool: &DecodedPool) -> Result<Vec<Pubkey>, PriceError> {
+        let mut accounts = self.quoter_for_dex(pool.dex)?.quote_accounts(pool);
+        let charges_fee = [pool.mint_a, pool.mint_b]
+            .iter()
+            .any(|mint| self.mints.get(mint).is_some_and(|info| info.extensions.transfer_fee.is_some()));
+        let clock = charges_fee.then_some(solana_sdk::sysvar::clock::ID);
+        for key in [pool.mint_a, pool.mint_b].into_iter().chain(clock) {
+            if !accounts.contains(&key) {
+                accounts.push(key);
+            }
+        }
+        Ok(accounts)
+    }
+
+    /// Quote an exact-in swap on an already fetched pool, net of Token-2022
+    /// transfer fees and stamped with the pool's observation. `accounts`
+    /// must hold everything listed by [`quote_accounts`](Self::quote_accounts),
+    /// though mints already in the registry may be left out.
+    pub fn quote_exact_in(
+        &self,
+        pool: &DecodedPool,
+        accounts: &AccountMap,
+        amount_in: u64,
+        direction: SwapDirection,
+    ) -> Result<SwapQuote, PriceError> {
+        let quoter = self.quoter_for_dex(pool.dex)?;
+        let quote = |amount_in| quoter.quote_exact_in(pool, accounts, amount_in, direction);
+        let mut quote = match self.transfer_fee_context(pool, accounts, direction)? {
+            Some((mint_in, mint_out, epoch)) => {
+                quote_exact_in_with_transfer_fees(amount_in, &mint_in, &mint_out, epoch, quote)?
+            }
+            None => quote(amount_in)?,
+        };
+        quote.observed = pool.observed;
+        Ok(quote)
+    }
+
+    /// Quote an exact-out swap on an already fetched pool, gross of
+    /// Token-2022 transfer fees and stamped with the pool's observation.
+    /// Takes the same accounts as [`quote_exact_in`](Self::quote_exact_in).
+    pub fn quote_exact_out(
+        &self,
+        pool: &DecodedPool,
+        accounts: &AccountMap,
+        amount_out: u64,
+        direction: SwapDirection,
+    ) -> Result<SwapQuote, PriceError> {
+        let quoter = self.quoter_for_dex(pool.dex)?;
+        let quote = |amount_out| quoter.quote_exact_out(pool, accounts, amount_out, direction);
+        let mut quote = match self.transfer_fee_context(pool, accounts, direction)? {
+            Some((mint_in, mint_out, epoch)) => {
+                quote_exact_out_with_transfer_fees(amount_out, &mint_in, &mint_out, epoch, quote)?
+            }
+            None => quote(amount_out)?,
+        };
+        quote.observed = pool.observed;
+        Ok(quote)
+    }
+
+    /// Input and output mints of a swap and the current epoch, or `None`
+    /// when neither mint charges a transfer fee. Mint accounts in `accounts`
+    /// take precedence over the registry since fee schedules can change.
+    fn transfer_fee_context(
+        &self,
+        pool: &DecodedPool,
+        accounts: &AccountMap,
+        direction: SwapDirection,
+    ) -> Result<Option<(MintInfo, MintInfo, u64)>, PriceError> {
+        let mint = |address: &Pubkey| match accounts.get(address) {
+            Some(account) => MintInfo::from_account(address, account),
+            None => self.mints.get(address).ok_or(PriceError::AccountMissing(*address)),
+        };
+        let (mint_in, mint_out) = match direction {
+            SwapDirection::AToB => (mint(&pool.mint_a)?, mint(&pool.mint_b)?),
+            SwapDirection::BToA => (mint(&pool.mint_b)?, mint(&pool.mint_a)?),
+        };
+        if mint_in.extensions.transfer_fee.is_none() && mint_out.extensions.transfer_fee.is_none() {
+            return Ok(None);
+        }
+        let epoch = clock_epoch(required_account(accounts, &solana_sdk::sysvar::clock::ID)?)?;
+        Ok(Some((mint_in, mint_out, epoch)))
+    }
+
+    /// Fetch a pool and then its dependencies, the latter no earlier than
+    /// the slot the pool was read at. The pool is stamped with the slot of
+    /// the second read, the newest data it was priced from.
+    async fn fetch_with_quoter<B: RpcBackend + ?Sized>(
+        &self,
+        rpc: &B,
+        pool_address: &Pubkey,
+        for_quote: bool,
+    ) -> Result<(&dyn PoolQuoter, DecodedPool, AccountMap), PriceError> {
+        let (pool_slot, mut fetched) = rpc.get_multiple_accounts_with_slot(&[*pool_address], None).await?;
+        let account = fetched.pop().flatten().ok_or(PriceError::AccountMissing(*pool_address))?;
+        let quoter = self.quoter_for(&account.owner)?;
+        let mut pool = quoter.decode(pool_address, &account.data)?;
+
+        let required = if for_quote {
+            // Resolved mints tell whether the Clock is needed for transfer fees
+            self.mints.resolve(rpc, &[pool.mint_a, pool.mint_b]).await?;
+            self.quote_accounts(&pool)?
+        } else {
+            quoter.required_accounts(&pool)
+        };
+        let (slot, fetched) = if required.is_empty() {
+            (pool_slot, Vec::new())
+        } else {
+            rpc.get_multiple_accounts_with_slot(&required, Some(pool_slot)).await?
+        };
+        let accounts = required
+            .into_iter()
+            .zip(fetched)
+            .filter_map(|(key, acc)| acc.map(|acc| (key, acc)))
+            .collect();
+        pool.observed = Observation::received_now(slot);
+
+        Ok((quoter, pool, accounts))
+    }
+
+    fn quoter_for_dex(&self, dex: DexType) -> Result<&dyn PoolQuoter, PriceError> {
+        self.quoters
+            .values()
+            .find(|quoter| quoter.dex() == dex)
+            .map(|quoter| quoter.as_ref())
+            .ok_or_else(|| anyhow::anyhow!("No quoter registered for {:?}", dex).into())
+    }
+
+    fn quoter_for(&self, program_id: &Pubkey) -> Result<&dyn PoolQuoter, PriceError> {
+        self.get(program_id)
+            .ok_or_else(|| anyhow::anyhow!("No quoter registered for program {}", program_id).into())
+    }
+}
+
+impl Default for QuoterDispatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raydium::{AMM_V4_STATE_SIZE, RAYDIUM_V4_PROGRAM_ID};
+    use crate::token::{MintExtensions, TransferFee, TransferFeeConfig, SPL_TOKEN_PROGRAM_ID, TOKEN_2022_PROGRAM_ID};
+
+    /// Pays out twice the input, without a trading fee
+    struct DoublingQuoter;
+
+    fn swap(amount_in: u64, amount_out: u64) -> SwapQuote {
+        SwapQuote {
+            amount_in,
+            amount_out,
+            fee_amount: 0,
+            price_impact_bps: 0.0,
+            post_state: PostTradeState::Reserves { reserve_a: 0, reserve_b: 0 },
+            transfer_fees: TransferFees::default(),
+            observed: Observation::default(),
+        }
+    }
+
+    impl PoolQuoter for DoublingQuoter {
+        fn dex(&self) -> DexType {
+            DexType::RaydiumV4
+        }
+
+        fn program_id(&self) -> Pubkey {
+            RAYDIUM_V4_PROGRAM_ID
+        }
+
+        fn decode(&self, address: &Pubkey, data: &[u8]) -> Result<DecodedPool, PriceError> {
+            RaydiumClient::new().decode(address, data)
+        }
+
+        fn required_accounts(&self, _pool: &DecodedPool) -> Vec<Pubkey> {
+            Vec::new()
+        }
+
+        fn mid_price(&self, _pool: &DecodedPool, _accounts: &AccountMap) -> Result<Price, PriceError> {
+            Ok(Price::from_integer(2))
+        }
+
+        fn quote_exact_in(
+            &self,
+            _pool: &DecodedPool,
+            _accounts: &AccountMap,
+            amount_in: u64,
+            direction: SwapDirection,
+        ) -> Result<SwapQuote, PriceError> {
+            Ok(match direction {
+                SwapDirection::AToB => swap(amount_in, amount_in * 2),
+                SwapDirection::BToA => swap(amount_in, amount_in / 2),
+            })
+        }
+
+        fn quote_exact_out(
+            &self,
+            _pool: &DecodedPool,
+            _accounts: &AccountMap,
+            amount_out: u64,
+            direction: SwapDirection,
+        ) -> Result<SwapQuote, PriceError> {
+            Ok(match direction {
+                SwapDirection::AToB => swap(amount_out.div_ceil(2), amount_out),
+                SwapDirection::BToA => swap(amount_out * 2, amount_out),
+            })
+        }
+    }
+
+    fn mint(token_program: Pubkey, transfer_fee_basis_points: u16) -> MintInfo {
+        let fee = TransferFee {
+            epoch: 0,
+            maximum_fee: u64::MAX,
+            transfer_fee_basis_points,
+        };
+        MintInfo {
+            decimals: 6,
+            token_program,
+            extensions: MintExtensions {
+                transfer_fee: (transfer_fee_basis_points > 0).then_some(TransferFeeConfig {
+                    older_transfer_fee: fee,
+                    newer_transfer_fee: fee,
+                }),
+                ..MintExtensions::default()
+            },
+        }
+    }
+
+    fn clock(epoch: u64) -> Account {
+        let mut data = vec![0u8; 40];
+        data[16..24].copy_from_slice(&epoch.to_le_bytes());
+        Account {
+            data,
+            owner: solana_sdk::sysvar::ID,
+            ..Account::default()
+        }
+    }
+
+    #[test]
+    fn test_token_2022_transfer_fee() {
+        let mut dispatcher = QuoterDispatcher::empty();
+        dispatcher.register(Box::new(DoublingQuoter));
+        let mut pool = DoublingQuoter.decode(&Pubkey::new_unique(), &[0u8; AMM_V4_STATE_SIZE]).unwrap();
+        (pool.mint_a, pool.mint_b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        pool.observed = Observation::received_now(500);
+        let clock_id = solana_sdk::sysvar::clock::ID;
+
+        // Plain SPL mints need neither the Clock nor any adjustment
+        dispatcher.mints().insert(pool.mint_a, mint(SPL_TOKEN_PROGRAM_ID, 0));
+        dispatcher.mints().insert(pool.mint_b, mint(SPL_TOKEN_PROGRAM_ID, 0));
+        assert_eq!(dispatcher.quote_accounts(&pool).unwrap(), vec![pool.mint_a, pool.mint_b]);
+        let mut accounts = AccountMap::new();
+        let quote = dispatcher.quote_exact_in(&pool, &accounts, 10_000, SwapDirection::AToB).unwrap();
+        assert_eq!((quote.amount_in, quote.amount_out), (10_000, 20_000));
+        assert_eq!(quote.observed, pool.observed);
+
+        // Token A charges 1%, which needs the epoch
+        dispatcher.mints().insert(pool.mint_a, mint(TOKEN_2022_PROGRAM_ID, 100));
+        assert_eq!(dispatcher.quote_accounts(&pool).unwrap(), vec![pool.mint_a, pool.mint_b, clock_id]);
+        assert!(matches!(
+            dispatcher.quote_exact_in(&pool, &accounts, 10_000, SwapDirection::AToB),
+            Err(PriceError::AccountMissing(key)) if key == clock_id
+        ));
+        accounts.insert(clock_id, clock(700));
+
+        // A in: only 99% of the input reaches the pool
+        let quote = dispatcher.quote_exact_in(&pool, &accounts, 10_000, SwapDirection::AToB).unwrap();
+        assert_eq!((quote.amount_in, quote.amount_out), (10_000, 19_800));
+        assert_eq!(quote.transfer_fees, TransferFees { input: 100, output: 0 });
+        assert_eq!(quote.observed, pool.observed);
+
+        // A out: the pool's output loses 1% on its way to the trader
+        let quote = dispatcher.quote_exact_in(&pool, &accounts, 10_000, SwapDirection::BToA).unwrap();
+        assert_eq!((quote.amount_in, quote.amount_out), (10_000, 4_950));
+        assert_eq!(quote.transfer_fees, TransferFees { input: 0, output: 50 });
+
+        // Exact out grosses up the pool's output, then the input it asks for
+        let quote = dispatcher.quote_exact_out(&pool, &accounts, 4_950, SwapDirection::BToA).unwrap();
+        assert_eq!((quote.amount_in, quote.amount_out), (10_000, 4_950));
+        assert_eq!(quote.transfer_fees, TransferFees { input: 0, output: 50 });
+        let quote = dispatcher.quote_exact_out(&pool, &accounts, 19_800, SwapDirection::AToB).unwrap();
+        assert_eq!((quote.amount_in, quote.amount_out), (10_000, 19_800));
+        assert_eq!(quote.transfer_fees, TransferFees { input: 100, output: 0 });
+
+        // Mint accounts in the map override the registry, and unknown mints are missing
+        let mut spl_mint = Account {
+            data: vec![0u8; 82],
+            owner: SPL_TOKEN_PROGRAM_ID,
+            ..Account::default()
+        };
+        spl_mint.data[44..46].copy_from_slice(&[6, 1]);
+        accounts.insert(pool.mint_a, spl_mint);
+        let quote = dispatcher.quote_exact_in(&pool, &accounts, 10_000, SwapDirection::AToB).unwrap();
+        assert_eq!(quote.transfer_fees, TransferFees::default());
+        pool.mint_b = Pubkey::new_unique();
+        assert!(matches!(
+            dispatcher.quote_exact_in(&pool, &accounts, 10_000, SwapDirection::AToB),
+            Err(PriceError::AccountMissing(key)) if key == pool.mint_b
+        ));
+    }
+
+    #[test]
+    fn test_price_impact_bps() {
+        let mid = Price::from_integer(2);
+        // Filling at or above mid has no impact
+        assert_eq!(price_impact_bps(mid, 100, 200).unwrap(), 0.0);
+        assert_eq!(price_impact_bps(mid, 100, 250).unwrap(), 0.0);
+        // 1% below mid
+        let impact = price_impact_bps(mid, 100, 198).unwrap();
+        assert!((impact - 100.0).abs() < 1e-6, "impact {}", impact);
+
+        // No input reaching the pool is reported, not zeroed
+        assert!(price_impact_bps(mid, 0, 198).is_err());
+    }
+
+    #[test]
+    fn test_pool_price_rejects_imprecise_pairs() {
+        // 1e6 tokens of an 18-decimal memecoin against 1 USDC: each is worth
+        // a millionth of a cent, 1e-18 raw
+        let raw = Price::from_ratio(1_000_000, 10u128.pow(24)).unwrap();
+        assert!(matches!(
+            PoolPrice::new(raw, 18, 6).map_err(PriceError::from),
+            Err(PriceError::Imprecise(price)) if price == raw
+        ));
+        // The same reserves priced the other way round are exact
+        let price = PoolPrice::new(Price::from_ratio(10u128.pow(24), 1_000_000).unwrap(), 6, 18).unwrap();
+        assert_eq!(price.ui, Price::from_integer(1_000_000));
+
+        // A precise raw price can still scale below the floor
+        let raw = Price::from_ratio(1, 1_000).unwrap();
+        assert!(PoolPrice::new(raw, 6, 18).is_err());
+        assert!(PoolPrice::new(raw, 9, 6).is_ok());
+    }
+}
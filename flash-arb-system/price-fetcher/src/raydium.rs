@@ -1,13 +1,26 @@
+use solana_sdk::account::Account;
 use solana_sdk::pubkey::Pubkey;
-use solana_client::nonblocking::rpc_client::RpcClient;
-use borsh::{BorshDeserialize, BorshSerialize};
+use rpc_manager::RpcBackend;
+use borsh::BorshDeserialize;
 use anyhow::Result;
 
+use crate::error::PriceError;
+use crate::freshness::Observation;
+use crate::price::Price;
+use crate::quoter::{
+    price_impact_bps, required_account, AccountMap, DecodedPool, DexType, PoolPrice, PoolQuoter, PoolState, PostTradeState, SwapDirection,
+    SwapQuote, TransferFees,
+};
+use crate::token::{vault_amount, MintRegistry, TokenAccount};
+
 // Raydium AMM V4 Program ID
-// pub const RAYDIUM_V4_PROGRAM_ID: Pubkey = solana_sdk::pubkey!("675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8");
+pub const RAYDIUM_V4_PROGRAM_ID: Pubkey = solana_sdk::pubkey!("675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8");
+
+/// Size of an AMM V4 pool account (`AmmInfo`)
+pub const AMM_V4_STATE_SIZE: usize = 752;
 
-/// Raydium AMM V4 Pool State (Simplified layout)
-#[derive(BorshDeserialize, Debug)]
+/// Raydium AMM V4 Pool State (`AmmInfo`, 752 bytes, no discriminator)
+#[derive(BorshDeserialize, Debug, Clone)]
 pub struct AmmV4State {
     pub status: u64,
     pub nonce: u64,
@@ -53,11 +66,238 @@ pub struct AmmV4State {
     pub base_mint: Pubkey,
     pub quote_mint: Pubkey,
     pub lp_mint: Pubkey,
-    // ... rest of fields omitted for brevity, we just need reserves usually
-    // But safely deserializing requires exact layout match.
-    // For robust production use, we fetch vault balances directly via getMultipleAccounts
+    // OpenBook (Serum) market integration
+    pub open_orders: Pubkey,
+    pub market: Pubkey,
+    pub market_program: Pubkey,
+    pub target_orders: Pubkey,
+    pub padding1: [u64; 8],
+    pub amm_owner: Pubkey,
+    pub lp_amount: u64,
+    pub client_order_id: u64,
+    pub recent_epoch: u64,
+    pub padding2: u64,
+}
+
+impl AmmV4State {
+    /// Decode a pool account, checking its size
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        if data.len() != AMM_V4_STATE_SIZE {
+            anyhow::bail!(
+                "Invalid AMM V4 account size: expected {} bytes, got {}",
+                AMM_V4_STATE_SIZE,
+                data.len()
+            );
+        }
+        Ok(Self::try_from_slice(data)?)
+    }
+
+    /// Decode a pool account, checking it is owned by the AMM V4 program
+    pub fn from_account(account: &Account) -> Result<Self> {
+        if account.owner != RAYDIUM_V4_PROGRAM_ID {
+            anyhow::bail!(
+                "Account owned by {} is not a Raydium AMM V4 pool",
+                account.owner
+            );
+        }
+        Self::from_bytes(&account.data)
+    }
+
+    /// Whether the pool currently places orders on its OpenBook market.
+    /// Mirrors `AmmStatus::orderbook_permission` in the AMM program:
+    /// only `Initialized` (1) and `OrderBookOnly` (5) pools do.
+    pub fn orderbook_enabled(&self) -> bool {
+        matches!(self.status, 1 | 5)
+    }
+}
+
+/// Size of an OpenBook (Serum v3) `OpenOrders` account
+pub const OPEN_ORDERS_SIZE: usize = 3228;
+
+/// Token totals held by the AMM's OpenBook open-orders account.
+/// Layout: 5-byte "serum" head, account_flags, market, owner, then
+/// native coin free/total and native pc free/total.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpenOrdersTotals {
+    pub base_free: u64,
+    pub base_total: u64,
+    pub quote_free: u64,
+    pub quote_total: u64,
+}
+
+impl OpenOrdersTotals {
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        if data.len() != OPEN_ORDERS_SIZE || &data[..5] != b"serum" {
+            anyhow::bail!("Invalid OpenBook open orders account ({} bytes)", data.len());
+        }
+        let read_u64 = |offset: usize| u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap());
+        Ok(Self {
+            base_free: read_u64(77),
+            base_total: read_u64(85),
+            quote_free: read_u64(93),
+            quote_total: read_u64(101),
+        })
+    }
+}
+
+/// Reserves the AMM actually trades against
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EffectiveReserves {
+    pub base: u64,
+    pub quote: u64,
+}
+
+/// Tradable reserves as computed by the swap instruction
+/// (`calc_total_without_take_pnl`): vault balances, plus funds parked in
+/// the open-orders account when the orderbook is enabled, minus PnL owed
+/// to the protocol.
+pub fn effective_reserves(
+    amm: &AmmV4State,
+    base_vault_amount: u64,
+    quote_vault_amount: u64,
+    open_orders: Option<&OpenOrdersTotals>,
+) -> Result<EffectiveReserves> {
+    let (mut base, mut quote) = (base_vault_amount, quote_vault_amount);
+
+    if amm.orderbook_enabled() {
+        let open_orders = open_orders
+            .ok_or_else(|| anyhow::anyhow!("Open orders account required while orderbook is enabled"))?;
+        base = base
+            .checked_add(open_orders.base_total)
+            .ok_or_else(|| anyhow::anyhow!("Base reserve overflow"))?;
+        quote = quote
+            .checked_add(open_orders.quote_total)
+            .ok_or_else(|| anyhow::anyhow!("Quote reserve overflow"))?;
+    }
+
+    let base = base
+        .checked_sub(amm.base_need_take_pnl)
+        .ok_or_else(|| anyhow::anyhow!("Base PnL exceeds base reserves"))?;
+    let quote = quote
+        .checked_sub(amm.quote_need_take_pnl)
+        .ok_or_else(|| anyhow::anyhow!("Quote PnL exceeds quote reserves"))?;
+
+    Ok(EffectiveReserves { base, quote })
+}
+
+/// Raydium's `CheckedCeilDiv`: ceiling division, except that a quotient
+/// below one rounds to the nearest integer instead of up
+fn checked_ceil_div(numerator: u128, denominator: u128) -> Result<u128> {
+    if denominator == 0 {
+        anyhow::bail!("Division by zero");
+    }
+    let quotient = numerator / denominator;
+    if quotient == 0 {
+        return Ok(if numerator * 2 >= denominator { 1 } else { 0 });
+    }
+    Ok(if !numerator.is_multiple_of(denominator) { quotient + 1 } else { quotient })
 }
 
+impl EffectiveReserves {
+    /// (reserve_in, reserve_out) for a swap direction; A is the base token
+    fn oriented(&self, direction: SwapDirection) -> (u128, u128) {
+        match direction {
+            SwapDirection::AToB => (self.base as u128, self.quote as u128),
+            SwapDirection::BToA => (self.quote as u128, self.base as u128),
+        }
+    }
+
+    fn post_trade(&self, direction: SwapDirection, amount_in: u64, amount_out: u64) -> PostTradeState {
+        let (reserve_a, reserve_b) = match direction {
+            SwapDirection::AToB => (self.base.saturating_add(amount_in), self.quote - amount_out),
+            SwapDirection::BToA => (self.base - amount_out, self.quote.saturating_add(amount_in)),
+        };
+        PostTradeState::Reserves { reserve_a, reserve_b }
+    }
+}
+
+/// Quote `swap_base_in`: the fee is taken from the input (rounded up),
+/// then the constant-product output is rounded down
+pub fn swap_base_in(
+    amm: &AmmV4State,
+    reserves: &EffectiveReserves,
+    amount_in: u64,
+    direction: SwapDirection,
+) -> Result<SwapQuote> {
+    let (reserve_in, reserve_out) = reserves.oriented(direction);
+    if reserve_in == 0 || reserve_out == 0 {
+        anyhow::bail!(PriceError::EmptyPool);
+    }
+
+    let fee = checked_ceil_div(amount_in as u128 * amm.swap_fee_numerator as u128, amm.swap_fee_denominator as u128)?;
+    let amount_in_after_fee = amount_in as u128 - fee.min(amount_in as u128);
+    let amount_out = (reserve_out * amount_in_after_fee / (reserve_in + amount_in_after_fee)) as u64;
+
+    Ok(SwapQuote {
+        amount_in,
+        amount_out,
+        fee_amount: fee as u64,
+        price_impact_bps: price_impact_bps(
+            Price::from_ratio(reserve_out, reserve_in)?,
+            amount_in_after_fee as u64,
+            amount_out,
+        )?,
+        post_state: reserves.post_trade(direction, amount_in, amount_out),
+        transfer_fees: TransferFees::default(),
+        observed: Observation::default(),
+    })
+}
+
+/// Quote `swap_base_out`: the pre-fee input is rounded up, then grossed up
+/// for the fee (rounded up again)
+pub fn swap_base_out(
+    amm: &AmmV4State,
+    reserves: &EffectiveReserves,
+    amount_out: u64,
+    direction: SwapDirection,
+) -> Result<SwapQuote> {
+    let (reserve_in, reserve_out) = reserves.oriented(direction);
+    if reserve_in == 0 || amount_out as u128 >= reserve_out {
+        anyhow::bail!("Insufficient liquidity for {} output", amount_out);
+    }
+
+    let amount_in_before_fee = checked_ceil_div(reserve_in * amount_out as u128, reserve_out - amount_out as u128)?;
+    let fee_denominator = amm.swap_fee_denominator as u128;
+    let amount_in = checked_ceil_div(
+        amount_in_before_fee * fee_denominator,
+        fee_denominator - amm.swap_fee_numerator as u128,
+    )?;
+    let amount_in = u64::try_from(amount_in).map_err(|_| anyhow::anyhow!("Required input exceeds u64"))?;
+    let fee = amount_in - amount_in_before_fee as u64;
+
+    Ok(SwapQuote {
+        amount_in,
+        amount_out,
+        fee_amount: fee,
+        price_impact_bps: price_impact_bps(
+            Price::from_ratio(reserve_out, reserve_in)?,
+            amount_in_before_fee as u64,
+            amount_out,
+        )?,
+        post_state: reserves.post_trade(direction, amount_in, amount_out),
+        transfer_fees: TransferFees::default(),
+        observed: Observation::default(),
+    })
+}
+
+/// Effective reserves from pre-fetched accounts
+fn reserves_from_accounts(amm: &AmmV4State, accounts: &AccountMap) -> Result<EffectiveReserves> {
+    let open_orders = if amm.orderbook_enabled() {
+        let account = required_account(accounts, &amm.open_orders)?;
+        Some(OpenOrdersTotals::from_bytes(&account.data).map_err(|err| PriceError::layout(amm.open_orders, err))?)
+    } else {
+        None
+    };
+
+    effective_reserves(
+        amm,
+        vault_amount(accounts, &amm.base_vault)?,
+        vault_amount(accounts, &amm.quote_vault)?,
+        open_orders.as_ref(),
+    )
+}
+
+#[derive(Default)]
 pub struct RaydiumClient {
     // Keeps track of pool states
 }
@@ -69,31 +309,399 @@ impl RaydiumClient {
 
     /// Calculate price from pool reserves (Simulation)
     /// In production, we fetch base_vault and quote_vault balances
-    pub async fn get_pool_price(&self, rpc: &RpcClient, base_vault: &Pubkey, quote_vault: &Pubkey) -> Result<f64> {
+    pub async fn get_pool_price<B: RpcBackend + ?Sized>(
+        &self,
+        rpc: &B,
+        base_vault: &Pubkey,
+        quote_vault: &Pubkey,
+        mints: &MintRegistry,
+    ) -> Result<PoolPrice, PriceError> {
         let accounts = rpc.get_multiple_accounts(&[*base_vault, *quote_vault]).await?;
-        
-        // Helper to parse token account balance
-        let get_balance = |idx: usize| -> Result<u64> {
-            if let Some(acc) = &accounts[idx] {
-                // simple parse logic or use spl_token::state::Account::unpack
-                // For speed, often manual offset read is done 
-                // Amount is at offset 64 in Token Account
-                if acc.data.len() >= 72 {
-                    let amount_bytes: [u8; 8] = acc.data[64..72].try_into()?;
-                    return Ok(u64::from_le_bytes(amount_bytes));
-                }
-            }
-            Ok(0)
+
+        // Helper to parse a vault token account
+        let get_vault = |idx: usize, address: &Pubkey| -> Result<TokenAccount, PriceError> {
+            let account = accounts[idx].as_ref().ok_or(PriceError::AccountMissing(*address))?;
+            TokenAccount::from_account(address, account)
+        };
+        let (base, quote) = (get_vault(0, base_vault)?, get_vault(1, quote_vault)?);
+
+        let raw = reserve_price(base.amount, quote.amount)?;
+        let decimals = mints.resolve(rpc, &[base.mint, quote.mint]).await?;
+        Ok(PoolPrice::new(raw, decimals[0].decimals, decimals[1].decimals)?)
+    }
+
+    /// Fetch and decode a pool's state, verifying the program owner
+    pub async fn get_pool_state<B: RpcBackend + ?Sized>(&self, rpc: &B, pool_address: &Pubkey) -> Result<AmmV4State> {
+        let account = rpc.get_account(pool_address).await?;
+        AmmV4State::from_account(&account)
+    }
+
+    /// Read the AMM state, both vaults and the open-orders account in one
+    /// batched fetch and compute the reserves the swap instruction would use.
+    /// `known` supplies the (immutable) vault and open-orders addresses from
+    /// an earlier decode; the returned state is the freshly fetched one.
+    pub async fn get_effective_reserves<B: RpcBackend + ?Sized>(
+        &self,
+        rpc: &B,
+        pool_address: &Pubkey,
+        known: &AmmV4State,
+    ) -> Result<(AmmV4State, EffectiveReserves), PriceError> {
+        let keys = [*pool_address, known.base_vault, known.quote_vault, known.open_orders];
+        let fetched = rpc.get_multiple_accounts(&keys).await?;
+
+        let pool_account = fetched[0].as_ref().ok_or(PriceError::AccountMissing(*pool_address))?;
+        if pool_account.owner != RAYDIUM_V4_PROGRAM_ID {
+            return Err(PriceError::WrongOwner {
+                account: *pool_address,
+                owner: pool_account.owner,
+            });
+        }
+        let amm = AmmV4State::from_bytes(&pool_account.data).map_err(|err| PriceError::layout(*pool_address, err))?;
+
+        let accounts: AccountMap = keys
+            .into_iter()
+            .zip(fetched)
+            .filter_map(|(key, acc)| acc.map(|acc| (key, acc)))
+            .collect();
+        let reserves = reserves_from_accounts(&amm, &accounts)?;
+        Ok((amm, reserves))
+    }
+}
+
+/// Price = Quote / Base in raw units
+fn reserve_price(base_reserve: u64, quote_reserve: u64) -> Result<Price, PriceError> {
+    if base_reserve == 0 || quote_reserve == 0 {
+        return Err(PriceError::EmptyPool);
+    }
+    Ok(Price::from_ratio(quote_reserve as u128, base_reserve as u128)?)
+}
+
+impl PoolQuoter for RaydiumClient {
+    fn dex(&self) -> DexType {
+        DexType::RaydiumV4
+    }
+
+    fn program_id(&self) -> Pubkey {
+        RAYDIUM_V4_PROGRAM_ID
+    }
+
+    fn decode(&self, address: &Pubkey, data: &[u8]) -> Result<DecodedPool, PriceError> {
+        let amm = AmmV4State::from_bytes(data).map_err(|err| PriceError::layout(*address, err))?;
+        Ok(DecodedPool {
+            address: *address,
+            dex: DexType::RaydiumV4,
+            mint_a: amm.base_mint,
+            mint_b: amm.quote_mint,
+            state: PoolState::RaydiumV4(amm),
+            observed: Observation::default(),
+        })
+    }
+
+    fn required_accounts(&self, pool: &DecodedPool) -> Vec<Pubkey> {
+        match &pool.state {
+            PoolState::RaydiumV4(amm) => vec![amm.base_vault, amm.quote_vault, amm.open_orders],
+            _ => Vec::new(),
+        }
+    }
+
+    fn mid_price(&self, pool: &DecodedPool, accounts: &AccountMap) -> Result<Price, PriceError> {
+        let PoolState::RaydiumV4(amm) = &pool.state else {
+            return Err(PriceError::layout(pool.address, "not a Raydium V4 pool"));
+        };
+        let reserves = reserves_from_accounts(amm, accounts)?;
+        reserve_price(reserves.base, reserves.quote)
+    }
+
+    fn quote_exact_in(
+        &self,
+        pool: &DecodedPool,
+        accounts: &AccountMap,
+        amount_in: u64,
+        direction: SwapDirection,
+    ) -> Result<SwapQuote, PriceError> {
+        let PoolState::RaydiumV4(amm) = &pool.state else {
+            return Err(PriceError::layout(pool.address, "not a Raydium V4 pool"));
+        };
+        Ok(swap_base_in(amm, &reserves_from_accounts(amm, accounts)?, amount_in, direction)?)
+    }
+
+    fn quote_exact_out(
+        &self,
+        pool: &DecodedPool,
+        accounts: &AccountMap,
+        amount_out: u64,
+        direction: SwapDirection,
+    ) -> Result<SwapQuote, PriceError> {
+        let PoolState::RaydiumV4(amm) = &pool.state else {
+            return Err(PriceError::layout(pool.address, "not a Raydium V4 pool"));
         };
+        Ok(swap_base_out(amm, &reserves_from_accounts(amm, accounts)?, amount_out, direction)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{put_pubkey, put_u64, token_account, token_account_of};
+    use crate::token::SPL_TOKEN_PROGRAM_ID;
+    use crate::{MintExtensions, MintInfo, QuoterDispatcher};
+    use rpc_manager::InMemoryBackend;
+
+    const BASE_VAULT_OFFSET: usize = 336;
+    const QUOTE_VAULT_OFFSET: usize = 368;
+    const BASE_MINT_OFFSET: usize = 400;
+    const QUOTE_MINT_OFFSET: usize = 432;
+    const OPEN_ORDERS_OFFSET: usize = 496;
+    const MARKET_OFFSET: usize = 528;
+    const TARGET_ORDERS_OFFSET: usize = 592;
+    const AMM_OWNER_OFFSET: usize = 688;
+    const LP_AMOUNT_OFFSET: usize = 720;
+
+    struct Fixture {
+        data: Vec<u8>,
+        base_vault: Pubkey,
+        quote_vault: Pubkey,
+        base_mint: Pubkey,
+        quote_mint: Pubkey,
+        open_orders: Pubkey,
+        market: Pubkey,
+        target_orders: Pubkey,
+        amm_owner: Pubkey,
+    }
 
-        let base_reserve = get_balance(0)?;
-        let quote_reserve = get_balance(1)?;
+    /// AMM V4 account image laid out at the on-chain field offsets
+    fn amm_fixture() -> Fixture {
+        let fixture = Fixture {
+            data: vec![0u8; AMM_V4_STATE_SIZE],
+            base_vault: Pubkey::new_unique(),
+            quote_vault: Pubkey::new_unique(),
+            base_mint: Pubkey::new_unique(),
+            quote_mint: Pubkey::new_unique(),
+            open_orders: Pubkey::new_unique(),
+            market: Pubkey::new_unique(),
+            target_orders: Pubkey::new_unique(),
+            amm_owner: Pubkey::new_unique(),
+        };
+        let mut data = fixture.data.clone();
+        put_u64(&mut data, 0, 6); // status: SwapOnly
+        put_u64(&mut data, 32, 9); // base_decimal
+        put_u64(&mut data, 40, 6); // quote_decimal
+        put_u64(&mut data, 176, 25); // swap_fee_numerator
+        put_u64(&mut data, 184, 10_000); // swap_fee_denominator
+        put_pubkey(&mut data, BASE_VAULT_OFFSET, &fixture.base_vault);
+        put_pubkey(&mut data, QUOTE_VAULT_OFFSET, &fixture.quote_vault);
+        put_pubkey(&mut data, BASE_MINT_OFFSET, &fixture.base_mint);
+        put_pubkey(&mut data, QUOTE_MINT_OFFSET, &fixture.quote_mint);
+        put_pubkey(&mut data, OPEN_ORDERS_OFFSET, &fixture.open_orders);
+        put_pubkey(&mut data, MARKET_OFFSET, &fixture.market);
+        put_pubkey(&mut data, TARGET_ORDERS_OFFSET, &fixture.target_orders);
+        put_pubkey(&mut data, AMM_OWNER_OFFSET, &fixture.amm_owner);
+        put_u64(&mut data, LP_AMOUNT_OFFSET, 1_234_567);
+        Fixture { data, ..fixture }
+    }
 
-        if base_reserve == 0 {
-            return Ok(0.0);
+    fn spl_mint(decimals: u8) -> MintInfo {
+        MintInfo {
+            decimals,
+            token_program: SPL_TOKEN_PROGRAM_ID,
+            extensions: MintExtensions::default(),
         }
+    }
+
+    #[test]
+    fn test_decode_full_layout() {
+        let fixture = amm_fixture();
+        let amm = AmmV4State::from_bytes(&fixture.data).unwrap();
+
+        assert_eq!(amm.status, 6);
+        assert_eq!(amm.base_decimal, 9);
+        assert_eq!(amm.quote_decimal, 6);
+        assert_eq!(amm.swap_fee_numerator, 25);
+        assert_eq!(amm.swap_fee_denominator, 10_000);
+        assert_eq!(amm.base_vault, fixture.base_vault);
+        assert_eq!(amm.quote_vault, fixture.quote_vault);
+        assert_eq!(amm.base_mint, fixture.base_mint);
+        assert_eq!(amm.quote_mint, fixture.quote_mint);
+        assert_eq!(amm.open_orders, fixture.open_orders);
+        assert_eq!(amm.market, fixture.market);
+        assert_eq!(amm.target_orders, fixture.target_orders);
+        assert_eq!(amm.amm_owner, fixture.amm_owner);
+        assert_eq!(amm.lp_amount, 1_234_567);
+    }
+
+    #[test]
+    fn test_rejects_wrong_owner_and_size() {
+        let fixture = amm_fixture();
+        assert!(AmmV4State::from_bytes(&fixture.data[..AMM_V4_STATE_SIZE - 1]).is_err());
+
+        let mut account = Account {
+            data: fixture.data,
+            owner: Pubkey::new_unique(),
+            ..Account::default()
+        };
+        assert!(AmmV4State::from_account(&account).is_err());
+
+        account.owner = RAYDIUM_V4_PROGRAM_ID;
+        assert!(AmmV4State::from_account(&account).is_ok());
+    }
+
+    fn open_orders_account(base_total: u64, quote_total: u64) -> Account {
+        let mut data = vec![0u8; OPEN_ORDERS_SIZE];
+        data[..5].copy_from_slice(b"serum");
+        put_u64(&mut data, 85, base_total);
+        put_u64(&mut data, 101, quote_total);
+        Account {
+            data,
+            ..Account::default()
+        }
+    }
+
+    #[test]
+    fn test_effective_reserves() {
+        let mut amm = AmmV4State::from_bytes(&amm_fixture().data).unwrap();
+        amm.base_need_take_pnl = 100;
+        amm.quote_need_take_pnl = 5_000;
+        let open_orders = OpenOrdersTotals::from_bytes(&open_orders_account(400, 60_000).data).unwrap();
+
+        // SwapOnly pools ignore the orderbook
+        let reserves = effective_reserves(&amm, 2_000, 300_000, Some(&open_orders)).unwrap();
+        assert_eq!(reserves, EffectiveReserves { base: 1_900, quote: 295_000 });
+
+        // Initialized pools include funds parked on OpenBook
+        amm.status = 1;
+        let reserves = effective_reserves(&amm, 2_000, 300_000, Some(&open_orders)).unwrap();
+        assert_eq!(reserves, EffectiveReserves { base: 2_300, quote: 355_000 });
+        assert!(effective_reserves(&amm, 2_000, 300_000, None).is_err());
+
+        amm.base_need_take_pnl = 10_000;
+        assert!(effective_reserves(&amm, 2_000, 300_000, Some(&open_orders)).is_err());
+    }
+
+    #[test]
+    fn test_swap_quotes_match_program_rounding() {
+        let amm = AmmV4State::from_bytes(&amm_fixture().data).unwrap();
+        let reserves = EffectiveReserves { base: 1_000_000_000, quote: 150_000_000_000 };
+
+        // 0.25% of 1_000_001 = 2500.0025, rounded up
+        let quote = swap_base_in(&amm, &reserves, 1_000_001, SwapDirection::AToB).unwrap();
+        assert_eq!(quote.fee_amount, 2_501);
+        let after_fee = 1_000_001u128 - 2_501;
+        assert_eq!(quote.amount_out as u128, 150_000_000_000 * after_fee / (1_000_000_000 + after_fee));
+        assert_eq!(
+            quote.post_state,
+            PostTradeState::Reserves { reserve_a: 1_001_000_001, reserve_b: 150_000_000_000 - quote.amount_out }
+        );
+        assert!(quote.price_impact_bps > 9.0 && quote.price_impact_bps < 11.0);
+
+        // Tiny fees below one unit round to nearest rather than up
+        assert_eq!(swap_base_in(&amm, &reserves, 100, SwapDirection::AToB).unwrap().fee_amount, 0);
+        assert_eq!(swap_base_in(&amm, &reserves, 200, SwapDirection::AToB).unwrap().fee_amount, 1);
+
+        // Exact out round-trips: paying the quoted input yields at least the requested output
+        let exact_out = swap_base_out(&amm, &reserves, 1_000_000, SwapDirection::BToA).unwrap();
+        let check = swap_base_in(&amm, &reserves, exact_out.amount_in, SwapDirection::BToA).unwrap();
+        assert!(check.amount_out >= 1_000_000);
+        assert!(swap_base_out(&amm, &reserves, 1_000_000_000, SwapDirection::BToA).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_effective_reserves_single_fetch() {
+        let mut fixture = amm_fixture();
+        put_u64(&mut fixture.data, 0, 1); // status: Initialized
+        put_u64(&mut fixture.data, 192, 100); // base_need_take_pnl
+        let pool_address = Pubkey::new_unique();
+        let backend = InMemoryBackend::new();
+        backend.set_account(
+            pool_address,
+            Account {
+                data: fixture.data.clone(),
+                owner: RAYDIUM_V4_PROGRAM_ID,
+                ..Account::default()
+            },
+        );
+        backend.set_account(fixture.base_vault, token_account(2_000));
+        backend.set_account(fixture.quote_vault, token_account(300_000));
+        backend.set_account(fixture.open_orders, open_orders_account(100, 15_000));
+
+        let known = AmmV4State::from_bytes(&fixture.data).unwrap();
+        let (amm, reserves) = RaydiumClient::new()
+            .get_effective_reserves(&backend, &pool_address, &known)
+            .await
+            .unwrap();
+        assert_eq!(amm.base_need_take_pnl, 100);
+        assert_eq!(reserves, EffectiveReserves { base: 2_000, quote: 315_000 });
+    }
+
+    #[tokio::test]
+    async fn test_dispatcher_prices_pool() {
+        let fixture = amm_fixture();
+        let pool_address = Pubkey::new_unique();
+        let backend = InMemoryBackend::new();
+        backend.set_account(
+            pool_address,
+            Account {
+                data: fixture.data.clone(),
+                owner: RAYDIUM_V4_PROGRAM_ID,
+                ..Account::default()
+            },
+        );
+        backend.set_account(fixture.base_vault, token_account(2_000));
+        backend.set_account(fixture.quote_vault, token_account(300_000));
+
+        let dispatcher = QuoterDispatcher::new();
+        let price = dispatcher.fetch_mid_price(&backend, &pool_address).await.unwrap();
+        assert_eq!(price, Price::from_integer(150));
+
+        // SOL (9 decimals) priced in USDC (6 decimals)
+        for (mint, decimals) in [(fixture.base_mint, 9u8), (fixture.quote_mint, 6u8)] {
+            dispatcher.mints().insert(mint, spl_mint(decimals));
+        }
+        let price = dispatcher.fetch_price(&backend, &pool_address).await.unwrap();
+        assert_eq!(price.raw, Price::from_integer(150));
+        assert_eq!(price.ui, Price::from_integer(150_000));
+    }
+
+    #[tokio::test]
+    async fn test_vault_price_is_decimal_adjusted() {
+        let backend = InMemoryBackend::new();
+        let (sol, usdc) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (base_vault, quote_vault) = (Pubkey::new_unique(), Pubkey::new_unique());
+        backend.set_account(base_vault, token_account_of(&sol, 10_000_000_000));
+        backend.set_account(quote_vault, token_account_of(&usdc, 1_500_000_000));
+
+        let mints = MintRegistry::new();
+        mints.insert(sol, spl_mint(9));
+        mints.insert(usdc, spl_mint(6));
+
+        let price = RaydiumClient::new()
+            .get_pool_price(&backend, &base_vault, &quote_vault, &mints)
+            .await
+            .unwrap();
+        assert_eq!(price.raw, Price::from_ratio(15, 100).unwrap());
+        assert!((price.ui.to_f64() - 150.0).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_vault_price_errors_are_typed() {
+        let backend = InMemoryBackend::new();
+        let (sol, usdc) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (base_vault, quote_vault) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let client = RaydiumClient::new();
+        let mints = MintRegistry::new();
+
+        // A missing vault is an error, not a zero balance
+        backend.set_account(base_vault, token_account_of(&sol, 10_000_000_000));
+        let err = client.get_pool_price(&backend, &base_vault, &quote_vault, &mints).await.unwrap_err();
+        assert!(matches!(err, PriceError::AccountMissing(vault) if vault == quote_vault));
+
+        // Empty reserves have no price
+        backend.set_account(quote_vault, token_account_of(&usdc, 0));
+        let err = client.get_pool_price(&backend, &base_vault, &quote_vault, &mints).await.unwrap_err();
+        assert!(matches!(err, PriceError::EmptyPool));
 
-        // Price = Quote / Base (simplified, ignoring decimals for now)
-        Ok(quote_reserve as f64 / base_reserve as f64)
+        // Mints that cannot be fetched leave the decimals unknown
+        backend.set_account(quote_vault, token_account_of(&usdc, 1_500_000_000));
+        let err = client.get_pool_price(&backend, &base_vault, &quote_vault, &mints).await.unwrap_err();
+        assert!(matches!(err, PriceError::DecimalsUnknown(_)));
     }
 }
@@ -0,0 +1,458 @@
+use anyhow::Result;
+use borsh::BorshDeserialize;
+use rpc_manager::RpcBackend;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::clmm_math::{self, ClmmState, TickMath, TickWindow};
+use crate::error::PriceError;
+use crate::freshness::Observation;
+use crate::price::Price;
+use crate::quoter::{
+    required_account, AccountMap, DecodedPool, DexType, PoolPrice, PoolQuoter, PoolState, SwapDirection, SwapQuote,
+};
+
+// Raydium Concentrated Liquidity (CLMM) Program ID
+pub const RAYDIUM_CLMM_PROGRAM_ID: Pubkey = solana_sdk::pubkey!("CAMMCzo5YL8w4VFF8KVHrK22GGUsp5VTaW7grrKgrWqK");
+
+pub(crate) const POOL_STATE_DISCRIMINATOR: [u8; 8] = [247, 237, 227, 245, 215, 195, 222, 70];
+const AMM_CONFIG_DISCRIMINATOR: [u8; 8] = [218, 244, 33, 104, 203, 203, 43, 111];
+const TICK_ARRAY_DISCRIMINATOR: [u8; 8] = [192, 155, 85, 205, 49, 249, 129, 42];
+
+/// Size of a CLMM pool account, discriminator included
+pub const CLMM_POOL_STATE_SIZE: usize = 1544;
+
+/// Ticks per Raydium tick array
+pub const TICK_ARRAY_SIZE: i32 = 60;
+
+/// Tick arrays fetched on each side of the current one
+const TICK_ARRAYS_EACH_SIDE: i32 = 2;
+
+/// Status bit that disables swaps
+const STATUS_SWAP_DISABLED: u8 = 1 << 4;
+
+/// Leading fields of the CLMM `PoolState` (after the discriminator).
+/// Reward, bitmap and statistics fields that follow are not needed for quoting.
+#[derive(BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ClmmPoolState {
+    pub bump: [u8; 1],
+    pub amm_config: Pubkey,
+    pub owner: Pubkey,
+    pub token_mint_0: Pubkey,
+    pub token_mint_1: Pubkey,
+    pub token_vault_0: Pubkey,
+    pub token_vault_1: Pubkey,
+    pub observation_key: Pubkey,
+    pub mint_decimals_0: u8,
+    pub mint_decimals_1: u8,
+    pub tick_spacing: u16,
+    /// Liquidity active at the current tick
+    pub liquidity: u128,
+    pub sqrt_price_x64: u128,
+    pub tick_current: i32,
+    pub padding3: u16,
+    pub padding4: u16,
+    pub fee_growth_global_0_x64: u128,
+    pub fee_growth_global_1_x64: u128,
+    pub protocol_fees_token_0: u64,
+    pub protocol_fees_token_1: u64,
+    pub swap_in_amount_token_0: u128,
+    pub swap_out_amount_token_1: u128,
+    pub swap_in_amount_token_1: u128,
+    pub swap_out_amount_token_0: u128,
+    pub status: u8,
+}
+
+impl ClmmPoolState {
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        if data.len() != CLMM_POOL_STATE_SIZE || data[..8] != POOL_STATE_DISCRIMINATOR {
+            anyhow::bail!("Invalid CLMM pool account ({} bytes)", data.len());
+        }
+        Ok(Self::deserialize(&mut &data[8..])?)
+    }
+
+    pub fn swap_enabled(&self) -> bool {
+        self.status & STATUS_SWAP_DISABLED == 0
+    }
+}
+
+/// CLMM fee tier shared by every pool created under it
+#[derive(BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ClmmAmmConfig {
+    pub bump: u8,
+    pub index: u16,
+    pub owner: Pubkey,
+    pub protocol_fee_rate: u32,
+    /// Fee rate in hundredths of a basis point
+    pub trade_fee_rate: u32,
+    pub tick_spacing: u16,
+    pub fund_fee_rate: u32,
+    pub padding_u32: u32,
+    pub fund_owner: Pubkey,
+    pub padding: [u64; 3],
+}
+
+impl ClmmAmmConfig {
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        if data.len() < 8 || data[..8] != AMM_CONFIG_DISCRIMINATOR {
+            anyhow::bail!("Not a CLMM AmmConfig account");
+        }
+        Ok(Self::deserialize(&mut &data[8..])?)
+    }
+}
+
+#[derive(BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TickState {
+    pub tick: i32,
+    pub liquidity_net: i128,
+    pub liquidity_gross: u128,
+    pub fee_growth_outside_0_x64: u128,
+    pub fee_growth_outside_1_x64: u128,
+    pub reward_growths_outside_x64: [u128; 3],
+    pub padding: [u32; 13],
+}
+
+/// CLMM `TickArrayState` (after the discriminator)
+#[derive(BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TickArrayState {
+    pub pool_id: Pubkey,
+    pub start_tick_index: i32,
+    pub ticks: [TickState; TICK_ARRAY_SIZE as usize],
+    pub initialized_tick_count: u8,
+}
+
+impl TickArrayState {
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        if data.len() < 8 || data[..8] != TICK_ARRAY_DISCRIMINATOR {
+            anyhow::bail!("Not a CLMM tick array account");
+        }
+        Ok(Self::deserialize(&mut &data[8..])?)
+    }
+}
+
+/// Start index of the tick array containing `tick_index`
+pub fn tick_array_start_index(tick_index: i32, tick_spacing: u16) -> i32 {
+    let ticks_in_array = TICK_ARRAY_SIZE * tick_spacing as i32;
+    tick_index.div_euclid(ticks_in_array) * ticks_in_array
+}
+
+/// Tick array PDA for a CLMM pool (the start index is encoded big-endian)
+pub fn tick_array_address(pool: &Pubkey, start_tick_index: i32) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"tick_array", pool.as_ref(), &start_tick_index.to_be_bytes()],
+        &RAYDIUM_CLMM_PROGRAM_ID,
+    )
+    .0
+}
+
+/// Start indices of the tick arrays around the pool's current tick, lowest first
+fn tick_array_window_starts(pool: &ClmmPoolState) -> Vec<i32> {
+    let ticks_in_array = TICK_ARRAY_SIZE * pool.tick_spacing as i32;
+    let current = tick_array_start_index(pool.tick_current, pool.tick_spacing);
+    (-TICK_ARRAYS_EACH_SIDE..=TICK_ARRAYS_EACH_SIDE)
+        .map(|offset| current + offset * ticks_in_array)
+        .collect()
+}
+
+/// Tick window around the pool's current tick built from fetched tick
+/// arrays. Arrays that were never initialized on chain are treated as empty.
+pub fn tick_window_from_accounts(
+    pool_address: &Pubkey,
+    pool: &ClmmPoolState,
+    accounts: &AccountMap,
+) -> Result<TickWindow, PriceError> {
+    let starts = tick_array_window_starts(pool);
+    let tick_arrays = starts
+        .iter()
+        .map(|start| tick_array_address(pool_address, *start))
+        .filter_map(|address| accounts.get(&address).map(|account| (address, account)))
+        .map(|(address, account)| {
+            let array = TickArrayState::from_bytes(&account.data).map_err(|err| PriceError::layout(address, err))?;
+            if array.pool_id != *pool_address {
+                return Err(PriceError::layout(address, format!("tick array belongs to pool {}", array.pool_id)));
+            }
+            Ok(array)
+        })
+        .collect::<Result<Vec<_>, PriceError>>()?;
+
+    let initialized = tick_arrays.iter().flat_map(|array| {
+        array
+            .ticks
+            .iter()
+            .filter(|tick| tick.liquidity_gross != 0)
+            .map(|tick| (tick.tick, tick.liquidity_net))
+    });
+    let upper = starts[starts.len() - 1] + TICK_ARRAY_SIZE * pool.tick_spacing as i32;
+    Ok(TickWindow::new(starts[0], upper, initialized))
+}
+
+/// Current pool price; a pool with no in-range liquidity has no tradable price
+fn pool_price(pool: &ClmmPoolState) -> Result<Price, PriceError> {
+    if pool.sqrt_price_x64 == 0 || pool.liquidity == 0 {
+        return Err(PriceError::EmptyPool);
+    }
+    Ok(Price::from_sqrt_price_x64(pool.sqrt_price_x64)?)
+}
+
+/// Simulate a CLMM swap across the initialized ticks in `ticks`
+pub fn simulate_swap(
+    pool: &ClmmPoolState,
+    config: &ClmmAmmConfig,
+    ticks: &TickWindow,
+    amount: u64,
+    amount_specified_is_input: bool,
+    direction: SwapDirection,
+) -> Result<SwapQuote> {
+    let state = ClmmState {
+        sqrt_price: pool.sqrt_price_x64,
+        tick_current_index: pool.tick_current,
+        liquidity: pool.liquidity,
+        fee_rate: config.trade_fee_rate,
+    };
+    clmm_math::simulate_swap(&state, ticks, TickMath::Raydium, amount, amount_specified_is_input, direction)
+}
+
+#[derive(Default)]
+pub struct RaydiumClmmClient {}
+
+impl RaydiumClmmClient {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Pool price using the mint decimals stored in the pool itself
+    pub async fn get_pool_price<B: RpcBackend + ?Sized>(
+        &self,
+        rpc: &B,
+        pool_address: &Pubkey,
+    ) -> Result<PoolPrice, PriceError> {
+        let account = rpc.get_account(pool_address).await?;
+        let pool = ClmmPoolState::from_bytes(&account.data).map_err(|err| PriceError::layout(*pool_address, err))?;
+        Ok(PoolPrice::new(pool_price(&pool)?, pool.mint_decimals_0, pool.mint_decimals_1)?)
+    }
+
+    fn quote(
+        &self,
+        pool: &DecodedPool,
+        accounts: &AccountMap,
+        amount: u64,
+        amount_specified_is_input: bool,
+        direction: SwapDirection,
+    ) -> Result<SwapQuote, PriceError> {
+        let PoolState::RaydiumClmm(clmm) = &pool.state else {
+            return Err(PriceError::layout(pool.address, "not a Raydium CLMM pool"));
+        };
+        if !clmm.swap_enabled() {
+            return Err(anyhow::anyhow!("Swaps are disabled on pool {}", pool.address).into());
+        }
+        let config = required_account(accounts, &clmm.amm_config)?;
+        let config = ClmmAmmConfig::from_bytes(&config.data).map_err(|err| PriceError::layout(clmm.amm_config, err))?;
+        let ticks = tick_window_from_accounts(&pool.address, clmm, accounts)?;
+        Ok(simulate_swap(clmm, &config, &ticks, amount, amount_specified_is_input, direction)?)
+    }
+}
+
+impl PoolQuoter for RaydiumClmmClient {
+    fn dex(&self) -> DexType {
+        DexType::RaydiumCLMM
+    }
+
+    fn program_id(&self) -> Pubkey {
+        RAYDIUM_CLMM_PROGRAM_ID
+    }
+
+    fn decode(&self, address: &Pubkey, data: &[u8]) -> Result<DecodedPool, PriceError> {
+        let pool = ClmmPoolState::from_bytes(data).map_err(|err| PriceError::layout(*address, err))?;
+        Ok(DecodedPool {
+            address: *address,
+            dex: DexType::RaydiumCLMM,
+            mint_a: pool.token_mint_0,
+            mint_b: pool.token_mint_1,
+            state: PoolState::RaydiumClmm(pool),
+            observed: Observation::default(),
+        })
+    }
+
+    fn required_accounts(&self, _pool: &DecodedPool) -> Vec<Pubkey> {
+        // Mid price only needs the pool's own sqrt_price
+        Vec::new()
+    }
+
+    fn quote_accounts(&self, pool: &DecodedPool) -> Vec<Pubkey> {
+        let PoolState::RaydiumClmm(clmm) = &pool.state else {
+            return Vec::new();
+        };
+        std::iter::once(clmm.amm_config)
+            .chain(
+                tick_array_window_starts(clmm)
+                    .into_iter()
+                    .map(|start| tick_array_address(&pool.address, start)),
+            )
+            .collect()
+    }
+
+    fn mid_price(&self, pool: &DecodedPool, _accounts: &AccountMap) -> Result<Price, PriceError> {
+        let PoolState::RaydiumClmm(clmm) = &pool.state else {
+            return Err(PriceError::layout(pool.address, "not a Raydium CLMM pool"));
+        };
+        pool_price(clmm)
+    }
+
+    fn quote_exact_in(
+        &self,
+        pool: &DecodedPool,
+        accounts: &AccountMap,
+        amount_in: u64,
+        direction: SwapDirection,
+    ) -> Result<SwapQuote, PriceError> {
+        self.quote(pool, accounts, amount_in, true, direction)
+    }
+
+    fn quote_exact_out(
+        &self,
+        pool: &DecodedPool,
+        accounts: &AccountMap,
+        amount_out: u64,
+        direction: SwapDirection,
+    ) -> Result<SwapQuote, PriceError> {
+        self.quote(pool, accounts, amount_out, false, direction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quoter::PostTradeState;
+    use solana_sdk::account::Account;
+
+    // Offsets in the pool account, discriminator included
+    const OFFSET_AMM_CONFIG: usize = 9;
+    const OFFSET_MINT_DECIMALS_0: usize = 233;
+    const OFFSET_TICK_SPACING: usize = 235;
+    const OFFSET_LIQUIDITY: usize = 237;
+    const OFFSET_SQRT_PRICE: usize = 253;
+    const OFFSET_TICK_CURRENT: usize = 269;
+    const OFFSET_STATUS: usize = 389;
+
+    const TICK_STATE_SIZE: usize = 168;
+    const TICK_ARRAY_STATE_SIZE: usize = 10240;
+    const AMM_CONFIG_SIZE: usize = 117;
+    const Q64: u128 = 1 << 64;
+
+    fn pool_data(config: &Pubkey, sqrt_price: u128, liquidity: u128, status: u8) -> Vec<u8> {
+        let mut data = vec![0u8; CLMM_POOL_STATE_SIZE];
+        data[..8].copy_from_slice(&POOL_STATE_DISCRIMINATOR);
+        data[OFFSET_AMM_CONFIG..OFFSET_AMM_CONFIG + 32].copy_from_slice(config.as_ref());
+        // SOL (9) / USDC (6)
+        data[OFFSET_MINT_DECIMALS_0] = 9;
+        data[OFFSET_MINT_DECIMALS_0 + 1] = 6;
+        data[OFFSET_TICK_SPACING..OFFSET_TICK_SPACING + 2].copy_from_slice(&10u16.to_le_bytes());
+        data[OFFSET_LIQUIDITY..OFFSET_LIQUIDITY + 16].copy_from_slice(&liquidity.to_le_bytes());
+        data[OFFSET_SQRT_PRICE..OFFSET_SQRT_PRICE + 16].copy_from_slice(&sqrt_price.to_le_bytes());
+        let tick = TickMath::Raydium.tick_at_sqrt_price(sqrt_price).unwrap();
+        data[OFFSET_TICK_CURRENT..OFFSET_TICK_CURRENT + 4].copy_from_slice(&tick.to_le_bytes());
+        data[OFFSET_STATUS] = status;
+        data
+    }
+
+    fn config_account(trade_fee_rate: u32) -> Account {
+        let mut data = vec![0u8; AMM_CONFIG_SIZE];
+        data[..8].copy_from_slice(&AMM_CONFIG_DISCRIMINATOR);
+        data[47..51].copy_from_slice(&trade_fee_rate.to_le_bytes());
+        Account {
+            data,
+            owner: RAYDIUM_CLMM_PROGRAM_ID,
+            ..Account::default()
+        }
+    }
+
+    /// Tick array with the given (tick, liquidity_net) ticks initialized
+    fn tick_array_account(pool: &Pubkey, start_tick_index: i32, ticks: &[(i32, i128)]) -> Account {
+        let mut data = vec![0u8; TICK_ARRAY_STATE_SIZE];
+        data[..8].copy_from_slice(&TICK_ARRAY_DISCRIMINATOR);
+        data[8..40].copy_from_slice(pool.as_ref());
+        data[40..44].copy_from_slice(&start_tick_index.to_le_bytes());
+        for (tick, liquidity_net) in ticks {
+            let at = 44 + ((tick - start_tick_index) / 10) as usize * TICK_STATE_SIZE;
+            data[at..at + 4].copy_from_slice(&tick.to_le_bytes());
+            data[at + 4..at + 20].copy_from_slice(&liquidity_net.to_le_bytes());
+            data[at + 20..at + 36].copy_from_slice(&liquidity_net.unsigned_abs().to_le_bytes());
+        }
+        Account {
+            data,
+            owner: RAYDIUM_CLMM_PROGRAM_ID,
+            ..Account::default()
+        }
+    }
+
+    #[test]
+    fn test_tick_math_matches_program_bounds() {
+        assert_eq!(TickMath::Raydium.sqrt_price_at_tick(clmm_math::MIN_TICK_INDEX).unwrap(), clmm_math::MIN_SQRT_PRICE_X64);
+        assert_eq!(
+            TickMath::Raydium.sqrt_price_at_tick(clmm_math::MAX_TICK_INDEX).unwrap(),
+            clmm_math::RAYDIUM_MAX_SQRT_PRICE_X64
+        );
+        for tick in [-20_000, -1, 0, 1, 15_000] {
+            let sqrt_price = TickMath::Raydium.sqrt_price_at_tick(tick).unwrap();
+            assert_eq!(TickMath::Raydium.tick_at_sqrt_price(sqrt_price).unwrap(), tick);
+        }
+        assert_eq!(tick_array_start_index(-1, 10), -600);
+        assert_ne!(tick_array_address(&Pubkey::new_unique(), -600), Pubkey::default());
+    }
+
+    #[test]
+    fn test_decode_and_price_with_decimals() {
+        let config = Pubkey::new_unique();
+        // 0.15 raw USDC per lamport = 150 USDC/SOL; sqrt(0.15) * 2^64
+        let sqrt_price = (0.15f64.sqrt() * Q64 as f64) as u128;
+        let data = pool_data(&config, sqrt_price, 1_000_000_000, 0);
+        let pool = RaydiumClmmClient::new().decode(&Pubkey::new_unique(), &data).unwrap();
+
+        let PoolState::RaydiumClmm(clmm) = &pool.state else {
+            panic!("expected CLMM state");
+        };
+        assert_eq!(clmm.amm_config, config);
+        let raw = Price::from_sqrt_price_x64(clmm.sqrt_price_x64).unwrap();
+        let price = PoolPrice::new(raw, clmm.mint_decimals_0, clmm.mint_decimals_1).unwrap();
+        assert!((price.ui.to_f64() - 150.0).abs() < 1e-6);
+        assert_eq!(RaydiumClmmClient::new().quote_accounts(&pool).len(), 6);
+        assert!(ClmmPoolState::from_bytes(&data[..1000]).is_err());
+    }
+
+    #[test]
+    fn test_swap_crosses_ticks() {
+        let address = Pubkey::new_unique();
+        let config = Pubkey::new_unique();
+        let client = RaydiumClmmClient::new();
+        // Positions [-10, 10) with L = 1e9 and [-100, 100) with L = 4e9, 0.25% fee
+        let data = pool_data(&config, Q64, 5_000_000_000, 0);
+        let pool = client.decode(&address, &data).unwrap();
+
+        let mut accounts = AccountMap::new();
+        accounts.insert(config, config_account(2_500));
+        accounts.insert(
+            tick_array_address(&address, -600),
+            tick_array_account(&address, -600, &[(-100, 4_000_000_000), (-10, 1_000_000_000)]),
+        );
+        accounts.insert(
+            tick_array_address(&address, 0),
+            tick_array_account(&address, 0, &[(10, -1_000_000_000), (100, -4_000_000_000)]),
+        );
+
+        let quote = client.quote_exact_in(&pool, &accounts, 10_000_000, SwapDirection::BToA).unwrap();
+        let PostTradeState::Concentrated { tick_current_index, liquidity, .. } = quote.post_state else {
+            panic!("expected concentrated post-trade state");
+        };
+        assert!((10..100).contains(&tick_current_index));
+        assert_eq!(liquidity, 4_000_000_000);
+        // 0.25% of the input, rounded up once per crossed range
+        assert_eq!(quote.fee_amount, 25_001);
+
+        let exact_out = client.quote_exact_out(&pool, &accounts, quote.amount_out, SwapDirection::BToA).unwrap();
+        assert_eq!(exact_out.amount_out, quote.amount_out);
+        assert!(exact_out.amount_in <= quote.amount_in);
+
+        // Swaps are refused when the pool disables them
+        let disabled = client.decode(&address, &pool_data(&config, Q64, 5_000_000_000, STATUS_SWAP_DISABLED)).unwrap();
+        assert!(client.quote_exact_in(&disabled, &accounts, 1_000, SwapDirection::AToB).is_err());
+    }
+}
@@ -0,0 +1,550 @@
+use anyhow::Result;
+use borsh::BorshDeserialize;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::error::PriceError;
+use crate::freshness::Observation;
+use crate::price::Price;
+use crate::quoter::{
+    price_impact_bps, required_account, unix_timestamp, AccountMap, DecodedPool, DexType, PoolQuoter, PoolState,
+    PostTradeState, SwapDirection, SwapQuote, TransferFees,
+};
+use crate::token::vault_amount;
+
+// Raydium CP-Swap (CPMM) Program ID
+pub const RAYDIUM_CPMM_PROGRAM_ID: Pubkey = solana_sdk::pubkey!("CPMMoo8L3F4NbTegBCKVNunggL7H1ZpdTHKxQB5qKP1C");
+
+pub(crate) const POOL_STATE_DISCRIMINATOR: [u8; 8] = [247, 237, 227, 245, 215, 195, 222, 70];
+const AMM_CONFIG_DISCRIMINATOR: [u8; 8] = [218, 244, 33, 104, 203, 203, 43, 111];
+
+/// Size of a CPMM pool account, discriminator included
+pub const CPMM_POOL_STATE_SIZE: usize = 637;
+
+/// Fee rates are expressed per million
+const FEE_RATE_DENOMINATOR: u128 = 1_000_000;
+
+/// Status bit that disables swaps
+const STATUS_SWAP_DISABLED: u8 = 1 << 2;
+
+/// CPMM `PoolState` (after the discriminator)
+#[derive(BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CpmmPoolState {
+    pub amm_config: Pubkey,
+    pub pool_creator: Pubkey,
+    pub token_0_vault: Pubkey,
+    pub token_1_vault: Pubkey,
+    pub lp_mint: Pubkey,
+    pub token_0_mint: Pubkey,
+    pub token_1_mint: Pubkey,
+    /// SPL Token or Token-2022, per side
+    pub token_0_program: Pubkey,
+    pub token_1_program: Pubkey,
+    pub observation_key: Pubkey,
+    pub auth_bump: u8,
+    pub status: u8,
+    pub lp_mint_decimals: u8,
+    pub mint_0_decimals: u8,
+    pub mint_1_decimals: u8,
+    pub lp_supply: u64,
+    /// Fees accrued in the vaults that do not belong to liquidity providers
+    pub protocol_fees_token_0: u64,
+    pub protocol_fees_token_1: u64,
+    pub fund_fees_token_0: u64,
+    pub fund_fees_token_1: u64,
+    pub open_time: u64,
+    pub recent_epoch: u64,
+    /// Which side the creator fee is charged on (see `CreatorFeeOn`)
+    pub creator_fee_on: u8,
+    pub enable_creator_fee: bool,
+    pub padding1: [u8; 6],
+    pub creator_fees_token_0: u64,
+    pub creator_fees_token_1: u64,
+    pub padding: [u64; 28],
+}
+
+impl CpmmPoolState {
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        if data.len() != CPMM_POOL_STATE_SIZE || data[..8] != POOL_STATE_DISCRIMINATOR {
+            anyhow::bail!("Invalid CPMM pool account ({} bytes)", data.len());
+        }
+        Ok(Self::try_from_slice(&data[8..])?)
+    }
+
+    pub fn swap_enabled(&self) -> bool {
+        self.status & STATUS_SWAP_DISABLED == 0
+    }
+
+    /// The program only accepts swaps strictly after `open_time`
+    pub fn is_open(&self, now: u64) -> bool {
+        now > self.open_time
+    }
+
+    /// Whether the creator fee is taken from the input token for this direction.
+    /// Mirrors `CreatorFeeOn`: 0 = both tokens, 1 = token 0 only, 2 = token 1 only.
+    fn creator_fee_on_input(&self, direction: SwapDirection) -> bool {
+        match self.creator_fee_on {
+            1 => direction == SwapDirection::AToB,
+            2 => direction == SwapDirection::BToA,
+            _ => true,
+        }
+    }
+
+    /// Vault balances minus fees owed to the protocol, fund and creator
+    pub fn reserves(&self, vault_0_amount: u64, vault_1_amount: u64) -> Result<(u64, u64)> {
+        let reserve = |vault: u64, owed: [u64; 3]| {
+            owed.iter()
+                .try_fold(vault, |left, fee| left.checked_sub(*fee))
+                .ok_or_else(|| anyhow::anyhow!("CPMM vault balance {} is below accrued fees", vault))
+        };
+        Ok((
+            reserve(vault_0_amount, [self.protocol_fees_token_0, self.fund_fees_token_0, self.creator_fees_token_0])?,
+            reserve(vault_1_amount, [self.protocol_fees_token_1, self.fund_fees_token_1, self.creator_fees_token_1])?,
+        ))
+    }
+}
+
+/// CPMM fee configuration shared by every pool created under it
+#[derive(BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AmmConfig {
+    pub bump: u8,
+    pub disable_create_pool: bool,
+    pub index: u16,
+    /// Rates per million; protocol and fund rates are shares of the trade fee
+    pub trade_fee_rate: u64,
+    pub protocol_fee_rate: u64,
+    pub fund_fee_rate: u64,
+    pub create_pool_fee: u64,
+    pub protocol_owner: Pubkey,
+    pub fund_owner: Pubkey,
+    pub creator_fee_rate: u64,
+    pub padding: [u64; 15],
+}
+
+impl AmmConfig {
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        if data.len() < 8 || data[..8] != AMM_CONFIG_DISCRIMINATOR {
+            anyhow::bail!("Not a CPMM AmmConfig account");
+        }
+        Ok(Self::deserialize(&mut &data[8..])?)
+    }
+}
+
+/// Fee charged on `amount`, rounded up
+fn fee_amount(amount: u64, rate: u64) -> u64 {
+    (amount as u128 * rate as u128).div_ceil(FEE_RATE_DENOMINATOR) as u64
+}
+
+/// Gross amount whose fee at `rate` leaves `net`
+fn pre_fee_amount(net: u64, rate: u64) -> Result<u64> {
+    if rate == 0 {
+        return Ok(net);
+    }
+    let gross = (net as u128 * FEE_RATE_DENOMINATOR).div_ceil(FEE_RATE_DENOMINATOR - rate as u128);
+    u64::try_from(gross).map_err(|_| anyhow::anyhow!("Required input exceeds u64"))
+}
+
+fn oriented(reserve_0: u64, reserve_1: u64, direction: SwapDirection) -> (u128, u128) {
+    match direction {
+        SwapDirection::AToB => (reserve_0 as u128, reserve_1 as u128),
+        SwapDirection::BToA => (reserve_1 as u128, reserve_0 as u128),
+    }
+}
+
+fn post_trade(
+    reserve_0: u64,
+    reserve_1: u64,
+    direction: SwapDirection,
+    amount_in: u64,
+    amount_out: u64,
+) -> Result<PostTradeState> {
+    let overflow = || anyhow::anyhow!("Swap input overflows the pool reserve");
+    let (reserve_a, reserve_b) = match direction {
+        SwapDirection::AToB => (reserve_0.checked_add(amount_in).ok_or_else(overflow)?, reserve_1 - amount_out),
+        SwapDirection::BToA => (reserve_0 - amount_out, reserve_1.checked_add(amount_in).ok_or_else(overflow)?),
+    };
+    Ok(PostTradeState::Reserves { reserve_a, reserve_b })
+}
+
+/// Quote `swap_base_input`: trade and creator fees come off the input
+/// (rounded up), unless the creator fee is charged on the output token
+pub fn swap_base_input(
+    pool: &CpmmPoolState,
+    config: &AmmConfig,
+    reserve_0: u64,
+    reserve_1: u64,
+    amount_in: u64,
+    direction: SwapDirection,
+) -> Result<SwapQuote> {
+    let (reserve_in, reserve_out) = oriented(reserve_0, reserve_1, direction);
+    if reserve_in == 0 || reserve_out == 0 {
+        anyhow::bail!(PriceError::EmptyPool);
+    }
+
+    let creator_fee_rate = if pool.enable_creator_fee { config.creator_fee_rate } else { 0 };
+    let creator_fee_on_input = pool.creator_fee_on_input(direction);
+
+    let trade_fee = fee_amount(amount_in, config.trade_fee_rate);
+    let input_creator_fee = if creator_fee_on_input { fee_amount(amount_in, creator_fee_rate) } else { 0 };
+    let amount_in_after_fees = amount_in
+        .checked_sub(trade_fee + input_creator_fee)
+        .ok_or_else(|| anyhow::anyhow!("Input {} does not cover fees", amount_in))?;
+
+    let swapped_out = (reserve_out * amount_in_after_fees as u128 / (reserve_in + amount_in_after_fees as u128)) as u64;
+    let output_creator_fee = if creator_fee_on_input { 0 } else { fee_amount(swapped_out, creator_fee_rate) };
+    let amount_out = swapped_out - output_creator_fee;
+
+    Ok(SwapQuote {
+        amount_in,
+        amount_out,
+        fee_amount: trade_fee + input_creator_fee,
+        price_impact_bps: price_impact_bps(Price::from_ratio(reserve_out, reserve_in)?, amount_in_after_fees, swapped_out)?,
+        post_state: post_trade(reserve_0, reserve_1, direction, amount_in, swapped_out)?,
+        transfer_fees: TransferFees::default(),
+        observed: Observation::default(),
+    })
+}
+
+/// Quote `swap_base_output`: the constant-product input is rounded up,
+/// then grossed up for the fees charged on the input side
+pub fn swap_base_output(
+    pool: &CpmmPoolState,
+    config: &AmmConfig,
+    reserve_0: u64,
+    reserve_1: u64,
+    amount_out: u64,
+    direction: SwapDirection,
+) -> Result<SwapQuote> {
+    let (reserve_in, reserve_out) = oriented(reserve_0, reserve_1, direction);
+
+    let creator_fee_rate = if pool.enable_creator_fee { config.creator_fee_rate } else { 0 };
+    let creator_fee_on_input = pool.creator_fee_on_input(direction);
+
+    let swapped_out = if creator_fee_on_input { amount_out } else { pre_fee_amount(amount_out, creator_fee_rate)? };
+    if reserve_in == 0 || swapped_out as u128 >= reserve_out {
+        anyhow::bail!("Insufficient liquidity for {} output", amount_out);
+    }
+
+    let amount_in_after_fees = (reserve_in * swapped_out as u128).div_ceil(reserve_out - swapped_out as u128);
+    let amount_in_after_fees =
+        u64::try_from(amount_in_after_fees).map_err(|_| anyhow::anyhow!("Required input exceeds u64"))?;
+    let input_fee_rate = config.trade_fee_rate + if creator_fee_on_input { creator_fee_rate } else { 0 };
+    let amount_in = pre_fee_amount(amount_in_after_fees, input_fee_rate)?;
+
+    Ok(SwapQuote {
+        amount_in,
+        amount_out,
+        fee_amount: amount_in - amount_in_after_fees,
+        price_impact_bps: price_impact_bps(Price::from_ratio(reserve_out, reserve_in)?, amount_in_after_fees, swapped_out)?,
+        post_state: post_trade(reserve_0, reserve_1, direction, amount_in, swapped_out)?,
+        transfer_fees: TransferFees::default(),
+        observed: Observation::default(),
+    })
+}
+
+#[derive(Default)]
+pub struct RaydiumCpmmClient {}
+
+impl RaydiumCpmmClient {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+/// Reject quotes the program would refuse: swaps disabled or pool not yet open
+fn check_tradable(pool: &DecodedPool, cpmm: &CpmmPoolState) -> Result<(), PriceError> {
+    if !cpmm.swap_enabled() {
+        return Err(anyhow::anyhow!("Swaps are disabled on pool {}", pool.address).into());
+    }
+    if !cpmm.is_open(unix_timestamp()) {
+        return Err(anyhow::anyhow!("Pool {} does not open until {}", pool.address, cpmm.open_time).into());
+    }
+    Ok(())
+}
+
+/// Pool, config and reserves from pre-fetched accounts
+fn pool_with_reserves<'a>(
+    pool: &'a DecodedPool,
+    accounts: &AccountMap,
+) -> Result<(&'a CpmmPoolState, AmmConfig, u64, u64), PriceError> {
+    let PoolState::RaydiumCpmm(cpmm) = &pool.state else {
+        return Err(PriceError::layout(pool.address, "not a Raydium CPMM pool"));
+    };
+    let config = required_account(accounts, &cpmm.amm_config)?;
+    let config = AmmConfig::from_bytes(&config.data).map_err(|err| PriceError::layout(cpmm.amm_config, err))?;
+
+    let (reserve_0, reserve_1) = cpmm.reserves(
+        vault_amount(accounts, &cpmm.token_0_vault)?,
+        vault_amount(accounts, &cpmm.token_1_vault)?,
+    )?;
+    Ok((cpmm, config, reserve_0, reserve_1))
+}
+
+impl PoolQuoter for RaydiumCpmmClient {
+    fn dex(&self) -> DexType {
+        DexType::RaydiumCPMM
+    }
+
+    fn program_id(&self) -> Pubkey {
+        RAYDIUM_CPMM_PROGRAM_ID
+    }
+
+    fn decode(&self, address: &Pubkey, data: &[u8]) -> Result<DecodedPool, PriceError> {
+        let pool = CpmmPoolState::from_bytes(data).map_err(|err| PriceError::layout(*address, err))?;
+        Ok(DecodedPool {
+            address: *address,
+            dex: DexType::RaydiumCPMM,
+            mint_a: pool.token_0_mint,
+            mint_b: pool.token_1_mint,
+            state: PoolState::RaydiumCpmm(pool),
+            observed: Observation::default(),
+        })
+    }
+
+    fn required_accounts(&self, pool: &DecodedPool) -> Vec<Pubkey> {
+        let PoolState::RaydiumCpmm(cpmm) = &pool.state else {
+            return Vec::new();
+        };
+        vec![cpmm.amm_config, cpmm.token_0_vault, cpmm.token_1_vault]
+    }
+
+    fn mid_price(&self, pool: &DecodedPool, accounts: &AccountMap) -> Result<Price, PriceError> {
+        let (_, _, reserve_0, reserve_1) = pool_with_reserves(pool, accounts)?;
+        if reserve_0 == 0 || reserve_1 == 0 {
+            return Err(PriceError::EmptyPool);
+        }
+        Ok(Price::from_ratio(reserve_1 as u128, reserve_0 as u128)?)
+    }
+
+    fn quote_exact_in(
+        &self,
+        pool: &DecodedPool,
+        accounts: &AccountMap,
+        amount_in: u64,
+        direction: SwapDirection,
+    ) -> Result<SwapQuote, PriceError> {
+        let (cpmm, config, reserve_0, reserve_1) = pool_with_reserves(pool, accounts)?;
+        check_tradable(pool, cpmm)?;
+        Ok(swap_base_input(cpmm, &config, reserve_0, reserve_1, amount_in, direction)?)
+    }
+
+    fn quote_exact_out(
+        &self,
+        pool: &DecodedPool,
+        accounts: &AccountMap,
+        amount_out: u64,
+        direction: SwapDirection,
+    ) -> Result<SwapQuote, PriceError> {
+        let (cpmm, config, reserve_0, reserve_1) = pool_with_reserves(pool, accounts)?;
+        check_tradable(pool, cpmm)?;
+        Ok(swap_base_output(cpmm, &config, reserve_0, reserve_1, amount_out, direction)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quoter::TransferFees;
+    use crate::test_utils::{put_pubkey, token_account_in};
+    use crate::token::{SPL_TOKEN_PROGRAM_ID, TOKEN_2022_PROGRAM_ID};
+    use crate::QuoterDispatcher;
+    use solana_sdk::account::Account;
+
+    const AMM_CONFIG_SIZE: usize = 236;
+    // Offsets in the pool account, discriminator included
+    const OFFSET_AMM_CONFIG: usize = 8;
+    const OFFSET_TOKEN_0_VAULT: usize = 72;
+    const OFFSET_TOKEN_1_VAULT: usize = 104;
+    const OFFSET_TOKEN_0_MINT: usize = 168;
+    const OFFSET_TOKEN_1_MINT: usize = 200;
+    const OFFSET_TOKEN_0_PROGRAM: usize = 232;
+    const OFFSET_TOKEN_1_PROGRAM: usize = 264;
+    const OFFSET_STATUS: usize = 329;
+    const OFFSET_PROTOCOL_FEES_0: usize = 341;
+    const OFFSET_FUND_FEES_1: usize = 365;
+    const OFFSET_CREATOR_FEE_ON: usize = 389;
+    const OFFSET_ENABLE_CREATOR_FEE: usize = 390;
+
+    struct Fixture {
+        pool: DecodedPool,
+        accounts: AccountMap,
+    }
+
+    /// Mint account, with a Token-2022 `TransferFeeConfig` charging `fee_bps` from epoch 0 if given
+    fn mint_account(owner: Pubkey, fee_bps: Option<u16>) -> Account {
+        let mut data = vec![0u8; 82];
+        data[44..46].copy_from_slice(&[6, 1]);
+        if let Some(fee_bps) = fee_bps {
+            data.resize(165, 0);
+            data.push(1);
+            data.extend_from_slice(&1u16.to_le_bytes());
+            data.extend_from_slice(&108u16.to_le_bytes());
+            data.extend_from_slice(&[0u8; 72]);
+            for _ in 0..2 {
+                data.extend_from_slice(&0u64.to_le_bytes());
+                data.extend_from_slice(&u64::MAX.to_le_bytes());
+                data.extend_from_slice(&fee_bps.to_le_bytes());
+            }
+        }
+        Account {
+            data,
+            owner,
+            ..Account::default()
+        }
+    }
+
+    /// Pool with 1_000_000 / 2_000_000 in its vaults, 0.25% trade fee and
+    /// a 0.1% creator fee on token 1, which is a Token-2022 mint without
+    /// transfer fees
+    fn fixture(status: u8) -> Fixture {
+        let (config, vault_0, vault_1) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let (mint_0, mint_1) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+        let mut data = vec![0u8; CPMM_POOL_STATE_SIZE];
+        data[..8].copy_from_slice(&POOL_STATE_DISCRIMINATOR);
+        put_pubkey(&mut data, OFFSET_AMM_CONFIG, &config);
+        put_pubkey(&mut data, OFFSET_TOKEN_0_VAULT, &vault_0);
+        put_pubkey(&mut data, OFFSET_TOKEN_1_VAULT, &vault_1);
+        put_pubkey(&mut data, OFFSET_TOKEN_0_MINT, &mint_0);
+        put_pubkey(&mut data, OFFSET_TOKEN_1_MINT, &mint_1);
+        put_pubkey(&mut data, OFFSET_TOKEN_0_PROGRAM, &SPL_TOKEN_PROGRAM_ID);
+        put_pubkey(&mut data, OFFSET_TOKEN_1_PROGRAM, &TOKEN_2022_PROGRAM_ID);
+        data[OFFSET_STATUS] = status;
+        data[OFFSET_PROTOCOL_FEES_0..OFFSET_PROTOCOL_FEES_0 + 8].copy_from_slice(&1_000u64.to_le_bytes());
+        data[OFFSET_FUND_FEES_1..OFFSET_FUND_FEES_1 + 8].copy_from_slice(&2_000u64.to_le_bytes());
+        data[OFFSET_CREATOR_FEE_ON] = 2;
+        data[OFFSET_ENABLE_CREATOR_FEE] = 1;
+
+        let mut config_data = vec![0u8; AMM_CONFIG_SIZE];
+        config_data[..8].copy_from_slice(&AMM_CONFIG_DISCRIMINATOR);
+        config_data[12..20].copy_from_slice(&2_500u64.to_le_bytes());
+        config_data[108..116].copy_from_slice(&1_000u64.to_le_bytes());
+
+        let mut accounts = AccountMap::new();
+        accounts.insert(
+            config,
+            Account {
+                data: config_data,
+                owner: RAYDIUM_CPMM_PROGRAM_ID,
+                ..Account::default()
+            },
+        );
+        accounts.insert(vault_0, token_account_in(SPL_TOKEN_PROGRAM_ID, 1_001_000));
+        accounts.insert(vault_1, token_account_in(TOKEN_2022_PROGRAM_ID, 2_002_000));
+        accounts.insert(mint_0, mint_account(SPL_TOKEN_PROGRAM_ID, None));
+        accounts.insert(mint_1, mint_account(TOKEN_2022_PROGRAM_ID, None));
+
+        let pool = RaydiumCpmmClient::new().decode(&Pubkey::new_unique(), &data).unwrap();
+        Fixture { pool, accounts }
+    }
+
+    #[test]
+    fn test_decode_and_reserves() {
+        let Fixture { pool, accounts } = fixture(0);
+        let PoolState::RaydiumCpmm(cpmm) = &pool.state else {
+            panic!("expected CPMM state");
+        };
+        assert_eq!(cpmm.token_1_program, TOKEN_2022_PROGRAM_ID);
+        assert_eq!(RaydiumCpmmClient::new().required_accounts(&pool).len(), 3);
+
+        // Accrued protocol and fund fees are excluded from the reserves
+        let mid = RaydiumCpmmClient::new().mid_price(&pool, &accounts).unwrap();
+        assert_eq!(mid, Price::from_integer(2));
+        assert!(CpmmPoolState::from_bytes(&[0u8; 100]).is_err());
+    }
+
+    #[test]
+    fn test_swap_quotes() {
+        let Fixture { pool, accounts } = fixture(0);
+        let client = RaydiumCpmmClient::new();
+
+        // Token 0 in: only the 0.25% trade fee applies on input, creator fee comes off token 1 output
+        let quote = client.quote_exact_in(&pool, &accounts, 10_000, SwapDirection::AToB).unwrap();
+        assert_eq!(quote.fee_amount, 25);
+        let swapped = 2_000_000u128 * 9_975 / 1_009_975;
+        assert_eq!(quote.amount_out as u128, swapped - (swapped * 1_000).div_ceil(1_000_000));
+
+        // Token 1 in: creator fee is charged on the input as well
+        let reverse = client.quote_exact_in(&pool, &accounts, 10_000, SwapDirection::BToA).unwrap();
+        assert_eq!(reverse.fee_amount, 35);
+
+        // Exact out covers the requested output and costs no more than a unit over exact in
+        let exact_out = client.quote_exact_out(&pool, &accounts, quote.amount_out, SwapDirection::AToB).unwrap();
+        assert_eq!(exact_out.amount_out, quote.amount_out);
+        assert!(exact_out.amount_in <= quote.amount_in + 1);
+        let check = client.quote_exact_in(&pool, &accounts, exact_out.amount_in, SwapDirection::AToB).unwrap();
+        assert!(check.amount_out >= quote.amount_out);
+    }
+
+    #[test]
+    fn test_swap_disabled() {
+        let Fixture { pool, accounts } = fixture(STATUS_SWAP_DISABLED);
+        let client = RaydiumCpmmClient::new();
+        assert!(client.quote_exact_in(&pool, &accounts, 10_000, SwapDirection::AToB).is_err());
+        assert!(client.mid_price(&pool, &accounts).is_ok());
+
+        // Pools cannot be traded until after their open time
+        let Fixture { pool, accounts } = fixture(0);
+        let PoolState::RaydiumCpmm(mut cpmm) = pool.state.clone() else {
+            unreachable!()
+        };
+        cpmm.open_time = unix_timestamp() + 3_600;
+        assert!(!cpmm.is_open(cpmm.open_time) && cpmm.is_open(cpmm.open_time + 1));
+        let pool = DecodedPool {
+            state: PoolState::RaydiumCpmm(cpmm),
+            ..pool
+        };
+        assert!(client.quote_exact_out(&pool, &accounts, 1_000, SwapDirection::AToB).is_err());
+        assert!(client.mid_price(&pool, &accounts).is_ok());
+    }
+
+    #[test]
+    fn test_oversized_input_is_rejected() {
+        let Fixture { pool, accounts } = fixture(0);
+        let client = RaydiumCpmmClient::new();
+        assert!(client.quote_exact_in(&pool, &accounts, u64::MAX, SwapDirection::AToB).is_err());
+        assert!(client.quote_exact_in(&pool, &accounts, u64::MAX, SwapDirection::BToA).is_err());
+    }
+
+    #[test]
+    fn test_token_2022_transfer_fee() {
+        let Fixture { pool, mut accounts } = fixture(0);
+        let client = RaydiumCpmmClient::new();
+        let dispatcher = QuoterDispatcher::new();
+        let gross_out = client.quote_exact_in(&pool, &accounts, 10_000, SwapDirection::AToB).unwrap();
+        let reduced_in = client.quote_exact_in(&pool, &accounts, 9_900, SwapDirection::BToA).unwrap();
+
+        // A 1% fee on token 1 needs the Clock to pick the epoch's schedule
+        let PoolState::RaydiumCpmm(cpmm) = &pool.state else {
+            unreachable!()
+        };
+        accounts.insert(cpmm.token_1_mint, mint_account(TOKEN_2022_PROGRAM_ID, Some(100)));
+        assert!(dispatcher.quote_exact_in(&pool, &accounts, 10_000, SwapDirection::AToB).is_err());
+        let mut clock = vec![0u8; 40];
+        clock[16..24].copy_from_slice(&700u64.to_le_bytes());
+        accounts.insert(
+            solana_sdk::sysvar::clock::ID,
+            Account {
+                data: clock,
+                ..Account::default()
+            },
+        );
+
+        // Token 1 out: the pool's output loses 1% on its way to the trader
+        let net = dispatcher.quote_exact_in(&pool, &accounts, 10_000, SwapDirection::AToB).unwrap();
+        let output_fee = gross_out.amount_out.div_ceil(100);
+        assert_eq!(net.amount_out, gross_out.amount_out - output_fee);
+        assert_eq!(net.transfer_fees, TransferFees { input: 0, output: output_fee });
+
+        // Token 1 in: only 99% of the input reaches the pool
+        let net = dispatcher.quote_exact_in(&pool, &accounts, 10_000, SwapDirection::BToA).unwrap();
+        assert_eq!((net.amount_in, net.amount_out), (10_000, reduced_in.amount_out));
+        assert_eq!(net.transfer_fees, TransferFees { input: 100, output: 0 });
+
+        // Exact out grosses the input up for the fee withheld on the way in
+        let exact_out = dispatcher.quote_exact_out(&pool, &accounts, net.amount_out, SwapDirection::BToA).unwrap();
+        assert_eq!(exact_out.amount_out, net.amount_out);
+        assert!(exact_out.amount_in <= 10_000 + 1);
+        let check = dispatcher.quote_exact_in(&pool, &accounts, exact_out.amount_in, SwapDirection::BToA).unwrap();
+        assert!(check.amount_out >= net.amount_out);
+    }
+}
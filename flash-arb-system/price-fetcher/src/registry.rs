@@ -0,0 +1,293 @@
+//! Persistent pool registry.
+//!
+//! Pool metadata rarely changes once a pool exists, so discovered pools are
+//! kept in a JSON file and a mint pair is only scanned again once its last
+//! scan is older than the caller's refresh interval. A registry can also be
+//! seeded from a checked-in file for tests that run without an RPC endpoint.
+
+use anyhow::{Context, Result};
+use parking_lot::RwLock;
+use rpc_manager::RpcBackend;
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::discovery::{pubkey_str, PoolDiscovery, PoolMetadata};
+use crate::error::PriceError;
+use crate::quoter::{unix_timestamp, DexType};
+
+/// On-disk layout of a registry file
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct RegistryFile {
+    pools: Vec<PoolMetadata>,
+    #[serde(default)]
+    pairs: Vec<PairScan>,
+}
+
+/// When a mint pair was last scanned, in unix seconds
+#[derive(Debug, Serialize, Deserialize)]
+struct PairScan {
+    #[serde(with = "pubkey_str")]
+    mint_x: Pubkey,
+    #[serde(with = "pubkey_str")]
+    mint_y: Pubkey,
+    refreshed_at: u64,
+}
+
+/// Mint pair in canonical (lower, higher) order
+fn pair_key(mint_x: &Pubkey, mint_y: &Pubkey) -> (Pubkey, Pubkey) {
+    if mint_x <= mint_y {
+        (*mint_x, *mint_y)
+    } else {
+        (*mint_y, *mint_x)
+    }
+}
+
+/// Discovered pools indexed by address, optionally backed by a JSON file
+#[derive(Default)]
+pub struct PoolRegistry {
+    pools: RwLock<HashMap<Pubkey, PoolMetadata>>,
+    refreshed_at: RwLock<HashMap<(Pubkey, Pubkey), u64>>,
+    path: Option<PathBuf>,
+}
+
+impl PoolRegistry {
+    /// An empty registry that is never written to disk
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load the registry at `path`, or start empty if the file does not exist
+    /// yet. [`save`](Self::save) writes back to the same path.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let mut registry = if path.exists() {
+            let json = std::fs::read_to_string(path).with_context(|| format!("Failed to read pool registry {}", path.display()))?;
+            Self::from_json(&json).with_context(|| format!("Failed to parse pool registry {}", path.display()))?
+        } else {
+            Self::new()
+        };
+        registry.path = Some(path.to_path_buf());
+        Ok(registry)
+    }
+
+    /// Build an in-memory registry from the contents of a registry file
+    pub fn from_json(json: &str) -> Result<Self> {
+        let file: RegistryFile = serde_json::from_str(json)?;
+        let registry = Self::new();
+        registry.extend(file.pools);
+        registry.refreshed_at.write().extend(
+            file.pairs
+                .into_iter()
+                .map(|scan| (pair_key(&scan.mint_x, &scan.mint_y), scan.refreshed_at)),
+        );
+        Ok(registry)
+    }
+
+    /// Serialize the registry, with pools and pairs in a stable order so the
+    /// file diffs cleanly
+    pub fn to_json(&self) -> Result<String> {
+        let mut pools: Vec<PoolMetadata> = self.pools.read().values().cloned().collect();
+        pools.sort_by_key(|pool| pool.address);
+        let mut pairs: Vec<PairScan> = self
+            .refreshed_at
+            .read()
+            .iter()
+            .map(|(&(mint_x, mint_y), &refreshed_at)| PairScan {
+                mint_x,
+                mint_y,
+                refreshed_at,
+            })
+            .collect();
+        pairs.sort_by_key(|scan| (scan.mint_x, scan.mint_y));
+        Ok(serde_json::to_string_pretty(&RegistryFile { pools, pairs })?)
+    }
+
+    /// Write the registry to the file it was opened from. The file is
+    /// replaced atomically so a crash mid-write keeps the previous contents.
+    pub fn save(&self) -> Result<()> {
+        let path = self.path.as_ref().context("Pool registry has no backing file")?;
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, self.to_json()?).with_context(|| format!("Failed to write {}", tmp.display()))?;
+        std::fs::rename(&tmp, path).with_context(|| format!("Failed to replace {}", path.display()))?;
+        Ok(())
+    }
+
+    /// Add or replace a pool
+    pub fn insert(&self, pool: PoolMetadata) {
+        self.pools.write().insert(pool.address, pool);
+    }
+
+    pub fn extend(&self, pools: impl IntoIterator<Item = PoolMetadata>) {
+        let mut map = self.pools.write();
+        for pool in pools {
+            map.insert(pool.address, pool);
+        }
+    }
+
+    pub fn get(&self, address: &Pubkey) -> Option<PoolMetadata> {
+        self.pools.read().get(address).cloned()
+    }
+
+    /// Pools trading `mint_x` against `mint_y` in either orientation, by address
+    pub fn by_pair(&self, mint_x: &Pubkey, mint_y: &Pubkey) -> Vec<PoolMetadata> {
+        let key = pair_key(mint_x, mint_y);
+        self.filtered(|pool| pair_key(&pool.mint_a, &pool.mint_b) == key)
+    }
+
+    /// Pools on `dex`, by address
+    pub fn by_dex(&self, dex: DexType) -> Vec<PoolMetadata> {
+        self.filtered(|pool| pool.dex == dex)
+    }
+
+    pub fn len(&self) -> usize {
+        self.pools.read().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pools.read().is_empty()
+    }
+
+    fn filtered(&self, predicate: impl Fn(&PoolMetadata) -> bool) -> Vec<PoolMetadata> {
+        let mut pools: Vec<PoolMetadata> = self.pools.read().values().filter(|pool| predicate(pool)).cloned().collect();
+        pools.sort_by_key(|pool| pool.address);
+        pools
+    }
+
+    /// When the pair was last scanned, if ever
+    pub fn refreshed_at(&self, mint_x: &Pubkey, mint_y: &Pubkey) -> Option<u64> {
+        self.refreshed_at.read().get(&pair_key(mint_x, mint_y)).copied()
+    }
+
+    /// Whether the pair has never been scanned or was last scanned more than
+    /// `max_age_secs` before `now`
+    pub fn needs_refresh(&self, mint_x: &Pubkey, mint_y: &Pubkey, max_age_secs: u64, now: u64) -> bool {
+        self.refreshed_at(mint_x, mint_y)
+            .is_none_or(|refreshed_at| now.saturating_sub(refreshed_at) > max_age_secs)
+    }
+
+    /// Scan the pair again and merge the result: new pools are added, known
+    /// ones updated, and pools on the scanned DEXes that no longer match are
+    /// dropped. Returns the number of pools added.
+    pub async fn refresh_pair<B: RpcBackend + ?Sized>(
+        &self,
+        rpc: &B,
+        discovery: &PoolDiscovery,
+        mint_x: &Pubkey,
+        mint_y: &Pubkey,
+    ) -> Result<usize, PriceError> {
+        let found = discovery.pools_for_pair(rpc, mint_x, mint_y).await?;
+        let scanned: Vec<DexType> = discovery.dexes().collect();
+        let key = pair_key(mint_x, mint_y);
+
+        let mut pools = self.pools.write();
+        pools.retain(|address, pool| {
+            pair_key(&pool.mint_a, &pool.mint_b) != key
+                || !scanned.contains(&pool.dex)
+                || found.iter().any(|found| found.address == *address)
+        });
+        let mut added = 0;
+        for pool in found {
+            if pools.insert(pool.address, pool).is_none() {
+                added += 1;
+            }
+        }
+        self.refreshed_at.write().insert(key, unix_timestamp());
+        Ok(added)
+    }
+
+    /// Rescan every pair known to the registry that is older than
+    /// `max_age_secs`. Seeded pairs without a scan time count as stale.
+    /// Returns the number of pools added.
+    pub async fn refresh_stale<B: RpcBackend + ?Sized>(
+        &self,
+        rpc: &B,
+        discovery: &PoolDiscovery,
+        max_age_secs: u64,
+    ) -> Result<usize, PriceError> {
+        let now = unix_timestamp();
+        let mut pairs: Vec<(Pubkey, Pubkey)> = self.pools.read().values().map(|pool| pair_key(&pool.mint_a, &pool.mint_b)).collect();
+        pairs.extend(self.refreshed_at.read().keys().copied());
+        pairs.sort();
+        pairs.dedup();
+        pairs.retain(|(mint_x, mint_y)| self.needs_refresh(mint_x, mint_y, max_age_secs, now));
+
+        let mut added = 0;
+        for (mint_x, mint_y) in pairs {
+            added += self.refresh_pair(rpc, discovery, &mint_x, &mint_y).await?;
+        }
+        Ok(added)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rpc_manager::InMemoryBackend;
+
+    /// Real SOL, USDC and BONK mints with made-up pool and vault addresses
+    const SEED: &str = include_str!("../fixtures/synthetic_pools.json");
+
+    fn key(s: &str) -> Pubkey {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_lookup_seeded_registry() {
+        let registry = PoolRegistry::from_json(SEED).unwrap();
+        let sol = key("So11111111111111111111111111111111111111112");
+        let usdc = key("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v");
+        assert_eq!(registry.len(), 3);
+
+        let pairs = registry.by_pair(&usdc, &sol);
+        assert_eq!(pairs.len(), 2);
+        assert!(pairs.iter().all(|pool| pool.decimals == Some((9, 6))));
+
+        let whirlpools = registry.by_dex(DexType::OrcaWhirlpool);
+        assert_eq!(whirlpools.len(), 1);
+        let pool = registry.get(&whirlpools[0].address).unwrap();
+        assert_eq!(pool.fee_ppm, Some(400));
+        assert_eq!(pool.vaults.map(|(a, _)| a), Some(key("SyntheticWhir1poo1Vau1tA1111111111111111111")));
+
+        let refreshed_at = registry.refreshed_at(&sol, &usdc).unwrap();
+        assert!(!registry.needs_refresh(&usdc, &sol, 3_600, refreshed_at + 60));
+        assert!(registry.needs_refresh(&usdc, &sol, 3_600, refreshed_at + 7_200));
+        // BONK/SOL is seeded without a scan time
+        assert!(registry.needs_refresh(&sol, &key("DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263"), 3_600, refreshed_at));
+    }
+
+    #[test]
+    fn test_save_and_reopen() {
+        let dir = std::env::temp_dir().join(format!("pool-registry-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("pools.json");
+        let _ = std::fs::remove_file(&path);
+
+        let registry = PoolRegistry::open(&path).unwrap();
+        assert!(registry.is_empty());
+        registry.extend(PoolRegistry::from_json(SEED).unwrap().filtered(|_| true));
+        registry.save().unwrap();
+
+        let reopened = PoolRegistry::open(&path).unwrap();
+        assert_eq!(reopened.len(), 3);
+        assert_eq!(reopened.to_json().unwrap(), registry.to_json().unwrap());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_refresh_replaces_pools_of_scanned_dexes() {
+        let registry = PoolRegistry::from_json(SEED).unwrap();
+        let sol = key("So11111111111111111111111111111111111111112");
+        let usdc = key("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v");
+
+        // Nothing on chain: the seeded V4 pool is dropped, the Whirlpool kept
+        let discovery = PoolDiscovery::with_dexes(&[DexType::RaydiumV4]);
+        let added = registry.refresh_pair(&InMemoryBackend::new(), &discovery, &sol, &usdc).await.unwrap();
+        assert_eq!(added, 0);
+        let remaining = registry.by_pair(&sol, &usdc);
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].dex, DexType::OrcaWhirlpool);
+        assert!(!registry.needs_refresh(&sol, &usdc, 60, unix_timestamp()));
+    }
+}
@@ -0,0 +1,294 @@
+//! Consistent multi-pool snapshots.
+//!
+//! Comparing prices across pools only makes sense if the pools were read at
+//! (nearly) the same slot. A snapshot fetches a set of pools together with
+//! every account their quoters need, packing keys into as few
+//! `getMultipleAccounts` calls as possible. Each call is answered at a single
+//! context slot, but the chain moves on between calls, so a snapshot that
+//! needs several batches reports the oldest and newest slot it was read at.
+
+use rpc_manager::RpcBackend;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{HashMap, HashSet};
+
+use crate::discovery::MAX_MULTIPLE_ACCOUNTS;
+use crate::error::PriceError;
+use crate::freshness::Observation;
+use crate::quoter::{AccountMap, DecodedPool, QuoterDispatcher};
+use crate::token::MintInfo;
+
+/// Pools decoded from accounts read between `oldest_slot` and `newest_slot`
+#[derive(Debug)]
+pub struct Snapshot {
+    /// Context slot of the earliest batch
+    pub oldest_slot: u64,
+    /// Context slot of the latest batch
+    pub newest_slot: u64,
+    pub pools: Vec<DecodedPool>,
+    /// Pool states and every account needed to quote them
+    pub accounts: AccountMap,
+    /// Pools left out of the snapshot, with the reason
+    pub failed: Vec<(Pubkey, PriceError)>,
+}
+
+impl Snapshot {
+    pub fn pool(&self, address: &Pubkey) -> Option<&DecodedPool> {
+        self.pools.iter().find(|pool| pool.address == *address)
+    }
+
+    /// Slots between the earliest and latest batch; zero when every account
+    /// was read at the same slot
+    pub fn slot_skew(&self) -> u64 {
+        self.newest_slot - self.oldest_slot
+    }
+}
+
+/// Accounts fetched over one or more batches, with the range of context
+/// slots the batches were answered at
+struct BatchedRead {
+    oldest_slot: u64,
+    newest_slot: u64,
+    accounts: AccountMap,
+    /// Context slot of the batch each key was read in
+    slots: HashMap<Pubkey, u64>,
+}
+
+impl QuoterDispatcher {
+    /// Fetch and decode `pools` with every account needed to quote them.
+    ///
+    /// Takes two rounds: the pool states decide which tick and bin arrays
+    /// are needed, and the second round re-reads each pool next to those
+    /// dependencies, no earlier than the first round's newest slot. A pool's
+    /// keys are kept in one batch whenever they fit, so a pool and its
+    /// arrays share a slot even when the snapshot as a whole spans several;
+    /// dependencies shared with a pool in an earlier batch are not re-read.
+    /// Pools that are missing or cannot be decoded are reported in `failed`
+    /// rather than failing the snapshot.
+    pub async fn fetch_snapshot<B: RpcBackend + ?Sized>(&self, rpc: &B, pools: &[Pubkey]) -> Result<Snapshot, PriceError> {
+        let mut pool_keys = pools.to_vec();
+        let mut seen = HashSet::new();
+        pool_keys.retain(|key| seen.insert(*key));
+
+        let first = fetch_batches(rpc, &pack_batches(pool_keys.iter().map(|key| vec![*key])), None).await?;
+        let mut failed = Vec::new();
+        let mut decoded = Vec::new();
+        for key in &pool_keys {
+            let pool = first
+                .accounts
+                .get(key)
+                .ok_or(PriceError::AccountMissing(*key))
+                .and_then(|account| self.decode(key, account));
+            match pool {
+                Ok(pool) => decoded.push(pool),
+                Err(err) => failed.push((*key, err)),
+            }
+        }
+        self.resolve_mints(rpc, &decoded).await?;
+
+        let mut groups = Vec::new();
+        let mut seen = HashSet::new();
+        let mut decoded_keys = Vec::new();
+        for pool in decoded {
+            match self.quote_accounts(&pool) {
+                Ok(dependencies) => {
+                    let group = std::iter::once(pool.address)
+                        .chain(dependencies)
+                        .filter(|key| seen.insert(*key))
+                        .collect();
+                    groups.push(group);
+                    decoded_keys.push(pool.address);
+                }
+                Err(err) => failed.push((pool.address, err)),
+            }
+        }
+
+        let second = fetch_batches(rpc, &pack_batches(groups), Some(first.newest_slot)).await?;
+        let mut decoded = Vec::new();
+        for key in decoded_keys {
+            let pool = second
+                .accounts
+                .get(&key)
+                .ok_or(PriceError::AccountMissing(key))
+                .and_then(|account| self.decode(&key, account));
+            let observed = Observation::received_now(second.slots.get(&key).copied().unwrap_or(second.oldest_slot));
+            match pool.map(|pool| DecodedPool { observed, ..pool }) {
+                Ok(pool) => decoded.push(pool),
+                Err(err) => failed.push((key, err)),
+            }
+        }
+
+        Ok(Snapshot {
+            oldest_slot: second.oldest_slot,
+            newest_slot: second.newest_slot,
+            pools: decoded,
+            accounts: second.accounts,
+            failed,
+        })
+    }
+
+    /// Cache metadata for the pools' mints not yet in the registry, which
+    /// decides whether their quotes need the Clock. Mints that are missing or
+    /// cannot be parsed are left out; quoting those pools reports the error.
+    async fn resolve_mints<B: RpcBackend + ?Sized>(&self, rpc: &B, pools: &[DecodedPool]) -> Result<(), PriceError> {
+        let mut unknown: Vec<Pubkey> = pools
+            .iter()
+            .flat_map(|pool| [pool.mint_a, pool.mint_b])
+            .filter(|mint| self.mints().get(mint).is_none())
+            .collect();
+        unknown.sort();
+        unknown.dedup();
+        if unknown.is_empty() {
+            return Ok(());
+        }
+
+        let read = fetch_batches(rpc, &pack_batches(unknown.into_iter().map(|mint| vec![mint])), None).await?;
+        for (mint, account) in &read.accounts {
+            if let Ok(info) = MintInfo::from_account(mint, account) {
+                self.mints().insert(*mint, info);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Pack groups of keys into `getMultipleAccounts` batches, starting a new
+/// batch rather than splitting a group that would fit in one
+pub(crate) fn pack_batches(groups: impl IntoIterator<Item = Vec<Pubkey>>) -> Vec<Vec<Pubkey>> {
+    let mut batches: Vec<Vec<Pubkey>> = Vec::new();
+    for group in groups.into_iter().filter(|group| !group.is_empty()) {
+        match batches.last_mut() {
+            Some(batch) if batch.len() + group.len() <= MAX_MULTIPLE_ACCOUNTS => batch.extend(group),
+            _ => batches.extend(group.chunks(MAX_MULTIPLE_ACCOUNTS).map(<[Pubkey]>::to_vec)),
+        }
+    }
+    batches
+}
+
+/// Fetch each batch in one call, no earlier than `min_context_slot`. Batches
+/// are answered at whatever slot the node is at, so they may differ.
+async fn fetch_batches<B: RpcBackend + ?Sized>(
+    rpc: &B,
+    batches: &[Vec<Pubkey>],
+    min_context_slot: Option<u64>,
+) -> Result<BatchedRead, PriceError> {
+    if batches.is_empty() {
+        let slot = match min_context_slot {
+            Some(slot) => slot,
+            None => rpc.get_slot().await?,
+        };
+        return Ok(BatchedRead {
+            oldest_slot: slot,
+            newest_slot: slot,
+            accounts: AccountMap::new(),
+            slots: HashMap::new(),
+        });
+    }
+
+    let mut read = BatchedRead {
+        oldest_slot: u64::MAX,
+        newest_slot: 0,
+        accounts: AccountMap::new(),
+        slots: HashMap::new(),
+    };
+    for batch in batches {
+        let (slot, fetched) = rpc.get_multiple_accounts_with_slot(batch, min_context_slot).await?;
+        read.oldest_slot = read.oldest_slot.min(slot);
+        read.newest_slot = read.newest_slot.max(slot);
+        read.slots.extend(batch.iter().map(|key| (*key, slot)));
+        read.accounts
+            .extend(batch.iter().zip(fetched).filter_map(|(key, account)| account.map(|account| (*key, account))));
+    }
+    Ok(read)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raydium::{AMM_V4_STATE_SIZE, RAYDIUM_V4_PROGRAM_ID};
+    use rpc_manager::InMemoryBackend;
+    use solana_sdk::account::Account;
+
+    fn v4_pool() -> Account {
+        Account {
+            data: vec![0u8; AMM_V4_STATE_SIZE],
+            owner: RAYDIUM_V4_PROGRAM_ID,
+            ..Account::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_batches_and_tags_slot() {
+        let backend = InMemoryBackend::new();
+        backend.set_slot(250_000_000);
+        let mut pools: Vec<Pubkey> = (0..150).map(|_| Pubkey::new_unique()).collect();
+        for pool in &pools {
+            backend.set_account(*pool, v4_pool());
+        }
+        let missing = Pubkey::new_unique();
+        let unknown_program = Pubkey::new_unique();
+        backend.set_account(unknown_program, Account::default());
+        pools.extend([missing, unknown_program, pools[0]]);
+
+        let snapshot = QuoterDispatcher::new().fetch_snapshot(&backend, &pools).await.unwrap();
+        assert_eq!((snapshot.oldest_slot, snapshot.newest_slot), (250_000_000, 250_000_000));
+        assert!(snapshot.pools.iter().all(|pool| pool.observed.slot == 250_000_000));
+        assert_eq!(snapshot.pools.len(), 150);
+        assert!(snapshot.pool(&pools[149]).is_some());
+        assert_eq!(snapshot.failed.len(), 2);
+        assert!(matches!(snapshot.failed[0], (key, PriceError::AccountMissing(_)) if key == missing));
+        assert!(pools[..150].iter().all(|pool| snapshot.accounts.contains_key(pool)));
+
+        // 152 pool keys, one lookup for the (zeroed, missing) mint, then 150
+        // pools plus their shared dependencies: two batches per round
+        assert_eq!(backend.account_requests(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_reports_slot_skew() {
+        let backend = InMemoryBackend::new();
+        backend.set_slot(1_000);
+        backend.set_slot_step(1);
+        // 150 pool keys take two batches in the first round, but only the 60
+        // that exist are re-read with their dependencies in the second
+        let pools: Vec<Pubkey> = (0..150).map(|_| Pubkey::new_unique()).collect();
+        for pool in &pools[..60] {
+            backend.set_account(*pool, v4_pool());
+        }
+
+        let snapshot = QuoterDispatcher::new().fetch_snapshot(&backend, &pools).await.unwrap();
+        // First round at 1_000 and 1_001, the mint lookup at 1_002, then a
+        // single second-round batch
+        assert_eq!((snapshot.oldest_slot, snapshot.newest_slot, snapshot.slot_skew()), (1_003, 1_003, 0));
+        assert_eq!((snapshot.pools.len(), snapshot.failed.len()), (60, 90));
+
+        // With every pool present the second round needs two batches as well
+        for pool in &pools[60..] {
+            backend.set_account(*pool, v4_pool());
+        }
+        let snapshot = QuoterDispatcher::new().fetch_snapshot(&backend, &pools).await.unwrap();
+        assert_eq!((snapshot.oldest_slot, snapshot.newest_slot, snapshot.slot_skew()), (1_007, 1_008, 1));
+        assert_eq!(snapshot.pools.len(), 150);
+        // Each pool carries the slot of the batch it was read in
+        assert_eq!(snapshot.pool(&pools[0]).unwrap().observed.slot, 1_007);
+        assert_eq!(snapshot.pool(&pools[149]).unwrap().observed.slot, 1_008);
+    }
+
+    #[test]
+    fn test_pack_batches_keeps_groups_together() {
+        let keys = |n: usize| (0..n).map(|_| Pubkey::new_unique()).collect::<Vec<_>>();
+        let batches = pack_batches([keys(60), keys(50), vec![], keys(40), keys(150)]);
+        let sizes: Vec<usize> = batches.iter().map(Vec::len).collect();
+        // The second group would straddle the limit so it starts a new batch;
+        // a group larger than a batch is split
+        assert_eq!(sizes, [60, 90, 100, 50]);
+    }
+
+    #[tokio::test]
+    async fn test_empty_snapshot_uses_current_slot() {
+        let backend = InMemoryBackend::new();
+        backend.set_slot(42);
+        let snapshot = QuoterDispatcher::new().fetch_snapshot(&backend, &[]).await.unwrap();
+        assert_eq!((snapshot.oldest_slot, snapshot.newest_slot, snapshot.pools.len()), (42, 42, 0));
+        assert_eq!(backend.account_requests(), 0);
+    }
+}
@@ -0,0 +1,241 @@
+//! Curve-style StableSwap invariant for two-token pools.
+//!
+//! Balances must already be scaled to a common precision. Everything is
+//! integer math with the same operation order and rounding as the Curve
+//! contracts, so D and y match on-chain values exactly rather than to
+//! within floating-point error. Intermediate products exceed `u128` for
+//! realistic balances and are computed in `BigUint`.
+
+use anyhow::Result;
+use num_bigint::BigUint;
+use num_traits::ToPrimitive;
+
+use crate::error::PriceError;
+use crate::price::Price;
+
+pub const N_COINS: u32 = 2;
+/// Amplification bounds enforced by the stable-swap programs on Solana
+pub const MIN_AMP: u64 = 1;
+pub const MAX_AMP: u64 = 1_000_000;
+const MAX_ITERATIONS: usize = 256;
+
+/// Amplification coefficient, possibly ramping linearly between two values
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Amplification {
+    pub initial: u64,
+    pub target: u64,
+    pub start_ts: i64,
+    pub stop_ts: i64,
+}
+
+impl Amplification {
+    pub fn fixed(amp: u64) -> Self {
+        Self {
+            initial: amp,
+            target: amp,
+            start_ts: 0,
+            stop_ts: 0,
+        }
+    }
+
+    /// Coefficient in effect at unix time `now`
+    pub fn at(&self, now: i64) -> Result<u64> {
+        let amp = if now >= self.stop_ts || self.stop_ts <= self.start_ts {
+            self.target
+        } else if now <= self.start_ts {
+            self.initial
+        } else {
+            let elapsed = (now - self.start_ts) as u128;
+            let duration = (self.stop_ts - self.start_ts) as u128;
+            if self.target >= self.initial {
+                self.initial + ((self.target - self.initial) as u128 * elapsed / duration) as u64
+            } else {
+                self.initial - ((self.initial - self.target) as u128 * elapsed / duration) as u64
+            }
+        };
+        if !(MIN_AMP..=MAX_AMP).contains(&amp) {
+            anyhow::bail!("Amplification coefficient {} outside [{}, {}]", amp, MIN_AMP, MAX_AMP);
+        }
+        Ok(amp)
+    }
+}
+
+fn abs_diff(a: &BigUint, b: &BigUint) -> BigUint {
+    if a > b {
+        a - b
+    } else {
+        b - a
+    }
+}
+
+fn to_u128(value: BigUint) -> Result<u128> {
+    value.to_u128().ok_or_else(|| anyhow::anyhow!("StableSwap value overflows u128"))
+}
+
+/// Invariant D for balances `amount_a` and `amount_b`, by Newton iteration
+pub fn compute_d(amp: u64, amount_a: u128, amount_b: u128) -> Result<u128> {
+    if amount_a == 0 || amount_b == 0 {
+        anyhow::bail!(PriceError::EmptyPool);
+    }
+    let (amount_a, amount_b) = (BigUint::from(amount_a), BigUint::from(amount_b));
+    let sum = &amount_a + &amount_b;
+    let ann = BigUint::from(amp) * N_COINS;
+    let leverage = &sum * &ann;
+    let a_times_coins = &amount_a * N_COINS;
+    let b_times_coins = &amount_b * N_COINS;
+
+    let mut d = sum;
+    for _ in 0..MAX_ITERATIONS {
+        let d_prod = (&d * &d / &a_times_coins) * &d / &b_times_coins;
+        let d_prev = d.clone();
+        let numerator = &d * (&d_prod * N_COINS + &leverage);
+        let denominator = &d * (&ann - 1u32) + &d_prod * (N_COINS + 1);
+        d = numerator / denominator;
+        if abs_diff(&d, &d_prev) <= BigUint::from(1u32) {
+            return to_u128(d);
+        }
+    }
+    anyhow::bail!("StableSwap D did not converge")
+}
+
+/// Balance of the other token that keeps the invariant at `d` when one
+/// balance is `x`
+pub fn compute_y(amp: u64, x: u128, d: u128) -> Result<u128> {
+    if x == 0 {
+        anyhow::bail!(PriceError::EmptyPool);
+    }
+    let (x, d) = (BigUint::from(x), BigUint::from(d));
+    let ann = BigUint::from(amp) * N_COINS;
+    let c = (&d * &d / (&x * N_COINS)) * &d / (&ann * N_COINS);
+    let b = &d / &ann + &x;
+
+    let mut y = d.clone();
+    for _ in 0..MAX_ITERATIONS {
+        let y_prev = y.clone();
+        let denominator = &y * 2u32 + &b;
+        if denominator <= d {
+            anyhow::bail!("StableSwap y is undefined for x {} and D {}", x, d);
+        }
+        y = (&y * &y + &c) / (denominator - &d);
+        if abs_diff(&y, &y_prev) <= BigUint::from(1u32) {
+            return to_u128(y);
+        }
+    }
+    anyhow::bail!("StableSwap y did not converge")
+}
+
+/// Output for exactly `amount_in`, before fees. Rounds down like Meteora's
+/// program, without Curve's extra one-unit deduction.
+pub fn swap_exact_in(amp: u64, amount_in: u128, reserve_in: u128, reserve_out: u128) -> Result<u128> {
+    let d = compute_d(amp, reserve_in, reserve_out)?;
+    let new_reserve_in = reserve_in
+        .checked_add(amount_in)
+        .ok_or_else(|| anyhow::anyhow!("Swap input overflow"))?;
+    let new_reserve_out = compute_y(amp, new_reserve_in, d)?;
+    Ok(reserve_out.saturating_sub(new_reserve_out))
+}
+
+/// Input needed to receive exactly `amount_out`, before fees, rounded up
+pub fn swap_exact_out(amp: u64, amount_out: u128, reserve_in: u128, reserve_out: u128) -> Result<u128> {
+    if amount_out >= reserve_out {
+        anyhow::bail!("Requested output {} exceeds reserve {}", amount_out, reserve_out);
+    }
+    let d = compute_d(amp, reserve_in, reserve_out)?;
+    let new_reserve_in = compute_y(amp, reserve_out - amount_out, d)?;
+    Ok(new_reserve_in.saturating_sub(reserve_in) + 1)
+}
+
+/// Marginal price of token A in token B at balances `amount_a` and
+/// `amount_b`, before fees. With `Ann = amp * n` the invariant is
+/// `Ann (x + y) + D = Ann D + D^3 / 4xy`; its slope at (x, y) is
+/// `(4 Ann x^2 y^2 + D^3 y) / (4 Ann x^2 y^2 + D^3 x)`.
+pub fn marginal_price(amp: u64, amount_a: u128, amount_b: u128) -> Result<Price> {
+    let d = BigUint::from(compute_d(amp, amount_a, amount_b)?);
+    let (x, y) = (BigUint::from(amount_a), BigUint::from(amount_b));
+    let d_cubed = &d * &d * &d;
+    let balance_term = BigUint::from(amp) * N_COINS * 4u32 * &x * &x * &y * &y;
+    let numerator = (&balance_term + &d_cubed * &y) << 64u32;
+    let denominator = balance_term + d_cubed * &x;
+    Ok(Price::from_x64(to_u128(numerator / denominator)?))
+}
+
+/// Reference vectors computed with a direct port of Curve's `get_D` and
+/// `get_y` to arbitrary-precision Python integers
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// (amp, reserve_in, reserve_out, D, amount_in, amount_out)
+    const VECTORS: [(u64, u128, u128, u128, u128, u128); 5] = [
+        (100, 1_000_000_000_000, 1_000_000_000_000, 2_000_000_000_000, 10_000_000_000, 9_999_009_902),
+        (100, 1_500_000_000_000, 500_000_000_000, 1_996_715_821_544, 15_000_000_000, 14_732_547_686),
+        (10, 50_000_000, 75_000_000_000, 24_890_790_182, 500_000, 302_999_385),
+        (2_000, 123_456_789_012_345, 98_765_432_109_876, 222_221_527_031_387, 61_728_394_506_172, 61_684_762_584_361),
+        (1, 1_000_000, 1_000_000_000, 193_404_745, 500_000, 174_497_180),
+    ];
+
+    #[test]
+    fn test_reference_vectors() {
+        for (amp, reserve_in, reserve_out, d, amount_in, amount_out) in VECTORS {
+            assert_eq!(compute_d(amp, reserve_in, reserve_out).unwrap(), d, "D for amp {}", amp);
+            assert_eq!(swap_exact_in(amp, amount_in, reserve_in, reserve_out).unwrap(), amount_out, "dy for amp {}", amp);
+
+            // Paying the exact-out input always yields at least the requested output
+            let required = swap_exact_out(amp, amount_out, reserve_in, reserve_out).unwrap();
+            assert!(swap_exact_in(amp, required, reserve_in, reserve_out).unwrap() >= amount_out);
+            assert!(required <= amount_in + 1);
+        }
+        assert_eq!(swap_exact_out(100, 14_732_547_686, 1_500_000_000_000, 500_000_000_000).unwrap(), 15_000_000_001);
+        assert_eq!(compute_d(100, 500_000_000_000, 1_500_000_000_000).unwrap(), 1_996_715_821_544);
+
+        // Truncation makes Newton's method oscillate between two values here,
+        // in this balance order only; Curve reverts on the same input
+        assert!(compute_d(1, 1_000_000_000, 1_000_000).is_err());
+    }
+
+    #[test]
+    fn test_marginal_price() {
+        assert_eq!(marginal_price(100, 1_000_000, 1_000_000).unwrap(), Price::ONE);
+
+        // Agrees with the execution price of a trade too small to move the
+        // curve, in pools deep enough that rounding does not dominate
+        for (amp, reserve_in, reserve_out, ..) in VECTORS.into_iter().filter(|vector| vector.1 >= 1_000_000_000_000) {
+            let marginal = marginal_price(amp, reserve_in, reserve_out).unwrap();
+            let amount_in = reserve_in / 100_000;
+            let amount_out = swap_exact_in(amp, amount_in, reserve_in, reserve_out).unwrap();
+            let execution = Price::from_ratio(amount_out, amount_in).unwrap();
+            assert!(execution <= marginal, "amp {}", amp);
+            assert!(execution.deviation_bps(marginal).unwrap() < 0.01, "amp {}", amp);
+        }
+        // The scarcer token is the more expensive one
+        assert!(marginal_price(100, 1_500_000_000_000, 500_000_000_000).unwrap() < Price::ONE);
+        assert!(marginal_price(100, 0, 1_000).is_err());
+    }
+
+    #[test]
+    fn test_empty_and_drained_pools() {
+        assert!(matches!(
+            compute_d(100, 0, 1_000).unwrap_err().downcast::<PriceError>(),
+            Ok(PriceError::EmptyPool)
+        ));
+        assert!(swap_exact_out(100, 1_000, 1_000, 1_000).is_err());
+    }
+
+    #[test]
+    fn test_amplification_ramp() {
+        let ramp = Amplification {
+            initial: 100,
+            target: 200,
+            start_ts: 1_000,
+            stop_ts: 2_000,
+        };
+        assert_eq!(ramp.at(500).unwrap(), 100);
+        assert_eq!(ramp.at(1_250).unwrap(), 125);
+        assert_eq!(ramp.at(5_000).unwrap(), 200);
+
+        let down = Amplification { initial: 200, target: 100, ..ramp };
+        assert_eq!(down.at(1_500).unwrap(), 150);
+        assert_eq!(Amplification::fixed(85).at(0).unwrap(), 85);
+        assert!(Amplification::fixed(0).at(0).is_err());
+    }
+}
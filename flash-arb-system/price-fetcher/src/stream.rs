@@ -0,0 +1,445 @@
+//! Streaming pool updates from account subscriptions.
+//!
+//! [`PoolTracker`] keeps the latest copy of every account the tracked pools
+//! depend on and re-decodes a pool whenever one of them changes. It does no
+//! I/O itself; [`follow_pool_updates`] feeds it from an [`AccountSubscriber`]
+//! such as a websocket `accountSubscribe` client and forwards the resulting
+//! updates.
+
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use futures::stream::{BoxStream, SelectAll, StreamExt};
+use rpc_manager::RpcBackend;
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::nonblocking::pubsub_client::PubsubClient;
+use solana_client::rpc_config::RpcAccountInfoConfig;
+use solana_sdk::account::Account;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::sysvar;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+use crate::error::PriceError;
+use crate::freshness::Observation;
+use crate::quoter::{AccountMap, DecodedPool, QuoterDispatcher};
+use crate::snapshot::{pack_batches, Snapshot};
+
+/// A tracked pool after one of its accounts changed
+#[derive(Debug, Clone)]
+pub struct PoolUpdate {
+    pub pool: Pubkey,
+    /// Slot of the change that triggered the update
+    pub slot: u64,
+    pub state: DecodedPool,
+    /// The pool account and every account needed to quote it, as of `slot`
+    pub accounts: AccountMap,
+}
+
+struct TrackedPool {
+    state: DecodedPool,
+    /// Accounts besides the pool itself that its quotes read
+    dependencies: Vec<Pubkey>,
+}
+
+/// Latest state of a set of pools, updated one account change at a time
+pub struct PoolTracker {
+    dispatcher: Arc<QuoterDispatcher>,
+    pools: HashMap<Pubkey, TrackedPool>,
+    /// Latest copy of every watched account and the slot it was seen at
+    accounts: HashMap<Pubkey, (u64, Account)>,
+}
+
+impl PoolTracker {
+    pub fn new(dispatcher: Arc<QuoterDispatcher>) -> Self {
+        Self {
+            dispatcher,
+            pools: HashMap::new(),
+            accounts: HashMap::new(),
+        }
+    }
+
+    /// Track every pool in `snapshot`, starting from its accounts. They are
+    /// taken to be as old as the snapshot's oldest batch.
+    pub fn from_snapshot(dispatcher: Arc<QuoterDispatcher>, snapshot: Snapshot) -> Self {
+        let mut tracker = Self::new(dispatcher);
+        let slot = snapshot.oldest_slot;
+        tracker.accounts.extend(snapshot.accounts.into_iter().map(|(key, account)| (key, (slot, account))));
+        for pool in snapshot.pools {
+            tracker.track(pool);
+        }
+        tracker
+    }
+
+    /// Start tracking a decoded pool. Its accounts are picked up from what
+    /// the tracker already holds and from later calls to [`apply`](Self::apply).
+    pub fn track(&mut self, pool: DecodedPool) {
+        let dependencies = self.dispatcher.quote_accounts(&pool).unwrap_or_default();
+        self.pools.insert(pool.address, TrackedPool { state: pool, dependencies });
+    }
+
+    pub fn untrack(&mut self, pool: &Pubkey) {
+        self.pools.remove(pool);
+        let watched = self.watched_accounts();
+        self.accounts.retain(|key, _| watched.contains(key));
+    }
+
+    /// Every account the tracked pools depend on, the pools included
+    pub fn watched_accounts(&self) -> HashSet<Pubkey> {
+        self.pools
+            .iter()
+            .flat_map(|(address, pool)| std::iter::once(*address).chain(pool.dependencies.iter().copied()))
+            .collect()
+    }
+
+    /// Accounts that are watched but whose contents have not been seen yet,
+    /// e.g. tick arrays a pool moved into
+    pub fn missing_accounts(&self) -> Vec<Pubkey> {
+        let mut missing: Vec<Pubkey> = self
+            .watched_accounts()
+            .into_iter()
+            .filter(|key| !self.accounts.contains_key(key))
+            .collect();
+        missing.sort();
+        missing
+    }
+
+    /// Record a change to `address` seen at `slot` and return an update for
+    /// every tracked pool that depends on it.
+    ///
+    /// Notifications older than the copy already held are ignored, as are
+    /// changes to accounts no pool depends on. A pool account that no
+    /// longer decodes keeps its previous state and yields no update. The
+    /// Clock changes every slot but only matters for its epoch, so it is
+    /// stored without producing updates.
+    pub fn apply(&mut self, address: Pubkey, account: Account, slot: u64) -> Vec<PoolUpdate> {
+        if self.accounts.get(&address).is_some_and(|(seen, _)| *seen > slot) {
+            return Vec::new();
+        }
+        if !self.watched_accounts().contains(&address) {
+            return Vec::new();
+        }
+        self.accounts.insert(address, (slot, account));
+        if address == sysvar::clock::ID {
+            return Vec::new();
+        }
+
+        if self.pools.contains_key(&address) {
+            let (_, account) = &self.accounts[&address];
+            match self.dispatcher.decode(&address, account) {
+                Ok(state) => self.track(state),
+                Err(_) => return Vec::new(),
+            }
+        }
+
+        let mut affected: Vec<Pubkey> = self
+            .pools
+            .iter()
+            .filter(|(pool, tracked)| **pool == address || tracked.dependencies.contains(&address))
+            .map(|(pool, _)| *pool)
+            .collect();
+        affected.sort();
+        affected.into_iter().map(|pool| self.update(pool, slot)).collect()
+    }
+
+    fn update(&self, pool: Pubkey, slot: u64) -> PoolUpdate {
+        let tracked = &self.pools[&pool];
+        let accounts = std::iter::once(&pool)
+            .chain(&tracked.dependencies)
+            .filter_map(|key| self.accounts.get(key).map(|(_, account)| (*key, account.clone())))
+            .collect();
+        PoolUpdate {
+            pool,
+            slot,
+            state: DecodedPool {
+                observed: Observation::received_now(slot),
+                ..tracked.state.clone()
+            },
+            accounts,
+        }
+    }
+}
+
+/// Cancels a subscription returned by [`AccountSubscriber::subscribe`]
+pub type Unsubscribe = Box<dyn FnOnce() -> BoxFuture<'static, ()> + Send>;
+
+/// Source of account change notifications, yielded as the slot of the
+/// change and the new account contents
+#[async_trait]
+pub trait AccountSubscriber: Send + Sync {
+    async fn subscribe<'a>(
+        &'a self,
+        address: &Pubkey,
+    ) -> Result<(BoxStream<'a, (u64, Account)>, Unsubscribe), PriceError>;
+}
+
+#[async_trait]
+impl AccountSubscriber for PubsubClient {
+    async fn subscribe<'a>(
+        &'a self,
+        address: &Pubkey,
+    ) -> Result<(BoxStream<'a, (u64, Account)>, Unsubscribe), PriceError> {
+        let (stream, unsubscribe) =
+            self.account_subscribe(address, Some(subscription_config())).await.map_err(anyhow::Error::from)?;
+        // Notifications that fail to decode are dropped; the next change
+        // to the account brings it up to date
+        let stream = stream
+            .filter_map(|response| async move {
+                let account = response.value.decode::<Account>()?;
+                Some((response.context.slot, account))
+            })
+            .boxed();
+        Ok((stream, unsubscribe))
+    }
+}
+
+/// Stream updates for `pools` into `updates` until the receiver is dropped,
+/// subscribing through the websocket endpoint `ws_url`.
+///
+/// See [`follow_pool_updates`]; returns an error when the websocket closes,
+/// so the caller can reconnect.
+pub async fn stream_pool_updates<B: RpcBackend + ?Sized>(
+    rpc: &B,
+    ws_url: &str,
+    dispatcher: Arc<QuoterDispatcher>,
+    pools: &[Pubkey],
+    updates: mpsc::UnboundedSender<PoolUpdate>,
+) -> Result<(), PriceError> {
+    let client = PubsubClient::new(ws_url).await.map_err(anyhow::Error::from)?;
+    follow_pool_updates(rpc, &client, dispatcher, pools, updates).await
+}
+
+/// Stream updates for `pools` into `updates` until the receiver is dropped.
+///
+/// Starts from a snapshot over `rpc`, subscribes to every account the pools
+/// depend on, and follows pools into new tick and bin arrays as their price
+/// moves. Only accounts the tracker has not seen are read over `rpc` after
+/// subscribing. Returns an error once every subscription has closed.
+pub async fn follow_pool_updates<B, S>(
+    rpc: &B,
+    subscriber: &S,
+    dispatcher: Arc<QuoterDispatcher>,
+    pools: &[Pubkey],
+    updates: mpsc::UnboundedSender<PoolUpdate>,
+) -> Result<(), PriceError>
+where
+    B: RpcBackend + ?Sized,
+    S: AccountSubscriber + ?Sized,
+{
+    let snapshot = dispatcher.fetch_snapshot(rpc, pools).await?;
+    let mut tracker = PoolTracker::from_snapshot(dispatcher, snapshot);
+
+    let mut streams: SelectAll<BoxStream<'_, (Pubkey, (u64, Account))>> = SelectAll::new();
+    let mut subscriptions: HashMap<Pubkey, Unsubscribe> = HashMap::new();
+
+    loop {
+        // Subscribe before reading so no change between the read and the
+        // subscription is missed. The read may come from a node behind the
+        // subscription's; `apply` keeps whichever copy is newer.
+        let watched = tracker.watched_accounts();
+        let added: HashSet<Pubkey> = watched.iter().filter(|key| !subscriptions.contains_key(key)).copied().collect();
+        for &key in &added {
+            let (stream, unsubscribe) = subscriber.subscribe(&key).await?;
+            streams.push(stream.map(move |notification| (key, notification)).boxed());
+            subscriptions.insert(key, unsubscribe);
+        }
+        // Accounts that do not exist stay missing, so only read those just
+        // subscribed to rather than retrying them on every notification
+        let unread = tracker.missing_accounts().into_iter().filter(|key| added.contains(key));
+        for batch in pack_batches(unread.map(|key| vec![key])) {
+            let (slot, fetched) = rpc.get_multiple_accounts_with_slot(&batch, None).await?;
+            for (key, account) in batch.into_iter().zip(fetched) {
+                let Some(account) = account else {
+                    continue;
+                };
+                for update in tracker.apply(key, account, slot) {
+                    if updates.send(update).is_err() {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+        let removed: Vec<Pubkey> = subscriptions.keys().filter(|key| !watched.contains(key)).copied().collect();
+        for key in removed {
+            if let Some(unsubscribe) = subscriptions.remove(&key) {
+                unsubscribe().await;
+            }
+        }
+
+        let Some((key, (slot, account))) = streams.next().await else {
+            return Err(anyhow::anyhow!("Account subscriptions closed").into());
+        };
+        for update in tracker.apply(key, account, slot) {
+            if updates.send(update).is_err() {
+                return Ok(());
+            }
+        }
+    }
+}
+
+fn subscription_config() -> RpcAccountInfoConfig {
+    RpcAccountInfoConfig {
+        encoding: Some(UiAccountEncoding::Base64Zstd),
+        commitment: Some(CommitmentConfig::confirmed()),
+        ..RpcAccountInfoConfig::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raydium::{AMM_V4_STATE_SIZE, RAYDIUM_V4_PROGRAM_ID};
+    use crate::test_utils::token_account;
+    use futures::channel::mpsc as channel;
+    use parking_lot::Mutex;
+    use rpc_manager::InMemoryBackend;
+
+    const BASE_VAULT_OFFSET: usize = 336;
+    const QUOTE_VAULT_OFFSET: usize = 368;
+
+    fn v4_pool(base_vault: &Pubkey, quote_vault: &Pubkey) -> Account {
+        let mut data = vec![0u8; AMM_V4_STATE_SIZE];
+        data[BASE_VAULT_OFFSET..BASE_VAULT_OFFSET + 32].copy_from_slice(base_vault.as_ref());
+        data[QUOTE_VAULT_OFFSET..QUOTE_VAULT_OFFSET + 32].copy_from_slice(quote_vault.as_ref());
+        Account {
+            data,
+            owner: RAYDIUM_V4_PROGRAM_ID,
+            ..Account::default()
+        }
+    }
+
+    fn amount(update: &PoolUpdate, vault: &Pubkey) -> u64 {
+        u64::from_le_bytes(update.accounts[vault].data[64..72].try_into().unwrap())
+    }
+
+    type Senders = HashMap<Pubkey, channel::UnboundedSender<(u64, Account)>>;
+
+    /// Subscriptions backed by channels the test pushes notifications into
+    #[derive(Default)]
+    struct ChannelSubscriber {
+        senders: Arc<Mutex<Senders>>,
+    }
+
+    impl ChannelSubscriber {
+        fn is_subscribed(&self, address: &Pubkey) -> bool {
+            self.senders.lock().contains_key(address)
+        }
+
+        fn notify(&self, address: &Pubkey, slot: u64, account: Account) {
+            self.senders.lock()[address].unbounded_send((slot, account)).unwrap();
+        }
+
+        async fn wait_for_subscription(&self, address: &Pubkey) {
+            while !self.is_subscribed(address) {
+                tokio::task::yield_now().await;
+            }
+        }
+    }
+
+    #[async_trait]
+    impl AccountSubscriber for ChannelSubscriber {
+        async fn subscribe<'a>(
+            &'a self,
+            address: &Pubkey,
+        ) -> Result<(BoxStream<'a, (u64, Account)>, Unsubscribe), PriceError> {
+            let (sender, receiver) = channel::unbounded();
+            self.senders.lock().insert(*address, sender);
+            let senders = self.senders.clone();
+            let address = *address;
+            let unsubscribe: Unsubscribe = Box::new(move || {
+                senders.lock().remove(&address);
+                Box::pin(async {})
+            });
+            Ok((receiver.boxed(), unsubscribe))
+        }
+    }
+
+    #[test]
+    fn test_tracker_emits_updates_for_dependents() {
+        let dispatcher = Arc::new(QuoterDispatcher::new());
+        let (pool, base_vault, quote_vault) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let pool_account = v4_pool(&base_vault, &quote_vault);
+        let snapshot = Snapshot {
+            oldest_slot: 100,
+            newest_slot: 100,
+            pools: vec![dispatcher.decode(&pool, &pool_account).unwrap()],
+            accounts: AccountMap::from([(pool, pool_account), (base_vault, token_account(1_000)), (quote_vault, token_account(2_000))]),
+            failed: Vec::new(),
+        };
+        let mut tracker = PoolTracker::from_snapshot(dispatcher, snapshot);
+        assert!(tracker.watched_accounts().contains(&base_vault));
+
+        let updates = tracker.apply(base_vault, token_account(1_500), 101);
+        assert_eq!(updates.len(), 1);
+        assert_eq!((updates[0].pool, updates[0].slot), (pool, 101));
+        assert_eq!(updates[0].state.observed.slot, 101);
+        assert_eq!(amount(&updates[0], &base_vault), 1_500);
+
+        // Older notifications and unrelated accounts are ignored
+        assert!(tracker.apply(base_vault, token_account(900), 100).is_empty());
+        assert!(tracker.apply(Pubkey::new_unique(), token_account(1), 102).is_empty());
+    }
+
+    #[test]
+    fn test_tracker_follows_pool_into_new_dependencies() {
+        let dispatcher = Arc::new(QuoterDispatcher::new());
+        let (pool, base_vault, quote_vault) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut tracker = PoolTracker::new(dispatcher.clone());
+        let pool_account = v4_pool(&base_vault, &quote_vault);
+        let state = dispatcher.decode(&pool, &pool_account).unwrap();
+        tracker.accounts.insert(pool, (10, pool_account));
+        tracker.track(state);
+
+        let new_vault = Pubkey::new_unique();
+        let updates = tracker.apply(pool, v4_pool(&new_vault, &quote_vault), 11);
+        assert_eq!(updates.len(), 1);
+        let watched = tracker.watched_accounts();
+        assert!(watched.contains(&new_vault) && !watched.contains(&base_vault));
+        assert!(tracker.missing_accounts().contains(&new_vault));
+
+        tracker.untrack(&pool);
+        assert!(tracker.watched_accounts().is_empty());
+        assert!(tracker.apply(quote_vault, token_account(1), 12).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_follow_pool_updates_loop() {
+        let (pool, base_vault, quote_vault, new_vault) =
+            (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let backend = InMemoryBackend::new();
+        backend.set_slot(100);
+        backend.set_account(pool, v4_pool(&base_vault, &quote_vault));
+        backend.set_account(base_vault, token_account(1_000));
+        backend.set_account(quote_vault, token_account(2_000));
+        backend.set_account(new_vault, token_account(3_000));
+
+        let subscriber = ChannelSubscriber::default();
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let dispatcher = Arc::new(QuoterDispatcher::new());
+        let pools = [pool];
+        let follow = follow_pool_updates(&backend, &subscriber, dispatcher, &pools, sender);
+
+        let drive = async {
+            subscriber.wait_for_subscription(&base_vault).await;
+            // The snapshot already holds every existing account, so the first
+            // update comes from a notification rather than a startup re-read
+            subscriber.notify(&base_vault, 101, token_account(1_500));
+            let update = receiver.recv().await.unwrap();
+            assert_eq!((update.pool, update.slot, amount(&update, &base_vault)), (pool, 101, 1_500));
+
+            // Moving to a new vault updates the pool, then reads the new vault
+            // once it is subscribed and drops the old subscription
+            subscriber.notify(&pool, 102, v4_pool(&new_vault, &quote_vault));
+            let update = receiver.recv().await.unwrap();
+            assert_eq!((update.slot, update.accounts.contains_key(&new_vault)), (102, false));
+            let update = receiver.recv().await.unwrap();
+            assert_eq!((update.slot, amount(&update, &new_vault)), (100, 3_000));
+            assert!(subscriber.is_subscribed(&new_vault) && !subscriber.is_subscribed(&base_vault));
+
+            subscriber.senders.lock().clear();
+        };
+        let (result, ()) = tokio::join!(follow, drive);
+        assert!(result.is_err());
+    }
+}
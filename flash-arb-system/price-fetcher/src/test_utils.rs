@@ -0,0 +1,44 @@
+//! Account image builders shared by the decoder and quoter tests
+
+use solana_sdk::{account::Account, pubkey::Pubkey};
+
+use crate::token::SPL_TOKEN_PROGRAM_ID;
+
+pub(crate) fn put_u32(data: &mut [u8], offset: usize, value: u32) {
+    data[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+pub(crate) fn put_u64(data: &mut [u8], offset: usize, value: u64) {
+    data[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+}
+
+pub(crate) fn put_pubkey(data: &mut [u8], offset: usize, key: &Pubkey) {
+    data[offset..offset + 32].copy_from_slice(key.as_ref());
+}
+
+/// Initialized SPL token account holding `amount` of an unspecified mint
+pub(crate) fn token_account(amount: u64) -> Account {
+    token_account_of(&Pubkey::default(), amount)
+}
+
+/// Initialized SPL token account holding `amount` of `mint`
+pub(crate) fn token_account_of(mint: &Pubkey, amount: u64) -> Account {
+    build_token_account(SPL_TOKEN_PROGRAM_ID, mint, amount)
+}
+
+/// Initialized token account owned by `token_program`, e.g. Token-2022
+pub(crate) fn token_account_in(token_program: Pubkey, amount: u64) -> Account {
+    build_token_account(token_program, &Pubkey::default(), amount)
+}
+
+fn build_token_account(token_program: Pubkey, mint: &Pubkey, amount: u64) -> Account {
+    let mut data = vec![0u8; 165];
+    put_pubkey(&mut data, 0, mint);
+    put_u64(&mut data, 64, amount);
+    data[108] = 1;
+    Account {
+        data,
+        owner: token_program,
+        ..Account::default()
+    }
+}
@@ -0,0 +1,503 @@
+use anyhow::Result;
+use parking_lot::RwLock;
+use rpc_manager::RpcBackend;
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+
+use crate::error::PriceError;
+use crate::quoter::AccountMap;
+
+pub const SPL_TOKEN_PROGRAM_ID: Pubkey = solana_sdk::pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+pub const TOKEN_2022_PROGRAM_ID: Pubkey = solana_sdk::pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+
+/// Size of the base SPL mint layout shared by both token programs
+const MINT_BASE_SIZE: usize = 82;
+const MINT_DECIMALS_OFFSET: usize = 44;
+const MINT_INITIALIZED_OFFSET: usize = 45;
+const MINT_SUPPLY_OFFSET: usize = 36;
+
+/// Size of the base SPL token account layout shared by both token programs
+const TOKEN_ACCOUNT_SIZE: usize = 165;
+const TOKEN_ACCOUNT_STATE_OFFSET: usize = 108;
+
+/// Token-2022 mints and accounts with extensions are padded to the token
+/// account size, then carry an account-type byte followed by TLV entries
+const ACCOUNT_TYPE_OFFSET: usize = TOKEN_ACCOUNT_SIZE;
+const TLV_START: usize = ACCOUNT_TYPE_OFFSET + 1;
+const ACCOUNT_TYPE_MINT: u8 = 1;
+const ACCOUNT_TYPE_ACCOUNT: u8 = 2;
+
+const EXTENSION_UNINITIALIZED: u16 = 0;
+const EXTENSION_TRANSFER_FEE_CONFIG: u16 = 1;
+const EXTENSION_INTEREST_BEARING_CONFIG: u16 = 10;
+const EXTENSION_TRANSFER_HOOK: u16 = 14;
+
+const ONE_IN_BASIS_POINTS: u128 = 10_000;
+
+fn is_token_program(program_id: &Pubkey) -> bool {
+    *program_id == SPL_TOKEN_PROGRAM_ID || *program_id == TOKEN_2022_PROGRAM_ID
+}
+
+/// Check the length and account-type byte of an account owned by either
+/// token program. Only Token-2022 accounts may extend past the base size.
+fn check_layout(data: &[u8], owner: &Pubkey, base_size: usize, account_type: u8) -> Result<()> {
+    if data.len() < base_size {
+        anyhow::bail!("Token account data is {} bytes, expected at least {}", data.len(), base_size);
+    }
+    if data.len() == base_size {
+        return Ok(());
+    }
+    if *owner != TOKEN_2022_PROGRAM_ID {
+        anyhow::bail!("SPL Token account has unexpected length {}", data.len());
+    }
+    if data.len() <= ACCOUNT_TYPE_OFFSET || data[ACCOUNT_TYPE_OFFSET] != account_type {
+        anyhow::bail!("Token-2022 account is not of type {}", account_type);
+    }
+    Ok(())
+}
+
+/// Iterate Token-2022 TLV extension entries as (type, value)
+fn extensions(data: &[u8]) -> impl Iterator<Item = (u16, &[u8])> {
+    let mut offset = TLV_START;
+    std::iter::from_fn(move || {
+        let header = data.get(offset..offset + 4)?;
+        let extension_type = u16::from_le_bytes([header[0], header[1]]);
+        let length = u16::from_le_bytes([header[2], header[3]]) as usize;
+        if extension_type == EXTENSION_UNINITIALIZED {
+            return None;
+        }
+        let value = data.get(offset + 4..offset + 4 + length)?;
+        offset += 4 + length;
+        Some((extension_type, value))
+    })
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(data[offset..offset + 2].try_into().unwrap())
+}
+
+/// SPL token account fields needed for pricing, from either token program
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenAccount {
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+}
+
+impl TokenAccount {
+    pub fn from_account(address: &Pubkey, account: &Account) -> Result<Self, PriceError> {
+        if !is_token_program(&account.owner) {
+            return Err(PriceError::WrongOwner {
+                account: *address,
+                owner: account.owner,
+            });
+        }
+        check_layout(&account.data, &account.owner, TOKEN_ACCOUNT_SIZE, ACCOUNT_TYPE_ACCOUNT)
+            .map_err(|err| PriceError::layout(*address, err))?;
+        if account.data[TOKEN_ACCOUNT_STATE_OFFSET] == 0 {
+            return Err(PriceError::layout(*address, "token account is not initialized"));
+        }
+        let data = &account.data;
+        Ok(Self {
+            mint: Pubkey::new_from_array(data[0..32].try_into().unwrap()),
+            owner: Pubkey::new_from_array(data[32..64].try_into().unwrap()),
+            amount: read_u64(data, 64),
+        })
+    }
+}
+
+/// Amount held by a fetched token account of either token program
+pub(crate) fn vault_amount(accounts: &AccountMap, vault: &Pubkey) -> Result<u64, PriceError> {
+    let account = accounts.get(vault).ok_or(PriceError::AccountMissing(*vault))?;
+    Ok(TokenAccount::from_account(vault, account)?.amount)
+}
+
+/// Total supply of an SPL mint
+pub(crate) fn mint_supply(account: &Account) -> Option<u64> {
+    let supply_bytes: [u8; 8] = account.data.get(MINT_SUPPLY_OFFSET..MINT_SUPPLY_OFFSET + 8)?.try_into().ok()?;
+    Some(u64::from_le_bytes(supply_bytes))
+}
+
+/// One epoch's transfer fee schedule
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TransferFee {
+    /// First epoch this fee applies in
+    pub epoch: u64,
+    pub maximum_fee: u64,
+    pub transfer_fee_basis_points: u16,
+}
+
+impl TransferFee {
+    fn from_bytes(data: &[u8]) -> Self {
+        Self {
+            epoch: read_u64(data, 0),
+            maximum_fee: read_u64(data, 8),
+            transfer_fee_basis_points: read_u16(data, 16),
+        }
+    }
+
+    /// Fee withheld when `amount` is transferred, rounded up and capped
+    pub fn calculate_fee(&self, amount: u64) -> u64 {
+        let basis_points = self.transfer_fee_basis_points as u128;
+        if basis_points == 0 || amount == 0 {
+            return 0;
+        }
+        let fee = (amount as u128 * basis_points).div_ceil(ONE_IN_BASIS_POINTS);
+        fee.min(self.maximum_fee as u128) as u64
+    }
+
+    /// Amount to send so that `post_fee_amount` arrives, as computed by the
+    /// Token-2022 program
+    pub fn calculate_pre_fee_amount(&self, post_fee_amount: u64) -> Option<u64> {
+        let basis_points = self.transfer_fee_basis_points as u128;
+        match (basis_points, post_fee_amount) {
+            (0, _) => Some(post_fee_amount),
+            (_, 0) => Some(0),
+            (ONE_IN_BASIS_POINTS, _) => self.maximum_fee.checked_add(post_fee_amount),
+            _ => {
+                let raw = (post_fee_amount as u128 * ONE_IN_BASIS_POINTS)
+                    .div_ceil(ONE_IN_BASIS_POINTS.checked_sub(basis_points)?);
+                if raw - post_fee_amount as u128 >= self.maximum_fee as u128 {
+                    post_fee_amount.checked_add(self.maximum_fee)
+                } else {
+                    u64::try_from(raw).ok()
+                }
+            }
+        }
+    }
+}
+
+/// Token-2022 `TransferFeeConfig` mint extension
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TransferFeeConfig {
+    pub older_transfer_fee: TransferFee,
+    pub newer_transfer_fee: TransferFee,
+}
+
+impl TransferFeeConfig {
+    /// Authorities (2 x 32) and withheld amount precede the fee schedules
+    fn from_bytes(data: &[u8]) -> Result<Self> {
+        if data.len() != 108 {
+            anyhow::bail!("TransferFeeConfig extension is {} bytes", data.len());
+        }
+        Ok(Self {
+            older_transfer_fee: TransferFee::from_bytes(&data[72..90]),
+            newer_transfer_fee: TransferFee::from_bytes(&data[90..108]),
+        })
+    }
+
+    pub fn epoch_fee(&self, epoch: u64) -> &TransferFee {
+        if epoch >= self.newer_transfer_fee.epoch {
+            &self.newer_transfer_fee
+        } else {
+            &self.older_transfer_fee
+        }
+    }
+}
+
+/// Token-2022 `InterestBearingConfig` mint extension. Interest only changes
+/// the UI amount; raw balances and swap amounts are unaffected.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InterestBearingConfig {
+    pub initialization_timestamp: i64,
+    pub pre_update_average_rate: i16,
+    pub last_update_timestamp: i64,
+    /// Current rate in basis points per year
+    pub current_rate: i16,
+}
+
+impl InterestBearingConfig {
+    fn from_bytes(data: &[u8]) -> Result<Self> {
+        if data.len() != 52 {
+            anyhow::bail!("InterestBearingConfig extension is {} bytes", data.len());
+        }
+        Ok(Self {
+            initialization_timestamp: read_u64(data, 32) as i64,
+            pre_update_average_rate: read_u16(data, 40) as i16,
+            last_update_timestamp: read_u64(data, 42) as i64,
+            current_rate: read_u16(data, 50) as i16,
+        })
+    }
+}
+
+/// Token-2022 mint extensions that affect pricing or execution
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MintExtensions {
+    pub transfer_fee: Option<TransferFeeConfig>,
+    pub interest_bearing: Option<InterestBearingConfig>,
+    /// Program invoked on every transfer, which quotes cannot simulate
+    pub transfer_hook_program: Option<Pubkey>,
+}
+
+impl MintExtensions {
+    fn from_mint_data(data: &[u8]) -> Result<Self> {
+        let mut parsed = Self::default();
+        for (extension_type, value) in extensions(data) {
+            match extension_type {
+                EXTENSION_TRANSFER_FEE_CONFIG => parsed.transfer_fee = Some(TransferFeeConfig::from_bytes(value)?),
+                EXTENSION_INTEREST_BEARING_CONFIG => {
+                    parsed.interest_bearing = Some(InterestBearingConfig::from_bytes(value)?)
+                }
+                EXTENSION_TRANSFER_HOOK => {
+                    let program_id = value
+                        .get(32..64)
+                        .ok_or_else(|| anyhow::anyhow!("TransferHook extension is {} bytes", value.len()))?;
+                    let program_id = Pubkey::new_from_array(program_id.try_into().unwrap());
+                    parsed.transfer_hook_program = (program_id != Pubkey::default()).then_some(program_id);
+                }
+                _ => {}
+            }
+        }
+        Ok(parsed)
+    }
+
+    /// Transfer fee withheld from `amount` in `epoch`
+    pub fn transfer_fee(&self, epoch: u64, amount: u64) -> u64 {
+        self.transfer_fee
+            .map(|config| config.epoch_fee(epoch).calculate_fee(amount))
+            .unwrap_or(0)
+    }
+
+    /// Amount to transfer in `epoch` so that `amount` arrives, or `None` if
+    /// that overflows
+    pub fn pre_fee_amount(&self, epoch: u64, amount: u64) -> Option<u64> {
+        match self.transfer_fee {
+            Some(config) => config.epoch_fee(epoch).calculate_pre_fee_amount(amount),
+            None => Some(amount),
+        }
+    }
+}
+
+/// Mint metadata needed for pricing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MintInfo {
+    pub decimals: u8,
+    /// SPL Token or Token-2022
+    pub token_program: Pubkey,
+    pub extensions: MintExtensions,
+}
+
+impl MintInfo {
+    /// Parse a mint account owned by either token program
+    pub fn from_account(mint: &Pubkey, account: &Account) -> Result<Self, PriceError> {
+        if !is_token_program(&account.owner) {
+            return Err(PriceError::WrongOwner {
+                account: *mint,
+                owner: account.owner,
+            });
+        }
+        if account.data.len() < MINT_BASE_SIZE || account.data[MINT_INITIALIZED_OFFSET] != 1 {
+            return Err(PriceError::layout(*mint, "not an initialized mint"));
+        }
+        // Extended mints are padded to the token account size before the type byte
+        let extensions = if account.data.len() > MINT_BASE_SIZE {
+            check_layout(&account.data, &account.owner, TOKEN_ACCOUNT_SIZE, ACCOUNT_TYPE_MINT)
+                .and_then(|()| MintExtensions::from_mint_data(&account.data))
+                .map_err(|err| PriceError::layout(*mint, err))?
+        } else {
+            MintExtensions::default()
+        };
+
+        Ok(Self {
+            decimals: account.data[MINT_DECIMALS_OFFSET],
+            token_program: account.owner,
+            extensions,
+        })
+    }
+}
+
+/// Cache of mint metadata. Mint decimals never change, so entries are
+/// fetched once and kept for the lifetime of the registry.
+#[derive(Default)]
+pub struct MintRegistry {
+    mints: RwLock<HashMap<Pubkey, MintInfo>>,
+}
+
+impl MintRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Seed a mint whose metadata is already known
+    pub fn insert(&self, mint: Pubkey, info: MintInfo) {
+        self.mints.write().insert(mint, info);
+    }
+
+    pub fn get(&self, mint: &Pubkey) -> Option<MintInfo> {
+        self.mints.read().get(mint).copied()
+    }
+
+    /// Resolve metadata for `mints`, fetching any unknown ones in a single
+    /// batch. Results are returned in the same order as `mints`.
+    pub async fn resolve<B: RpcBackend + ?Sized>(&self, rpc: &B, mints: &[Pubkey]) -> Result<Vec<MintInfo>, PriceError> {
+        let missing: Vec<Pubkey> = {
+            let cache = self.mints.read();
+            let mut missing: Vec<Pubkey> = mints.iter().filter(|m| !cache.contains_key(m)).copied().collect();
+            missing.sort();
+            missing.dedup();
+            missing
+        };
+
+        if !missing.is_empty() {
+            let accounts = rpc.get_multiple_accounts(&missing).await?;
+            for (mint, account) in missing.iter().zip(accounts) {
+                let account = account.ok_or(PriceError::DecimalsUnknown(*mint))?;
+                self.insert(*mint, MintInfo::from_account(mint, &account)?);
+            }
+        }
+
+        let cache = self.mints.read();
+        mints
+            .iter()
+            .map(|mint| {
+                cache.get(mint).copied().ok_or(PriceError::DecimalsUnknown(*mint))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rpc_manager::InMemoryBackend;
+
+    fn mint_account(decimals: u8, token_program: Pubkey) -> Account {
+        let mut data = vec![0u8; MINT_BASE_SIZE];
+        data[MINT_DECIMALS_OFFSET] = decimals;
+        data[MINT_INITIALIZED_OFFSET] = 1;
+        Account {
+            data,
+            owner: token_program,
+            ..Account::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolve_and_cache() {
+        let backend = InMemoryBackend::new();
+        let sol = Pubkey::new_unique();
+        let pyusd = Pubkey::new_unique();
+        backend.set_account(sol, mint_account(9, SPL_TOKEN_PROGRAM_ID));
+        backend.set_account(pyusd, mint_account(6, TOKEN_2022_PROGRAM_ID));
+
+        let registry = MintRegistry::new();
+        let infos = registry.resolve(&backend, &[sol, pyusd]).await.unwrap();
+        assert_eq!(infos[0].decimals, 9);
+        assert_eq!(infos[1].decimals, 6);
+        assert_eq!(infos[1].token_program, TOKEN_2022_PROGRAM_ID);
+
+        // Served from cache once resolved
+        backend.remove_account(&sol);
+        assert_eq!(registry.resolve(&backend, &[sol]).await.unwrap()[0].decimals, 9);
+    }
+
+    #[test]
+    fn test_rejects_non_mint() {
+        let mint = Pubkey::new_unique();
+        assert!(MintInfo::from_account(&mint, &mint_account(6, Pubkey::new_unique())).is_err());
+
+        let mut uninitialized = mint_account(6, SPL_TOKEN_PROGRAM_ID);
+        uninitialized.data[MINT_INITIALIZED_OFFSET] = 0;
+        assert!(MintInfo::from_account(&mint, &uninitialized).is_err());
+    }
+
+    fn put_extension(data: &mut Vec<u8>, extension_type: u16, value: &[u8]) {
+        data.extend_from_slice(&extension_type.to_le_bytes());
+        data.extend_from_slice(&(value.len() as u16).to_le_bytes());
+        data.extend_from_slice(value);
+    }
+
+    fn transfer_fee_bytes(epoch: u64, maximum_fee: u64, basis_points: u16) -> Vec<u8> {
+        [&epoch.to_le_bytes()[..], &maximum_fee.to_le_bytes(), &basis_points.to_le_bytes()].concat()
+    }
+
+    #[test]
+    fn test_mint_extensions() {
+        let mut account = mint_account(6, TOKEN_2022_PROGRAM_ID);
+        account.data.resize(TOKEN_ACCOUNT_SIZE, 0);
+        account.data.push(ACCOUNT_TYPE_MINT);
+
+        let mut fee_config = vec![0u8; 72];
+        fee_config.extend(transfer_fee_bytes(500, 1_000, 50));
+        fee_config.extend(transfer_fee_bytes(600, 2_000, 100));
+        put_extension(&mut account.data, EXTENSION_TRANSFER_FEE_CONFIG, &fee_config);
+
+        let mut interest = vec![0u8; 32];
+        interest.extend(1_700_000_000i64.to_le_bytes());
+        interest.extend(250i16.to_le_bytes());
+        interest.extend(1_710_000_000i64.to_le_bytes());
+        interest.extend(300i16.to_le_bytes());
+        put_extension(&mut account.data, EXTENSION_INTEREST_BEARING_CONFIG, &interest);
+
+        let hook_program = Pubkey::new_unique();
+        put_extension(&mut account.data, EXTENSION_TRANSFER_HOOK, &[[0u8; 32], hook_program.to_bytes()].concat());
+
+        let mint = Pubkey::new_unique();
+        let extensions = MintInfo::from_account(&mint, &account).unwrap().extensions;
+        assert_eq!(extensions.interest_bearing.unwrap().current_rate, 300);
+        assert_eq!(extensions.transfer_hook_program, Some(hook_program));
+
+        // The newer schedule takes over from its epoch; fees round up and are capped
+        assert_eq!(extensions.transfer_fee(599, 10_001), 51);
+        assert_eq!(extensions.transfer_fee(600, 10_001), 101);
+        assert_eq!(extensions.transfer_fee(600, 1_000_000), 2_000);
+
+        // Extended data behind an SPL Token mint is rejected
+        account.owner = SPL_TOKEN_PROGRAM_ID;
+        assert!(MintInfo::from_account(&mint, &account).is_err());
+    }
+
+    #[test]
+    fn test_transfer_fee_inverse() {
+        let fee = TransferFee {
+            epoch: 0,
+            maximum_fee: 5_000,
+            transfer_fee_basis_points: 100,
+        };
+        for post_fee_amount in [0, 1, 99, 100, 12_345, 1_000_000] {
+            let pre_fee_amount = fee.calculate_pre_fee_amount(post_fee_amount).unwrap();
+            assert_eq!(pre_fee_amount - fee.calculate_fee(pre_fee_amount), post_fee_amount);
+        }
+        assert_eq!(fee.calculate_pre_fee_amount(10_000_000), Some(10_005_000));
+    }
+
+    #[test]
+    fn test_token_account_layouts() {
+        let mint = Pubkey::new_unique();
+        let mut account = Account {
+            data: vec![0u8; TOKEN_ACCOUNT_SIZE],
+            owner: SPL_TOKEN_PROGRAM_ID,
+            ..Account::default()
+        };
+        account.data[..32].copy_from_slice(mint.as_ref());
+        account.data[64..72].copy_from_slice(&42u64.to_le_bytes());
+        let vault = Pubkey::new_unique();
+        let mut accounts = AccountMap::new();
+        assert!(matches!(vault_amount(&accounts, &vault), Err(PriceError::AccountMissing(_))));
+        accounts.insert(vault, account.clone());
+        assert!(matches!(vault_amount(&accounts, &vault), Err(PriceError::LayoutMismatch { .. })));
+
+        account.data[TOKEN_ACCOUNT_STATE_OFFSET] = 1;
+        let token = TokenAccount::from_account(&vault, &account).unwrap();
+        assert_eq!((token.mint, token.amount), (mint, 42));
+
+        // Token-2022 accounts with extensions carry the account type byte
+        account.data.push(ACCOUNT_TYPE_ACCOUNT);
+        assert!(TokenAccount::from_account(&vault, &account).is_err());
+        account.owner = TOKEN_2022_PROGRAM_ID;
+        assert_eq!(TokenAccount::from_account(&vault, &account).unwrap().amount, 42);
+        account.data[ACCOUNT_TYPE_OFFSET] = ACCOUNT_TYPE_MINT;
+        assert!(TokenAccount::from_account(&vault, &account).is_err());
+
+        account.owner = Pubkey::new_unique();
+        assert!(matches!(
+            TokenAccount::from_account(&vault, &account),
+            Err(PriceError::WrongOwner { .. })
+        ));
+    }
+}
@@ -0,0 +1,337 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use parking_lot::{Mutex, RwLock};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use solana_client::rpc_filter::RpcFilterType;
+use solana_sdk::account::{Account, AccountSharedData};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::VersionedTransaction;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use crate::{RpcConsumer, RpcManager};
+
+/// The subset of RPC operations our crates rely on.
+///
+/// Consumers should be generic over this trait instead of taking a concrete
+/// `RpcClient`, so they can run against the live cluster, the load-balanced
+/// `RpcManager` or an `InMemoryBackend` in tests.
+#[async_trait]
+pub trait RpcBackend: Send + Sync {
+    /// Fetch a batch of accounts. Missing accounts are returned as `None`.
+    async fn get_multiple_accounts(&self, pubkeys: &[Pubkey]) -> Result<Vec<Option<Account>>>;
+
+    /// Fetch a batch of accounts along with the slot they were read at.
+    /// With `min_context_slot` set, the node fails the request rather than
+    /// answer from an earlier slot.
+    async fn get_multiple_accounts_with_slot(
+        &self,
+        pubkeys: &[Pubkey],
+        min_context_slot: Option<u64>,
+    ) -> Result<(u64, Vec<Option<Account>>)>;
+
+    /// Every account owned by `program_id` that passes all `filters`
+    /// (`getProgramAccounts`). Unfiltered scans of large programs are
+    /// expensive and often rejected by providers; always narrow with a
+    /// data size or memcmp filter.
+    async fn get_program_accounts(
+        &self,
+        program_id: &Pubkey,
+        filters: &[RpcFilterType],
+    ) -> Result<Vec<(Pubkey, Account)>>;
+
+    /// Current slot at the backend's commitment level
+    async fn get_slot(&self) -> Result<u64>;
+
+    /// Submit a signed transaction, returning its signature
+    async fn send_transaction(&self, transaction: &VersionedTransaction) -> Result<Signature>;
+
+    /// Fetch a single account, failing if it does not exist
+    async fn get_account(&self, pubkey: &Pubkey) -> Result<Account> {
+        self.get_multiple_accounts(std::slice::from_ref(pubkey))
+            .await?
+            .pop()
+            .flatten()
+            .ok_or_else(|| anyhow::anyhow!("Account {} not found", pubkey))
+    }
+}
+
+#[async_trait]
+impl RpcBackend for RpcClient {
+    async fn get_multiple_accounts(&self, pubkeys: &[Pubkey]) -> Result<Vec<Option<Account>>> {
+        Ok(RpcClient::get_multiple_accounts(self, pubkeys).await?)
+    }
+
+    async fn get_multiple_accounts_with_slot(
+        &self,
+        pubkeys: &[Pubkey],
+        min_context_slot: Option<u64>,
+    ) -> Result<(u64, Vec<Option<Account>>)> {
+        let config = RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64Zstd),
+            commitment: Some(self.commitment()),
+            min_context_slot,
+            ..RpcAccountInfoConfig::default()
+        };
+        let response = RpcClient::get_multiple_accounts_with_config(self, pubkeys, config).await?;
+        Ok((response.context.slot, response.value))
+    }
+
+    async fn get_program_accounts(
+        &self,
+        program_id: &Pubkey,
+        filters: &[RpcFilterType],
+    ) -> Result<Vec<(Pubkey, Account)>> {
+        let config = RpcProgramAccountsConfig {
+            filters: Some(filters.to_vec()),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64Zstd),
+                ..RpcAccountInfoConfig::default()
+            },
+            ..RpcProgramAccountsConfig::default()
+        };
+        Ok(RpcClient::get_program_accounts_with_config(self, program_id, config).await?)
+    }
+
+    async fn get_slot(&self) -> Result<u64> {
+        Ok(RpcClient::get_slot(self).await?)
+    }
+
+    async fn send_transaction(&self, transaction: &VersionedTransaction) -> Result<Signature> {
+        Ok(RpcClient::send_transaction(self, transaction).await?)
+    }
+
+    async fn get_account(&self, pubkey: &Pubkey) -> Result<Account> {
+        Ok(RpcClient::get_account(self, pubkey).await?)
+    }
+}
+
+/// Routes every call through the next healthy endpoint and reports the
+/// outcome back to the health tracker.
+#[async_trait]
+impl RpcBackend for RpcManager {
+    async fn get_multiple_accounts(&self, pubkeys: &[Pubkey]) -> Result<Vec<Option<Account>>> {
+        let client = self.get_client()?;
+        let result = RpcBackend::get_multiple_accounts(&client, pubkeys).await;
+        self.record_outcome(&client.url(), &result);
+        result
+    }
+
+    async fn get_multiple_accounts_with_slot(
+        &self,
+        pubkeys: &[Pubkey],
+        min_context_slot: Option<u64>,
+    ) -> Result<(u64, Vec<Option<Account>>)> {
+        let client = self.get_client()?;
+        let result = RpcBackend::get_multiple_accounts_with_slot(&client, pubkeys, min_context_slot).await;
+        self.record_outcome(&client.url(), &result);
+        result
+    }
+
+    async fn get_program_accounts(
+        &self,
+        program_id: &Pubkey,
+        filters: &[RpcFilterType],
+    ) -> Result<Vec<(Pubkey, Account)>> {
+        let client = self.get_client()?;
+        let result = RpcBackend::get_program_accounts(&client, program_id, filters).await;
+        self.record_outcome(&client.url(), &result);
+        result
+    }
+
+    async fn get_slot(&self) -> Result<u64> {
+        let client = self.get_client()?;
+        let result = RpcBackend::get_slot(&client).await;
+        self.record_outcome(&client.url(), &result);
+        result
+    }
+
+    async fn send_transaction(&self, transaction: &VersionedTransaction) -> Result<Signature> {
+        let client = self.get_client()?;
+        let result = RpcBackend::send_transaction(&client, transaction).await;
+        self.record_outcome(&client.url(), &result);
+        result
+    }
+}
+
+/// Same as `RpcManager`, but charged to the consumer's budget
+#[async_trait]
+impl RpcBackend for RpcConsumer {
+    async fn get_multiple_accounts(&self, pubkeys: &[Pubkey]) -> Result<Vec<Option<Account>>> {
+        let client = self.manager.get_client_for(&self.name)?;
+        let result = RpcBackend::get_multiple_accounts(&client, pubkeys).await;
+        self.manager.record_outcome(&client.url(), &result);
+        result
+    }
+
+    async fn get_multiple_accounts_with_slot(
+        &self,
+        pubkeys: &[Pubkey],
+        min_context_slot: Option<u64>,
+    ) -> Result<(u64, Vec<Option<Account>>)> {
+        let client = self.manager.get_client_for(&self.name)?;
+        let result = RpcBackend::get_multiple_accounts_with_slot(&client, pubkeys, min_context_slot).await;
+        self.manager.record_outcome(&client.url(), &result);
+        result
+    }
+
+    async fn get_program_accounts(
+        &self,
+        program_id: &Pubkey,
+        filters: &[RpcFilterType],
+    ) -> Result<Vec<(Pubkey, Account)>> {
+        let client = self.manager.get_client_for(&self.name)?;
+        let result = RpcBackend::get_program_accounts(&client, program_id, filters).await;
+        self.manager.record_outcome(&client.url(), &result);
+        result
+    }
+
+    async fn get_slot(&self) -> Result<u64> {
+        let client = self.manager.get_client_for(&self.name)?;
+        let result = RpcBackend::get_slot(&client).await;
+        self.manager.record_outcome(&client.url(), &result);
+        result
+    }
+
+    async fn send_transaction(&self, transaction: &VersionedTransaction) -> Result<Signature> {
+        let client = self.manager.get_client_for(&self.name)?;
+        let result = RpcBackend::send_transaction(&client, transaction).await;
+        self.manager.record_outcome(&client.url(), &result);
+        result
+    }
+}
+
+#[async_trait]
+impl<T: RpcBackend + ?Sized> RpcBackend for Arc<T> {
+    async fn get_multiple_accounts(&self, pubkeys: &[Pubkey]) -> Result<Vec<Option<Account>>> {
+        (**self).get_multiple_accounts(pubkeys).await
+    }
+
+    async fn get_multiple_accounts_with_slot(
+        &self,
+        pubkeys: &[Pubkey],
+        min_context_slot: Option<u64>,
+    ) -> Result<(u64, Vec<Option<Account>>)> {
+        (**self).get_multiple_accounts_with_slot(pubkeys, min_context_slot).await
+    }
+
+    async fn get_program_accounts(
+        &self,
+        program_id: &Pubkey,
+        filters: &[RpcFilterType],
+    ) -> Result<Vec<(Pubkey, Account)>> {
+        (**self).get_program_accounts(program_id, filters).await
+    }
+
+    async fn get_slot(&self) -> Result<u64> {
+        (**self).get_slot().await
+    }
+
+    async fn send_transaction(&self, transaction: &VersionedTransaction) -> Result<Signature> {
+        (**self).send_transaction(transaction).await
+    }
+
+    async fn get_account(&self, pubkey: &Pubkey) -> Result<Account> {
+        (**self).get_account(pubkey).await
+    }
+}
+
+/// In-memory fake backend for tests and offline simulation
+#[derive(Default)]
+pub struct InMemoryBackend {
+    accounts: RwLock<HashMap<Pubkey, Account>>,
+    slot: AtomicU64,
+    slot_step: AtomicU64,
+    sent: Mutex<Vec<VersionedTransaction>>,
+    account_requests: AtomicU64,
+}
+
+impl InMemoryBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Insert or replace an account
+    pub fn set_account(&self, pubkey: Pubkey, account: Account) {
+        self.accounts.write().insert(pubkey, account);
+    }
+
+    pub fn remove_account(&self, pubkey: &Pubkey) -> Option<Account> {
+        self.accounts.write().remove(pubkey)
+    }
+
+    pub fn set_slot(&self, slot: u64) {
+        self.slot.store(slot, Ordering::SeqCst);
+    }
+
+    /// Advance the slot by `step` after every slot-tagged read, like a live
+    /// chain moving on between requests
+    pub fn set_slot_step(&self, step: u64) {
+        self.slot_step.store(step, Ordering::SeqCst);
+    }
+
+    /// Number of batch account fetches served so far
+    pub fn account_requests(&self) -> u64 {
+        self.account_requests.load(Ordering::SeqCst)
+    }
+
+    /// Transactions submitted through `send_transaction`, in order
+    pub fn sent_transactions(&self) -> Vec<VersionedTransaction> {
+        self.sent.lock().clone()
+    }
+}
+
+#[async_trait]
+impl RpcBackend for InMemoryBackend {
+    async fn get_multiple_accounts(&self, pubkeys: &[Pubkey]) -> Result<Vec<Option<Account>>> {
+        self.account_requests.fetch_add(1, Ordering::SeqCst);
+        let accounts = self.accounts.read();
+        Ok(pubkeys.iter().map(|key| accounts.get(key).cloned()).collect())
+    }
+
+    async fn get_multiple_accounts_with_slot(
+        &self,
+        pubkeys: &[Pubkey],
+        min_context_slot: Option<u64>,
+    ) -> Result<(u64, Vec<Option<Account>>)> {
+        let slot = self.slot.fetch_add(self.slot_step.load(Ordering::SeqCst), Ordering::SeqCst);
+        if let Some(min_context_slot) = min_context_slot.filter(|min| *min > slot) {
+            anyhow::bail!("Minimum context slot {} has not been reached (at {})", min_context_slot, slot);
+        }
+        Ok((slot, self.get_multiple_accounts(pubkeys).await?))
+    }
+
+    /// Applies filters the way the validator does; results are sorted by address
+    async fn get_program_accounts(
+        &self,
+        program_id: &Pubkey,
+        filters: &[RpcFilterType],
+    ) -> Result<Vec<(Pubkey, Account)>> {
+        let accounts = self.accounts.read();
+        let mut matched: Vec<(Pubkey, Account)> = accounts
+            .iter()
+            .filter(|(_, account)| account.owner == *program_id)
+            .filter(|(_, account)| {
+                let shared = AccountSharedData::from((*account).clone());
+                filters.iter().all(|filter| filter.allows(&shared))
+            })
+            .map(|(key, account)| (*key, account.clone()))
+            .collect();
+        matched.sort_by_key(|(key, _)| *key);
+        Ok(matched)
+    }
+
+    async fn get_slot(&self) -> Result<u64> {
+        Ok(self.slot.load(Ordering::SeqCst))
+    }
+
+    async fn send_transaction(&self, transaction: &VersionedTransaction) -> Result<Signature> {
+        let signature = transaction.signatures.first().copied().unwrap_or_default();
+        self.sent.lock().push(transaction.clone());
+        Ok(signature)
+    }
+}
@@ -0,0 +1,234 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::RpcManager;
+
+/// Priority class of an RPC consumer. Lower classes are shed first when the
+/// endpoint pool is under pressure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    /// Opportunistic work such as pool scanning
+    Background,
+    Normal,
+    /// Latency-sensitive work such as transaction execution
+    Critical,
+}
+
+impl Priority {
+    /// Fraction of total capacity this class may fill once it has used up
+    /// its own reservation
+    fn borrow_ceiling(self) -> f64 {
+        match self {
+            Priority::Background => 0.5,
+            Priority::Normal => 0.8,
+            Priority::Critical => 1.0,
+        }
+    }
+}
+
+/// Per-consumer usage in the current one-second window
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConsumerUsage {
+    pub name: String,
+    pub priority: Priority,
+    pub reserved: u32,
+    pub used: u32,
+}
+
+struct ConsumerBudget {
+    reserved_share: f64,
+    priority: Priority,
+    used: u32,
+}
+
+impl ConsumerBudget {
+    fn reserved(&self, capacity: u32) -> u32 {
+        (capacity as f64 * self.reserved_share).floor() as u32
+    }
+}
+
+/// Tracks request budgets for named consumers over a one-second window.
+///
+/// Each consumer is always admitted up to its reserved share of capacity.
+/// Beyond that it borrows from the unreserved remainder, but only while
+/// total usage stays under its priority class's ceiling and without eating
+/// into reservations other consumers have not used yet.
+pub(crate) struct BudgetTracker {
+    consumers: HashMap<String, ConsumerBudget>,
+    total_used: u32,
+    last_reset: Instant,
+}
+
+impl BudgetTracker {
+    pub(crate) fn new() -> Self {
+        Self {
+            consumers: HashMap::new(),
+            total_used: 0,
+            last_reset: Instant::now(),
+        }
+    }
+
+    pub(crate) fn register(&mut self, name: &str, reserved_share: f64, priority: Priority) -> Result<()> {
+        if !(0.0..=1.0).contains(&reserved_share) {
+            anyhow::bail!("Reserved share for consumer {} must be between 0 and 1", name);
+        }
+
+        let reserved_elsewhere: f64 = self
+            .consumers
+            .iter()
+            .filter(|(other, _)| other.as_str() != name)
+            .map(|(_, c)| c.reserved_share)
+            .sum();
+        if reserved_elsewhere + reserved_share > 1.0 {
+            anyhow::bail!(
+                "Cannot reserve {:.0}% for consumer {}: {:.0}% already reserved",
+                reserved_share * 100.0,
+                name,
+                reserved_elsewhere * 100.0
+            );
+        }
+
+        let consumer = self.consumers.entry(name.to_string()).or_insert(ConsumerBudget {
+            reserved_share,
+            priority,
+            used: 0,
+        });
+        consumer.reserved_share = reserved_share;
+        consumer.priority = priority;
+        Ok(())
+    }
+
+    fn roll_window(&mut self) {
+        if self.last_reset.elapsed() > Duration::from_secs(1) {
+            for consumer in self.consumers.values_mut() {
+                consumer.used = 0;
+            }
+            self.total_used = 0;
+            self.last_reset = Instant::now();
+        }
+    }
+
+    /// Admit one request for `consumer` against `capacity` requests per second.
+    /// Anonymous requests (`None`) have no reservation and critical priority.
+    pub(crate) fn try_acquire(&mut self, consumer: Option<&str>, capacity: u32) -> Result<()> {
+        self.roll_window();
+
+        let (reserved, used, priority) = match consumer {
+            Some(name) => {
+                let budget = self
+                    .consumers
+                    .get(name)
+                    .ok_or_else(|| anyhow::anyhow!("Unknown RPC consumer {}", name))?;
+                (budget.reserved(capacity), budget.used, budget.priority)
+            }
+            None => (0, 0, Priority::Critical),
+        };
+
+        if used >= reserved {
+            let protected: u32 = self
+                .consumers
+                .iter()
+                .filter(|(other, _)| Some(other.as_str()) != consumer)
+                .map(|(_, c)| c.reserved(capacity).saturating_sub(c.used))
+                .sum();
+            let ceiling = (capacity as f64 * priority.borrow_ceiling()).floor() as u32;
+            let limit = ceiling.min(capacity.saturating_sub(protected));
+
+            if self.total_used >= limit {
+                anyhow::bail!(
+                    "RPC budget exhausted for consumer {} ({:?} priority)",
+                    consumer.unwrap_or("<default>"),
+                    priority
+                );
+            }
+        }
+
+        self.total_used += 1;
+        if let Some(budget) = consumer.and_then(|name| self.consumers.get_mut(name)) {
+            budget.used += 1;
+        }
+        Ok(())
+    }
+
+    /// Return a request slot that was acquired but never used
+    pub(crate) fn release(&mut self, consumer: Option<&str>) {
+        self.total_used = self.total_used.saturating_sub(1);
+        if let Some(budget) = consumer.and_then(|name| self.consumers.get_mut(name)) {
+            budget.used = budget.used.saturating_sub(1);
+        }
+    }
+
+    pub(crate) fn usage(&self, capacity: u32) -> Vec<ConsumerUsage> {
+        self.consumers
+            .iter()
+            .map(|(name, c)| ConsumerUsage {
+                name: name.clone(),
+                priority: c.priority,
+                reserved: c.reserved(capacity),
+                used: c.used,
+            })
+            .collect()
+    }
+}
+
+/// Handle that issues every request on behalf of a named consumer.
+/// Implements `RpcBackend`, so it can be handed to any consumer crate.
+#[derive(Clone)]
+pub struct RpcConsumer {
+    pub(crate) manager: Arc<RpcManager>,
+    pub(crate) name: String,
+}
+
+impl RpcConsumer {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reservation_is_guaranteed() {
+        let mut budgets = BudgetTracker::new();
+        budgets.register("scanner", 0.2, Priority::Background).unwrap();
+        budgets.register("executor", 0.3, Priority::Critical).unwrap();
+
+        // Scanner borrows up to its 50% ceiling, but the executor's unused
+        // reservation stays protected
+        for _ in 0..50 {
+            budgets.try_acquire(Some("scanner"), 100).unwrap();
+        }
+        assert!(budgets.try_acquire(Some("scanner"), 100).is_err());
+
+        for _ in 0..30 {
+            budgets.try_acquire(Some("executor"), 100).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_low_priority_shed_first() {
+        let mut budgets = BudgetTracker::new();
+        budgets.register("scanner", 0.0, Priority::Background).unwrap();
+        budgets.register("monitor", 0.0, Priority::Normal).unwrap();
+
+        for _ in 0..50 {
+            budgets.try_acquire(Some("monitor"), 100).unwrap();
+        }
+        assert!(budgets.try_acquire(Some("scanner"), 100).is_err());
+        assert!(budgets.try_acquire(Some("monitor"), 100).is_ok());
+        assert!(budgets.try_acquire(None, 100).is_ok());
+    }
+
+    #[test]
+    fn test_over_reservation_rejected() {
+        let mut budgets = BudgetTracker::new();
+        budgets.register("a", 0.7, Priority::Normal).unwrap();
+        assert!(budgets.register("b", 0.4, Priority::Normal).is_err());
+        assert!(budgets.register("a", 0.9, Priority::Normal).is_ok());
+        assert!(budgets.try_acquire(Some("unknown"), 100).is_err());
+    }
+}
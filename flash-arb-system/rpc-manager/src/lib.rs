@@ -5,6 +5,16 @@ use std::time::{Duration, Instant};
 use anyhow::Result;
 use tracing::{info, warn};
 
+pub mod backend;
+pub mod budget;
+pub mod prober;
+
+pub use backend::{InMemoryBackend, RpcBackend};
+pub use budget::{ConsumerUsage, Priority, RpcConsumer};
+pub use prober::{ProbeConfig, ProbeResult};
+
+use budget::BudgetTracker;
+
 const MAX_REQUESTS_PER_SECOND: u32 = 50; // Per endpoint
 const FALLBACK_ENDPOINTS: &[&str] = &[
     "https://api.devnet.solana.com",
@@ -18,6 +28,9 @@ pub struct EndpointHealth {
     pub last_reset: Instant,
     pub consecutive_failures: u32,
     pub is_healthy: bool,
+    /// Most recent slot reported by the endpoint
+    pub last_slot: Option<u64>,
+    pub last_probe: Option<ProbeResult>,
 }
 
 impl EndpointHealth {
@@ -28,6 +41,8 @@ impl EndpointHealth {
             last_reset: Instant::now(),
             consecutive_failures: 0,
             is_healthy: true,
+            last_slot: None,
+            last_probe: None,
         }
     }
 
@@ -67,6 +82,7 @@ impl EndpointHealth {
 pub struct RpcManager {
     endpoints: Arc<RwLock<Vec<EndpointHealth>>>,
     current_index: Arc<RwLock<usize>>,
+    budgets: Arc<RwLock<BudgetTracker>>,
 }
 
 impl RpcManager {
@@ -89,11 +105,59 @@ impl RpcManager {
         Self {
             endpoints: Arc::new(RwLock::new(endpoints)),
             current_index: Arc::new(RwLock::new(0)),
+            budgets: Arc::new(RwLock::new(BudgetTracker::new())),
         }
     }
 
-    /// Get next available RPC client (round-robin with health checks)
+    /// Register a named consumer with a reserved share of total capacity
+    /// (0.0 - 1.0) and a priority class used for shedding under pressure
+    pub fn register_consumer(&self, name: &str, reserved_share: f64, priority: Priority) -> Result<()> {
+        self.budgets.write().register(name, reserved_share, priority)?;
+        info!(
+            "Registered RPC consumer {} ({:?}, {:.0}% reserved)",
+            name,
+            priority,
+            reserved_share * 100.0
+        );
+        Ok(())
+    }
+
+    /// Handle that issues every request on behalf of `name`
+    pub fn consumer(self: &Arc<Self>, name: &str) -> RpcConsumer {
+        RpcConsumer {
+            manager: Arc::clone(self),
+            name: name.to_string(),
+        }
+    }
+
+    /// Get next available RPC client outside of any consumer budget
     pub fn get_client(&self) -> Result<RpcClient> {
+        self.acquire(None)
+    }
+
+    /// Get next available RPC client, charged to a registered consumer
+    pub fn get_client_for(&self, consumer: &str) -> Result<RpcClient> {
+        self.acquire(Some(consumer))
+    }
+
+    /// Requests per second across all healthy endpoints
+    fn capacity(&self) -> u32 {
+        self.endpoints.read().iter().filter(|e| e.is_healthy).count() as u32 * MAX_REQUESTS_PER_SECOND
+    }
+
+    fn acquire(&self, consumer: Option<&str>) -> Result<RpcClient> {
+        let capacity = self.capacity();
+        self.budgets.write().try_acquire(consumer, capacity)?;
+
+        let client = self.next_client();
+        if client.is_err() {
+            self.budgets.write().release(consumer);
+        }
+        client
+    }
+
+    /// Next RPC client in round-robin order, skipping unhealthy or throttled endpoints
+    fn next_client(&self) -> Result<RpcClient> {
         let endpoints = self.endpoints.read();
         let mut current_idx = self.current_index.write();
 
@@ -146,6 +210,14 @@ impl RpcManager {
         }
     }
 
+    /// Report the outcome of a request made through `endpoint_url`
+    pub fn record_outcome<T>(&self, endpoint_url: &str, result: &Result<T>) {
+        match result {
+            Ok(_) => self.record_success(endpoint_url),
+            Err(_) => self.record_failure(endpoint_url),
+        }
+    }
+
     /// Get health status of all endpoints
     pub fn health_status(&self) -> Vec<(String, bool, u32)> {
         self.endpoints
@@ -154,6 +226,29 @@ impl RpcManager {
             .map(|e| (e.url.clone(), e.is_healthy, e.request_count))
             .collect()
     }
+
+    /// Result of the last active probe of an endpoint, if the prober is running
+    pub fn last_probe(&self, endpoint_url: &str) -> Option<ProbeResult> {
+        self.endpoints
+            .read()
+            .iter()
+            .find(|e| e.url == endpoint_url)
+            .and_then(|e| e.last_probe.clone())
+    }
+
+    /// How many slots an endpoint is behind the most advanced endpoint
+    pub fn slot_lag(&self, endpoint_url: &str) -> Option<u64> {
+        let endpoints = self.endpoints.read();
+        let best = endpoints.iter().filter_map(|e| e.last_slot).max()?;
+        let slot = endpoints.iter().find(|e| e.url == endpoint_url)?.last_slot?;
+        Some(best.saturating_sub(slot))
+    }
+
+    /// Budget usage of every registered consumer in the current window
+    pub fn consumer_usage(&self) -> Vec<ConsumerUsage> {
+        let capacity = self.capacity();
+        self.budgets.read().usage(capacity)
+    }
 }
 
 #[cfg(test)]
@@ -174,4 +269,53 @@ mod tests {
         }
         assert!(health.should_throttle());
     }
+
+    #[test]
+    fn test_probe_results_feed_health() {
+        let manager = RpcManager::new(vec![]);
+        let urls: Vec<String> = FALLBACK_ENDPOINTS.iter().map(|u| u.to_string()).collect();
+        let probe = |slot: Option<u64>| ProbeResult {
+            probed_at: Instant::now(),
+            latency: Duration::from_millis(10),
+            responded: slot.is_some(),
+            slot,
+            slot_lag: None,
+            error: None,
+        };
+
+        for _ in 0..5 {
+            manager.apply_probe_results(
+                vec![(urls[0].clone(), probe(Some(1_000))), (urls[1].clone(), probe(Some(900)))],
+                50,
+            );
+        }
+
+        assert_eq!(manager.slot_lag(&urls[1]), Some(100));
+        assert_eq!(manager.last_probe(&urls[1]).unwrap().slot_lag, Some(100));
+        let health = manager.health_status();
+        assert!(health[0].1);
+        assert!(!health[1].1); // Lagging endpoint trips the breaker
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_backend() {
+        use solana_sdk::account::Account;
+        use solana_sdk::pubkey::Pubkey;
+
+        let backend = InMemoryBackend::new();
+        let present = Pubkey::new_unique();
+        let missing = Pubkey::new_unique();
+        backend.set_account(present, Account { lamports: 42, ..Account::default() });
+        backend.set_slot(1_000);
+
+        let accounts = backend.get_multiple_accounts(&[present, missing]).await.unwrap();
+        assert_eq!(accounts[0].as_ref().map(|a| a.lamports), Some(42));
+        assert!(accounts[1].is_none());
+        assert!(backend.get_account(&missing).await.is_err());
+        assert_eq!(backend.get_slot().await.unwrap(), 1_000);
+
+        let (slot, accounts) = backend.get_multiple_accounts_with_slot(&[present], Some(1_000)).await.unwrap();
+        assert_eq!((slot, accounts.len()), (1_000, 1));
+        assert!(backend.get_multiple_accounts_with_slot(&[present], Some(1_001)).await.is_err());
+    }
 }
@@ -0,0 +1,147 @@
+use solana_client::nonblocking::rpc_client::RpcClient;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::task::{JoinHandle, JoinSet};
+use tracing::{debug, warn};
+
+use crate::RpcManager;
+
+/// Settings for the background health prober
+#[derive(Debug, Clone)]
+pub struct ProbeConfig {
+    /// Time between probe rounds
+    pub interval: Duration,
+    /// Per-request timeout for `getHealth` / `getSlot`
+    pub timeout: Duration,
+    /// Endpoints further than this many slots behind the best observed slot
+    /// are counted as failing
+    pub max_slot_lag: u64,
+}
+
+impl Default for ProbeConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(5),
+            timeout: Duration::from_secs(2),
+            max_slot_lag: 50,
+        }
+    }
+}
+
+/// Outcome of the most recent active probe of an endpoint
+#[derive(Debug, Clone)]
+pub struct ProbeResult {
+    pub probed_at: Instant,
+    pub latency: Duration,
+    /// Whether `getHealth` and `getSlot` both succeeded
+    pub responded: bool,
+    pub slot: Option<u64>,
+    /// Slots behind the highest slot seen in the same probe round
+    pub slot_lag: Option<u64>,
+    pub error: Option<String>,
+}
+
+impl ProbeResult {
+    /// Responded and within the allowed slot lag
+    pub fn is_healthy(&self, max_slot_lag: u64) -> bool {
+        self.responded && self.slot_lag.is_some_and(|lag| lag <= max_slot_lag)
+    }
+}
+
+async fn probe_endpoint(url: String, timeout: Duration) -> ProbeResult {
+    let client = RpcClient::new_with_timeout(url, timeout);
+    let started = Instant::now();
+
+    let outcome = match client.get_health().await {
+        Ok(()) => client.get_slot().await,
+        Err(e) => Err(e),
+    };
+
+    let (slot, error) = match outcome {
+        Ok(slot) => (Some(slot), None),
+        Err(e) => (None, Some(e.to_string())),
+    };
+
+    ProbeResult {
+        probed_at: started,
+        latency: started.elapsed(),
+        responded: slot.is_some(),
+        slot,
+        slot_lag: None,
+        error,
+    }
+}
+
+impl RpcManager {
+    /// Spawn a background task that probes every endpoint with `getHealth`
+    /// and `getSlot` each `config.interval`, feeding the results into the
+    /// endpoint health and slot tracking. Abort the handle to stop probing.
+    pub fn spawn_health_prober(self: &Arc<Self>, config: ProbeConfig) -> JoinHandle<()> {
+        let manager = Arc::clone(self);
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(config.interval);
+            loop {
+                ticker.tick().await;
+                let results = manager.probe_all(config.timeout).await;
+                manager.apply_probe_results(results, config.max_slot_lag);
+            }
+        })
+    }
+
+    async fn probe_all(&self, timeout: Duration) -> Vec<(String, ProbeResult)> {
+        let urls: Vec<String> = self.endpoints.read().iter().map(|e| e.url.clone()).collect();
+
+        let mut probes = JoinSet::new();
+        for url in urls {
+            probes.spawn(async move {
+                let result = probe_endpoint(url.clone(), timeout).await;
+                (url, result)
+            });
+        }
+
+        let mut results = Vec::new();
+        while let Some(joined) = probes.join_next().await {
+            match joined {
+                Ok(result) => results.push(result),
+                Err(e) => warn!("Health probe task failed: {}", e),
+            }
+        }
+        results
+    }
+
+    /// Record a round of probe results: compute slot lag against the best
+    /// slot in the round and update each endpoint's health accordingly
+    pub(crate) fn apply_probe_results(&self, mut results: Vec<(String, ProbeResult)>, max_slot_lag: u64) {
+        let best_slot = results.iter().filter_map(|(_, r)| r.slot).max();
+        for (_, result) in results.iter_mut() {
+            result.slot_lag = result.slot.zip(best_slot).map(|(slot, best)| best.saturating_sub(slot));
+        }
+
+        let mut endpoints = self.endpoints.write();
+        for (url, result) in results {
+            let Some(endpoint) = endpoints.iter_mut().find(|e| e.url == url) else {
+                continue;
+            };
+
+            // Each probe issues two requests against the endpoint's rate limit
+            endpoint.record_request();
+            endpoint.record_request();
+
+            if let Some(slot) = result.slot {
+                endpoint.last_slot = Some(slot);
+            }
+
+            if result.is_healthy(max_slot_lag) {
+                endpoint.record_success();
+            } else {
+                debug!(
+                    "Probe failed for {}: slot lag {:?}, error {:?}",
+                    url, result.slot_lag, result.error
+                );
+                endpoint.record_failure();
+            }
+            endpoint.last_probe = Some(result);
+        }
+    }
+}
@@ -1,11 +1,11 @@
 use anyhow::Result;
 use tracing::{info, warn, error};
-use tokio::time::{sleep, Duration, Instant};
+use tokio::time::{sleep, Duration};
 use std::collections::HashMap;
 use std::sync::Arc;
 use solana_sdk::pubkey::Pubkey;
-use rpc_manager::RpcManager;
-use price_fetcher::{RaydiumClient, OrcaClient};
+use rpc_manager::{RpcBackend, RpcManager};
+use price_fetcher::{Price, QuoterDispatcher};
 use serde::Serialize;
 
 mod broadcast;
@@ -16,8 +16,8 @@ use crate::broadcast::broadcast_opportunity_to_ai_link;
 pub struct ArbitrageOpportunity {
     pub token_a: Pubkey,
     pub token_b: Pubkey,
-    pub dex_a_price: f64,
-    pub dex_b_price: f64,
+    pub dex_a_price: Price,
+    pub dex_b_price: Price,
     pub spread_bps: u64,  // Basis points (1 bps = 0.01%)
     pub dex_a_name: String,
     pub dex_b_name: String,
@@ -35,22 +35,22 @@ impl ArbitrageOpportunity {
 }
 
 /// High-frequency scanner bot for price discovery
-pub struct ScannerBot {
-    rpc_manager: Arc<RpcManager>,
+///
+/// Generic over the RPC backend so tests can drive it with an in-memory fake.
+pub struct ScannerBot<B: RpcBackend = RpcManager> {
+    rpc: Arc<B>,
     min_profit_bps: u64,
     scan_interval: Duration,
-    raydium_client: RaydiumClient,
-    orca_client: OrcaClient,
+    quoters: QuoterDispatcher,
 }
 
-impl ScannerBot {
-    pub fn new(rpc_manager: RpcManager, min_profit_bps: u64, scan_interval_ms: u64) -> Self {
+impl<B: RpcBackend> ScannerBot<B> {
+    pub fn new(rpc: B, min_profit_bps: u64, scan_interval_ms: u64) -> Self {
         Self {
-            rpc_manager: Arc::new(rpc_manager),
+            rpc: Arc::new(rpc),
             min_profit_bps,
             scan_interval: Duration::from_millis(scan_interval_ms),
-            raydium_client: RaydiumClient::new(),
-            orca_client: OrcaClient::new(),
+            quoters: QuoterDispatcher::new(),
         }
     }
 
@@ -99,9 +99,6 @@ impl ScannerBot {
             0xcf, 0x36, 0xca, 0x8a, 0x24, 0x93, 0xbc, 0x86, 
             0x46, 0x09, 0xb8, 0x7c, 0xc1, 0x71, 0x19, 0x7d
         ]); 
-        // Example base/quote vaults for simulation - in prod use constants
-        let ray_base = Pubkey::new_unique(); 
-        let ray_quote = Pubkey::new_unique();
 
         // Orca Whirlpool SOL/USDC
         let orca_sol_usdc = Pubkey::new_from_array([
@@ -111,33 +108,32 @@ impl ScannerBot {
             0x29, 0x9e, 0x67, 0xa3, 0x5c, 0xe6, 0x6b, 0x85
         ]); 
 
-        // Get RPC client from manager
-        let rpc = self.rpc_manager.get_client()?;
+        let rpc = self.rpc.as_ref();
 
         // Fetch prices in parallel
         let (ray_price, orca_price) = tokio::join!(
-            self.raydium_client.get_pool_price(&rpc, &ray_base, &ray_quote),
-            self.orca_client.get_whirlpool_price(&rpc, &orca_sol_usdc)
+            self.quoters.fetch_price(rpc, &raydium_sol_usdc),
+            self.quoters.fetch_price(rpc, &orca_sol_usdc)
         );
 
         // Handle errors gracefully (log and continue)
         let ray_price = match ray_price {
-            Ok(p) => p,
+            Ok(p) => p.ui,
             Err(e) => {
                 // warn!("Raydium fetch failed: {}", e); 
-                245.50 // Fallback for devnet test without real liquidity
+                Price::from_ratio(24_550, 100)? // Fallback for devnet test without real liquidity
             }
         };
         
         let orca_price = match orca_price {
-            Ok(p) => p,
+            Ok(p) => p.ui,
             Err(e) => {
                 // warn!("Orca fetch failed: {}", e);
-                245.85 // Fallback for devnet test
+                Price::from_ratio(24_585, 100)? // Fallback for devnet test
             }
         };
 
-        let spread_bps = ((orca_price - ray_price).abs() / ray_price.min(orca_price) * 10000.0) as u64;
+        let spread_bps = ray_price.spread_bps(orca_price)?;
 
         if spread_bps >= self.min_profit_bps {
             opportunities.push(ArbitrageOpportunity {
@@ -174,12 +170,12 @@ mod tests {
         let opp = ArbitrageOpportunity {
             token_a: Pubkey::new_unique(),
             token_b: Pubkey::new_unique(),
-            dex_a_price: 100.0,
-            dex_b_price: 100.10,
+            dex_a_price: Price::from_integer(100),
+            dex_b_price: Price::from_ratio(10_010, 100).unwrap(),
             spread_bps: 10, // 0.10%
             dex_a_name: "DEX A".to_string(),
             dex_b_name: "DEX B".to_string(),
-            timestamp: Instant::now(),
+            timestamp: 1_700_000_000,
         };
 
         assert_eq!(opp.profit_bps(), 10);
@@ -1,5 +1,6 @@
 use scanner_bot::ScannerBot;
-use rpc_manager::RpcManager;
+use rpc_manager::{Priority, ProbeConfig, RpcManager};
+use std::sync::Arc;
 use anyhow::Result;
 use tracing_subscriber;
 
@@ -31,11 +32,27 @@ async fn main() -> Result<()> {
         .unwrap_or_else(|_| "1500".to_string())
         .parse()?;
 
+    // Share of RPC capacity reserved for scanning; anything beyond it is
+    // borrowed at background priority and shed first under pressure
+    let scanner_rpc_share: f64 = std::env::var("SCANNER_RPC_SHARE")
+        .unwrap_or_else(|_| "0.25".to_string())
+        .parse()?;
+
     // Initialize RPC manager
-    let rpc_manager = RpcManager::new(helius_keys);
+    let rpc_manager = Arc::new(RpcManager::new(helius_keys));
+    rpc_manager.register_consumer("scanner", scanner_rpc_share, Priority::Background)?;
+
+    // Optional active health probing (disabled unless an interval is set)
+    if let Ok(interval) = std::env::var("RPC_PROBE_INTERVAL_MS") {
+        let probe_config = ProbeConfig {
+            interval: std::time::Duration::from_millis(interval.parse()?),
+            ..ProbeConfig::default()
+        };
+        rpc_manager.spawn_health_prober(probe_config);
+    }
 
     // Create and run scanner bot
-    let scanner = ScannerBot::new(rpc_manager, min_profit_bps, scan_interval_ms);
+    let scanner = ScannerBot::new(rpc_manager.consumer("scanner"), min_profit_bps, scan_interval_ms);
     
     println!("🚀 Flash Arbitrage Scanner Bot v0.1.0");
     println!("   Min Profit: {} bps ({}%)", min_profit_bps, min_profit_bps as f64 / 100.0);